@@ -0,0 +1,306 @@
+//! An opened Tun/Tap device driven by the async-io reactor
+
+use crate::Device;
+use async_io::{Async, IoSafe};
+use futures_io::{AsyncRead, AsyncWrite};
+use std::io::{Error, ErrorKind, IoSlice, IoSliceMut, Result};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+// Reading and writing a device never closes or replaces its descriptor.
+unsafe impl IoSafe for Device {}
+
+/**
+A [`Device`] registered in the [async-io] reactor, implementing
+[`AsyncRead`] and [`AsyncWrite`] from `futures-io`.
+
+It works with any executor, e.g. async-std or smol, and on every Unix
+target since the reactor only needs a pollable descriptor. One packet is
+read or written per call, as with [`Device`].
+
+This type is only available with the `async-io` feature.
+
+[`Device`]: struct.Device.html
+[async-io]: https://docs.rs/async-io
+[`AsyncRead`]: https://docs.rs/futures-io/latest/futures_io/trait.AsyncRead.html
+[`AsyncWrite`]: https://docs.rs/futures-io/latest/futures_io/trait.AsyncWrite.html
+
+# Examples
+
+```no_run
+use futures_lite::AsyncReadExt;
+use utuntap::tun::OpenOptions;
+
+async_io::block_on(async {
+    let mut device = OpenOptions::new().open_async(0).unwrap();
+    let mut buffer = [0; 1500];
+    let number = device.read(&mut buffer).await.unwrap();
+    println!("{} read {} bytes", device.name(), number);
+});
+```
+*/
+#[derive(Debug)]
+pub struct AsyncDevice {
+    inner: Async<Device>,
+}
+
+impl AsyncDevice {
+    /**
+    Registers `device` in the reactor, putting it in non-blocking mode.
+    */
+    pub fn new(device: Device) -> Result<Self> {
+        Ok(Self {
+            inner: Async::new(device)?,
+        })
+    }
+
+    /// Returns the name of the interface, e.g. `tun0`.
+    pub fn name(&self) -> &str {
+        self.inner.get_ref().name()
+    }
+
+    /// Returns the underlying device.
+    pub fn get_ref(&self) -> &Device {
+        self.inner.get_ref()
+    }
+
+    /// Deregisters the device from the reactor and returns it, still in
+    /// non-blocking mode.
+    pub fn into_inner(self) -> Result<Device> {
+        self.inner.into_inner()
+    }
+
+    /**
+    Splits the device into a half which reads and a half which writes, each
+    owning a duplicate of the descriptor, e.g. to move them into two tasks.
+
+    The halves are registered in the reactor on their own and may be
+    dropped independently: the interface goes away once both are dropped,
+    as with any other duplicated descriptor.
+
+    # Examples
+
+    ```no_run
+    use futures_lite::{AsyncReadExt, AsyncWriteExt};
+    use utuntap::tun::OpenOptions;
+
+    async_io::block_on(async {
+        let device = OpenOptions::new().open_async(0).unwrap();
+        let (mut reader, mut writer) = device.split().unwrap();
+        let mut buffer = [0; 1500];
+        let number = reader.read(&mut buffer).await.unwrap();
+        writer.write(&buffer[..number]).await.unwrap();
+    });
+    ```
+    */
+    pub fn split(self) -> Result<(AsyncReadHalf, AsyncWriteHalf)> {
+        let device = self.inner.into_inner()?;
+        let writer = device.try_clone()?;
+        Ok((
+            AsyncReadHalf {
+                inner: Async::new(device)?,
+            },
+            AsyncWriteHalf {
+                inner: Async::new(writer)?,
+            },
+        ))
+    }
+
+    /**
+    Writes `packets` from index `*written` on, one packet per write,
+    waiting for the device to become writable whenever it would block.
+
+    `*written` is advanced past each packet as soon as the device takes it,
+    so it tells how far the batch got when the future returns an error or is
+    dropped before completing. Packets are never split: the device takes a
+    whole packet or nothing.
+
+    # Cancel safety
+
+    This method is cancel safe. Dropping the future loses no packet and
+    writes none twice; awaiting the method again with the same `written`
+    resumes with the first packet not yet written.
+
+    # Errors
+
+    The first error other than [`WouldBlock`] is returned, with `*written`
+    pointing at the packet which failed. A device taking only part of a
+    packet fails with [`WriteZero`].
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    async_io::block_on(async {
+        let mut device = OpenOptions::new().open_async(0).unwrap();
+        let queue: Vec<Vec<u8>> = Vec::new();
+        let mut written = 0;
+        device.write_all_packets(&queue, &mut written).await.unwrap();
+    });
+    ```
+
+    [`WouldBlock`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.WouldBlock
+    [`WriteZero`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.WriteZero
+    */
+    pub async fn write_all_packets<P: AsRef<[u8]>>(
+        &mut self,
+        packets: &[P],
+        written: &mut usize,
+    ) -> Result<()> {
+        while let Some(packet) = packets.get(*written) {
+            let packet = packet.as_ref();
+            let number =
+                std::future::poll_fn(|cx| Pin::new(&mut self.inner).poll_write(cx, packet)).await?;
+            if number != packet.len() {
+                return Err(Error::new(
+                    ErrorKind::WriteZero,
+                    "the device took only part of the packet",
+                ));
+            }
+            *written += 1;
+        }
+        Ok(())
+    }
+}
+
+impl AsyncRead for AsyncDevice {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<Result<usize>> {
+        Pin::new(&mut self.inner).poll_read_vectored(cx, bufs)
+    }
+}
+
+impl AsyncWrite for AsyncDevice {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+/**
+The reading half of an [`AsyncDevice`], returned by [`AsyncDevice::split`].
+
+This type is only available with the `async-io` feature.
+
+[`AsyncDevice`]: struct.AsyncDevice.html
+[`AsyncDevice::split`]: struct.AsyncDevice.html#method.split
+*/
+#[derive(Debug)]
+pub struct AsyncReadHalf {
+    inner: Async<Device>,
+}
+
+impl AsyncReadHalf {
+    /// Returns the name of the interface, e.g. `tun0`.
+    pub fn name(&self) -> &str {
+        self.inner.get_ref().name()
+    }
+}
+
+impl AsyncRead for AsyncReadHalf {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<Result<usize>> {
+        Pin::new(&mut self.inner).poll_read_vectored(cx, bufs)
+    }
+}
+
+/**
+The writing half of an [`AsyncDevice`], returned by [`AsyncDevice::split`].
+
+This type is only available with the `async-io` feature.
+
+[`AsyncDevice`]: struct.AsyncDevice.html
+[`AsyncDevice::split`]: struct.AsyncDevice.html#method.split
+*/
+#[derive(Debug)]
+pub struct AsyncWriteHalf {
+    inner: Async<Device>,
+}
+
+impl AsyncWriteHalf {
+    /// Returns the name of the interface, e.g. `tun0`.
+    pub fn name(&self) -> &str {
+        self.inner.get_ref().name()
+    }
+}
+
+impl AsyncWrite for AsyncWriteHalf {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+impl AsRawFd for AsyncDevice {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl AsFd for AsyncDevice {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
+}
@@ -0,0 +1,113 @@
+//! The operations shared by every kind of device
+
+use crate::Device;
+use std::io::{Read, Result, Write};
+
+/**
+The core operations of a Tun/Tap device, for code generic over where its
+packets come from, e.g. a real [`Device`] in production and a
+[`MockDevice`] in tests.
+
+The trait is object safe, so devices of different kinds can be stored as
+`Box<dyn TunDevice>`. Frames are read and written whole, one per call: an
+IP packet on a Tun device, an Ethernet frame on a Tap device, with whatever
+header the device was opened with.
+
+[`Device`]: struct.Device.html
+[`MockDevice`]: struct.MockDevice.html
+
+# Examples
+
+```no_run
+use utuntap::{tun::OpenOptions, TunDevice};
+
+fn echo(device: &mut dyn TunDevice) -> std::io::Result<()> {
+    let mut buffer = vec![0; device.mtu()? as usize + 4];
+    let number = device.recv_frame(&mut buffer)?;
+    device.send_frame(&buffer[..number])?;
+    Ok(())
+}
+
+let mut device = OpenOptions::new().open_device(0).unwrap();
+device.set_up().unwrap();
+echo(&mut device).unwrap();
+```
+*/
+pub trait TunDevice {
+    /// Returns the name of the interface, e.g. `tun0`.
+    fn name(&self) -> &str;
+
+    /// Reads one frame into `buf`, returning its length. A frame longer
+    /// than `buf` is truncated.
+    fn recv_frame(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Writes `frame` whole, returning the number of bytes written.
+    fn send_frame(&mut self, frame: &[u8]) -> Result<usize>;
+
+    /// Returns the MTU of the interface.
+    fn mtu(&self) -> Result<u32>;
+
+    /// Brings the interface up.
+    fn set_up(&mut self) -> Result<()>;
+}
+
+/// The interface helpers fail with `Unsupported` on platforms without them,
+/// i.e. everywhere but Linux and macOS.
+impl TunDevice for Device {
+    fn name(&self) -> &str {
+        Device::name(self)
+    }
+
+    fn recv_frame(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.read(buf)
+    }
+
+    fn send_frame(&mut self, frame: &[u8]) -> Result<usize> {
+        self.write(frame)
+    }
+
+    fn mtu(&self) -> Result<u32> {
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        return Device::mtu(self);
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        return Err(unsupported());
+    }
+
+    fn set_up(&mut self) -> Result<()> {
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        return Device::set_up(self);
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        return Err(unsupported());
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn unsupported() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "interfaces can only be configured on Linux and macOS",
+    )
+}
+
+#[cfg(feature = "mock")]
+impl TunDevice for crate::MockDevice {
+    fn name(&self) -> &str {
+        crate::MockDevice::name(self)
+    }
+
+    fn recv_frame(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.read(buf)
+    }
+
+    fn send_frame(&mut self, frame: &[u8]) -> Result<usize> {
+        self.write(frame)
+    }
+
+    fn mtu(&self) -> Result<u32> {
+        crate::MockDevice::mtu(self)
+    }
+
+    fn set_up(&mut self) -> Result<()> {
+        crate::MockDevice::set_up(self)
+    }
+}
@@ -0,0 +1,128 @@
+//! A device over a descriptor owned by someone else
+
+use crate::device::mode_of;
+use crate::Mode;
+use std::io::{Error, Read, Result, Write};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+
+/**
+A Tun/Tap device over a borrowed file descriptor, together with the name of
+its interface.
+
+Unlike [`Device`], it never closes the descriptor: it is meant for hosts,
+e.g. a C program calling into Rust, which keep managing the lifetime of the
+descriptor and lend it to utuntap for a while. Only the helpers which do not
+change the device are offered, besides reading and writing packets.
+
+[`Device`]: struct.Device.html
+
+# Examples
+
+```no_run
+use std::io::Write;
+use std::os::unix::io::BorrowedFd;
+use utuntap::BorrowedDevice;
+
+// The descriptor 3 stays open for the whole call.
+let fd = unsafe { BorrowedFd::borrow_raw(3) };
+let mut device = BorrowedDevice::new(fd, "tun0".to_string());
+device.write(&[0; 20]).unwrap();
+```
+*/
+#[derive(Debug)]
+pub struct BorrowedDevice<'fd> {
+    fd: BorrowedFd<'fd>,
+    name: String,
+}
+
+impl<'fd> BorrowedDevice<'fd> {
+    /// Lends `fd`, a descriptor of a Tun/Tap device attached to the
+    /// interface `name`.
+    pub fn new(fd: BorrowedFd<'fd>, name: String) -> Self {
+        Self { fd, name }
+    }
+
+    /// Returns the name of the interface, e.g. `tun0`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /**
+    Returns the MTU of the interface.
+
+    This method is only available on Linux and macOS.
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn mtu(&self) -> Result<u32> {
+        crate::interface::get_mtu(&self.name)
+    }
+
+    /**
+    Returns the flags of the interface.
+
+    This method is only available on Linux and macOS.
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn flags(&self) -> Result<crate::interface::InterfaceFlags> {
+        crate::interface::get_flags(&self.name)
+    }
+
+    /**
+    Asks the kernel whether the device is a Tun or a Tap device.
+
+    See [`Device::mode`] for the errors.
+
+    [`Device::mode`]: struct.Device.html#method.mode
+    */
+    pub fn mode(&self) -> Result<Mode> {
+        mode_of(self.fd)
+    }
+}
+
+impl Read for BorrowedDevice<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let number = unsafe {
+            libc::read(
+                self.fd.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+            )
+        };
+        if number < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(number as usize)
+    }
+}
+
+impl Write for BorrowedDevice<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let number = unsafe {
+            libc::write(
+                self.fd.as_raw_fd(),
+                buf.as_ptr() as *const libc::c_void,
+                buf.len(),
+            )
+        };
+        if number < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(number as usize)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl AsRawFd for BorrowedDevice<'_> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl AsFd for BorrowedDevice<'_> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd
+    }
+}
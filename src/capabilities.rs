@@ -0,0 +1,98 @@
+//! The features the running system supports
+
+/**
+The features of Tun/Tap devices the running system supports, as returned by
+[`capabilities`].
+
+Each field tells whether the matching option or function of this crate can
+succeed here, so an application can adapt before opening a device rather
+than on the first error. Features this crate only implements on other
+platforms are `false`.
+
+[`capabilities`]: fn.capabilities.html
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Capabilities {
+    /// Tun devices can be opened.
+    pub tun: bool,
+    /// Tap devices can be opened, see also [`TAP_SUPPORTED`].
+    ///
+    /// [`TAP_SUPPORTED`]: constant.TAP_SUPPORTED.html
+    pub tap: bool,
+    /// The packet information header can be turned on and off, with
+    /// `packet_info`.
+    pub packet_info: bool,
+    /// Packets can carry the virtio net header, with `vnet_hdr`.
+    pub vnet_hdr: bool,
+    /// Offloads can be negotiated, with `offload`.
+    pub offload: bool,
+    /// An interface can have several queues, with `open_multiqueue`.
+    pub multi_queue: bool,
+    /// The interface can outlive the device, with [`tun::set_persist`].
+    ///
+    /// [`tun::set_persist`]: tun/fn.set_persist.html
+    pub persist: bool,
+    /// The MTU can be set when opening, with `mtu`.
+    pub set_mtu: bool,
+    /// Written packets can be received through NAPI, with `napi`.
+    pub napi: bool,
+}
+
+/**
+Probes the features of Tun/Tap devices the running system supports.
+
+On Linux, the driver is asked for its features with `TUNGETFEATURES`
+through the first device node which opens, without creating an interface;
+every feature is `false` when none opens, e.g. when the `tun` module is not
+loaded. Elsewhere, the features are known at compile time.
+
+# Examples
+
+```
+let capabilities = utuntap::capabilities();
+if !capabilities.multi_queue {
+    println!("reading with a single queue");
+}
+```
+*/
+pub fn capabilities() -> Capabilities {
+    #[cfg(target_os = "linux")]
+    {
+        use crate::interface::linux::{
+            IFF_MULTI_QUEUE, IFF_NAPI, IFF_NO_PI, IFF_TAP, IFF_TUN, IFF_VNET_HDR,
+        };
+
+        let features = crate::TUN_NODES.iter().find_map(|node| {
+            let file = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(node)
+                .ok()?;
+            crate::tun::get_features(&file).ok()
+        });
+        let Some(features) = features else {
+            return Capabilities::default();
+        };
+        let has = |flag: libc::c_short| features & flag as u16 as libc::c_uint != 0;
+        Capabilities {
+            tun: has(IFF_TUN),
+            tap: has(IFF_TAP),
+            packet_info: has(IFF_NO_PI),
+            vnet_hdr: has(IFF_VNET_HDR),
+            offload: has(IFF_VNET_HDR),
+            multi_queue: has(IFF_MULTI_QUEUE),
+            persist: true,
+            set_mtu: true,
+            napi: has(IFF_NAPI),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Capabilities {
+            tun: true,
+            tap: crate::TAP_SUPPORTED,
+            set_mtu: cfg!(target_os = "macos"),
+            ..Capabilities::default()
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! The Internet checksum of RFC 1071
+
+#[cfg(feature = "checksum")]
+use std::io::{Error, ErrorKind, Result};
+
+/// Adds `data` to the running one's complement `sum` as big-endian words.
+pub(crate) fn add(mut sum: u32, data: &[u8]) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+    for chunk in &mut chunks {
+        sum += u32::from(u16::from_be_bytes([chunk[0], chunk[1]]));
+    }
+    if let [last] = chunks.remainder() {
+        sum += u32::from(*last) << 8;
+    }
+    sum
+}
+
+/// Folds `sum` into the final one's complement checksum.
+pub(crate) fn finish(mut sum: u32) -> u16 {
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Returns the sum of the pseudo header of a transport segment.
+pub(crate) fn pseudo_header(source: &[u8], destination: &[u8], protocol: u8, length: u32) -> u32 {
+    let mut sum = add(add(0, source), destination);
+    sum += u32::from(protocol);
+    sum += length >> 16;
+    sum += length & 0xffff;
+    sum
+}
+
+/**
+Recomputes the checksums of the IP packet `packet`, as read from or written
+to a Tun device without packet info: the header checksum of IPv4 and the
+checksum of a TCP or UDP segment it carries, over IPv4 or IPv6.
+
+The kernel drops packets written with stale checksums unless offloads were
+negotiated, so this is needed after crafting or rewriting a packet by hand.
+Bytes past the length given in the IP header are ignored. Segments of other
+protocols, fragments, and IPv6 packets with extension headers are left
+alone.
+
+This function is only available with the `checksum` feature.
+
+# Errors
+
+* [`InvalidData`]: The packet is not IPv4 or IPv6, or is truncated.
+
+# Examples
+
+```no_run
+use std::io::Write;
+use utuntap::{fix_checksums, tun::OpenOptions};
+
+let (mut file, _) = OpenOptions::new().packet_info(false).open(0).unwrap();
+let mut packet = vec![0x45, 0, 0, 20, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2];
+fix_checksums(&mut packet).unwrap();
+file.write(&packet).unwrap();
+```
+
+[`InvalidData`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidData
+*/
+#[cfg(feature = "checksum")]
+pub fn fix_checksums(packet: &mut [u8]) -> Result<()> {
+    let malformed = |message| Error::new(ErrorKind::InvalidData, message);
+    let (packet, transport, protocol, addresses) = match packet.first().map(|byte| byte >> 4) {
+        Some(4) => {
+            let header_length = usize::from(packet[0] & 0x0f) * 4;
+            if header_length < 20 || packet.len() < header_length {
+                return Err(malformed("truncated IPv4 header"));
+            }
+            let total = usize::from(u16::from_be_bytes([packet[2], packet[3]]));
+            if total < header_length || packet.len() < total {
+                return Err(malformed("truncated IPv4 packet"));
+            }
+            let packet = &mut packet[..total];
+            packet[10..12].copy_from_slice(&[0, 0]);
+            let sum = finish(add(0, &packet[..header_length]));
+            packet[10..12].copy_from_slice(&sum.to_be_bytes());
+
+            // Only the first fragment carries the transport header, and the
+            // checksum covers every fragment.
+            const MORE_FRAGMENTS: u8 = 0x20;
+            if packet[6] & MORE_FRAGMENTS != 0 || packet[6] & 0x1f != 0 || packet[7] != 0 {
+                return Ok(());
+            }
+            let protocol = packet[9];
+            (packet, header_length, protocol, 12..20)
+        }
+        Some(6) => {
+            if packet.len() < 40 {
+                return Err(malformed("truncated IPv6 header"));
+            }
+            let total = 40 + usize::from(u16::from_be_bytes([packet[4], packet[5]]));
+            if packet.len() < total {
+                return Err(malformed("truncated IPv6 packet"));
+            }
+            let protocol = packet[6];
+            (&mut packet[..total], 40, protocol, 8..40)
+        }
+        Some(_) => return Err(malformed("unknown IP version")),
+        None => return Err(malformed("truncated packet")),
+    };
+
+    let offset = match protocol {
+        6 if packet.len() >= transport + 20 => transport + 16,
+        17 if packet.len() >= transport + 8 => transport + 6,
+        6 | 17 => return Err(malformed("truncated transport header")),
+        _ => return Ok(()),
+    };
+    packet[offset..offset + 2].copy_from_slice(&[0, 0]);
+    let half = (addresses.end - addresses.start) / 2;
+    let source = addresses.start..addresses.start + half;
+    let destination = addresses.start + half..addresses.end;
+    let sum = pseudo_header(
+        &packet[source],
+        &packet[destination],
+        protocol,
+        (packet.len() - transport) as u32,
+    );
+    let mut sum = finish(add(sum, &packet[transport..]));
+    // A UDP checksum of zero means none was computed.
+    if protocol == 17 && sum == 0 {
+        sum = 0xffff;
+    }
+    packet[offset..offset + 2].copy_from_slice(&sum.to_be_bytes());
+    Ok(())
+}
@@ -0,0 +1,314 @@
+//! Portable device configuration
+
+use super::{Device, Mode, OpenOptions};
+use std::fs::File;
+use std::io::Result;
+
+/**
+A plain description of a device to open, e.g. loaded from a configuration
+file.
+
+With the `serde` feature enabled, `DeviceConfig` implements `Serialize` and
+`Deserialize`, and every field but `mode` may be omitted. The struct is the
+same on every platform: options the target does not support are ignored,
+except for `name`, which makes [`open`] fail with [`Unsupported`] outside of
+Linux rather than open a different device.
+
+[`open`]: struct.DeviceConfig.html#method.open
+[`Unsupported`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.Unsupported
+
+# Examples
+
+```no_run
+use utuntap::{config::DeviceConfig, Mode};
+
+let config = DeviceConfig {
+    mode: Mode::Tun,
+    number: Some(0),
+    nonblock: true,
+    ..Default::default()
+};
+let (file, filename) = config.open().unwrap();
+```
+
+In TOML, the same configuration reads:
+
+```toml
+mode = "tun"
+number = 0
+nonblock = true
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DeviceConfig {
+    /// The kind of device.
+    pub mode: Mode,
+    /// The number of the device, e.g. the "0" of "tun0".
+    pub number: Option<u32>,
+    /// The exact name of the interface. Only supported on Linux.
+    pub name: Option<String>,
+    /// Whether the file is opened for reading.
+    pub read: bool,
+    /// Whether the file is opened for writing.
+    pub write: bool,
+    /// Whether the file is in non-blocking mode.
+    pub nonblock: bool,
+    /// Whether packets carry packet info. Ignored outside of Linux.
+    pub packet_info: bool,
+}
+
+impl DeviceConfig {
+    /**
+    Opens the device described by `self`, returning the file and the name of
+    the device.
+    */
+    pub fn open(&self) -> Result<(File, String)> {
+        self.options()?.open(self.number)
+    }
+
+    /**
+    Opens the device described by `self`, returning a [`Device`].
+
+    [`Device`]: ../struct.Device.html
+    */
+    pub fn open_device(&self) -> Result<Device> {
+        self.options()?.open_device(self.number)
+    }
+
+    /**
+    Reads a configuration from the environment variables whose names start
+    with `prefix`, e.g. `TUN_NAME` for the prefix `TUN_`, as orchestration
+    tools pass them to containers.
+
+    Every field has a variable; those not set keep their default:
+
+    | Variable              | Field         | Values                      |
+    |-----------------------|---------------|-----------------------------|
+    | `<prefix>MODE`        | `mode`        | `tun` or `tap`              |
+    | `<prefix>NUMBER`      | `number`      | a device number, e.g. `0`   |
+    | `<prefix>NAME`        | `name`        | an interface name           |
+    | `<prefix>READ`        | `read`        | `true`, `false`, `1` or `0` |
+    | `<prefix>WRITE`       | `write`       | as `READ`                   |
+    | `<prefix>NONBLOCK`    | `nonblock`    | as `READ`                   |
+    | `<prefix>PACKET_INFO` | `packet_info` | as `READ`                   |
+
+    # Errors
+
+    * [`InvalidInput`]: a variable holds a value which is not valid for its
+      field, or is not valid Unicode. The error names the variable.
+
+    # Examples
+
+    ```no_run
+    use utuntap::config::DeviceConfig;
+
+    // With TUN_MODE=tap and TUN_NAME=vpn0 set by the container runtime
+    let device = DeviceConfig::from_env("TUN_").unwrap().open_device().unwrap();
+    ```
+
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    pub fn from_env(prefix: &str) -> Result<Self> {
+        use std::io::{Error, ErrorKind};
+
+        let var = |name: &str| -> Result<Option<(String, String)>> {
+            let variable = format!("{}{}", prefix, name);
+            match std::env::var(&variable) {
+                Ok(value) => Ok(Some((variable, value))),
+                Err(std::env::VarError::NotPresent) => Ok(None),
+                Err(error) => Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{}: {}", variable, error),
+                )),
+            }
+        };
+        let invalid = |variable: &str, value: &str| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("{}: invalid value {:?}", variable, value),
+            )
+        };
+        let flag = |name: &str, default: bool| -> Result<bool> {
+            match var(name)? {
+                None => Ok(default),
+                Some((variable, value)) => match value.as_str() {
+                    "true" | "1" => Ok(true),
+                    "false" | "0" => Ok(false),
+                    _ => Err(invalid(&variable, &value)),
+                },
+            }
+        };
+
+        let mut config = Self::default();
+        if let Some((variable, value)) = var("MODE")? {
+            config.mode = value.parse().map_err(|_| invalid(&variable, &value))?;
+        }
+        if let Some((variable, value)) = var("NUMBER")? {
+            config.number = Some(value.parse().map_err(|_| invalid(&variable, &value))?);
+        }
+        if let Some((variable, value)) = var("NAME")? {
+            if value.is_empty() {
+                return Err(invalid(&variable, &value));
+            }
+            config.name = Some(value);
+        }
+        config.read = flag("READ", config.read)?;
+        config.write = flag("WRITE", config.write)?;
+        config.nonblock = flag("NONBLOCK", config.nonblock)?;
+        config.packet_info = flag("PACKET_INFO", config.packet_info)?;
+        Ok(config)
+    }
+
+    fn options(&self) -> Result<OpenOptions> {
+        #[cfg(not(target_os = "linux"))]
+        if self.name.is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "opening a device by name is only supported on Linux",
+            ));
+        }
+        Ok(OpenOptions::from(self.clone()))
+    }
+}
+
+impl Default for DeviceConfig {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Tun,
+            number: None,
+            name: None,
+            read: true,
+            write: true,
+            nonblock: false,
+            packet_info: false,
+        }
+    }
+}
+
+impl From<DeviceConfig> for OpenOptions {
+    /// Converts the configuration into a builder.
+    ///
+    /// `name` is dropped outside of Linux; use [`DeviceConfig::open`] to get
+    /// an error instead.
+    ///
+    /// [`DeviceConfig::open`]: DeviceConfig::open
+    fn from(config: DeviceConfig) -> Self {
+        let mut options = OpenOptions::new(config.mode);
+        options.read(config.read).write(config.write);
+        #[cfg(target_family = "unix")]
+        options.nonblock(config.nonblock);
+        #[cfg(target_os = "linux")]
+        {
+            options.packet_info(config.packet_info);
+            if let Some(name) = &config.name {
+                options.name(name);
+            }
+        }
+        options
+    }
+}
+
+/**
+Everything root does to set up a device, performed at once by [`provision`]
+before a privilege-separated daemon drops its privileges.
+
+This type is only available on Linux.
+
+# Examples
+
+```no_run
+use std::net::Ipv4Addr;
+use utuntap::config::{provision, DeviceConfig, DeviceSpec};
+
+let spec = DeviceSpec {
+    config: DeviceConfig {
+        name: Some("vpn0".to_string()),
+        ..Default::default()
+    },
+    owner: Some(1000),
+    mtu: Some(1420),
+    address: Some((Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(255, 255, 255, 0))),
+    up: true,
+    ..Default::default()
+};
+let device = provision(&spec).unwrap();
+// Privileges may be dropped from here on.
+```
+
+[`provision`]: fn.provision.html
+*/
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceSpec {
+    /// The device to open.
+    pub config: DeviceConfig,
+    /// The user allowed to attach to the device, set with `TUNSETOWNER`.
+    pub owner: Option<libc::uid_t>,
+    /// The group allowed to attach to the device, set with `TUNSETGROUP`.
+    pub group: Option<libc::gid_t>,
+    /// Whether the interface outlives the device, set with `TUNSETPERSIST`.
+    pub persist: bool,
+    /// The MTU of the interface.
+    pub mtu: Option<u32>,
+    /// The IPv4 address and netmask of the interface.
+    pub address: Option<(std::net::Ipv4Addr, std::net::Ipv4Addr)>,
+    /// Whether the interface is brought up.
+    pub up: bool,
+}
+
+/**
+Opens and configures the device described by `spec` in one step, setting
+the owner and group, persistence, MTU and address in that order before
+bringing the interface up.
+
+If any step fails, the device is closed and its error returned. A device
+made persistent by this call is made transient again first, so that the
+interface goes away with it; an interface which was persistent before is
+left in place.
+
+This function is only available on Linux.
+*/
+#[cfg(target_os = "linux")]
+pub fn provision(spec: &DeviceSpec) -> Result<Device> {
+    use crate::interface::{self, linux::IFF_PERSIST};
+    use crate::ioctl::{tun_ioctl_with_value, TUNSETGROUP, TUNSETOWNER};
+    use std::os::unix::io::AsRawFd;
+
+    let device = spec.config.open_device()?;
+    let was_persistent = device.tun_flags() & IFF_PERSIST != 0;
+    let configure = || -> Result<()> {
+        let fd = device.file.as_raw_fd();
+        if let Some(owner) = spec.owner {
+            unsafe { tun_ioctl_with_value(fd, TUNSETOWNER, owner.into())? };
+        }
+        if let Some(group) = spec.group {
+            unsafe { tun_ioctl_with_value(fd, TUNSETGROUP, group.into())? };
+        }
+        if spec.persist {
+            crate::tun::set_persist(&device.file, true)?;
+        }
+        let configurator = interface::InterfaceConfigurator::new()?;
+        if let Some(mtu) = spec.mtu {
+            configurator.set_mtu(&device.name, mtu)?;
+        }
+        if let Some((address, netmask)) = spec.address {
+            configurator.set_address(&device.name, address, netmask)?;
+        }
+        if spec.up {
+            configurator.set_up(&device.name)?;
+        }
+        Ok(())
+    };
+
+    match configure() {
+        Ok(()) => Ok(device),
+        Err(error) => {
+            if spec.persist && !was_persistent {
+                let _ = crate::tun::set_persist(&device.file, false);
+            }
+            Err(error)
+        }
+    }
+}
@@ -0,0 +1,2351 @@
+//! An opened Tun/Tap device
+
+use crate::packet_info::Protocol;
+use crate::Mode;
+use std::fs::File;
+use std::io::{Error, ErrorKind, IoSlice, IoSliceMut, Read, Result, Write};
+use std::mem;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::UnixStream;
+
+/// Length of an Ethernet header, which the MTU of an interface excludes.
+#[cfg(any(target_os = "linux", all(target_os = "macos", feature = "macos-feth")))]
+const ETHERNET_HEADER_LEN: usize = 14;
+
+/// Largest virtio net header read on the stack, beyond the 12 bytes of
+/// `struct virtio_net_hdr_mrg_rxbuf` and the 20 of the hash report header.
+#[cfg(target_os = "linux")]
+const MAX_VNET_HDR_LEN: usize = 64;
+
+/**
+An opened Tun/Tap device together with the name of its interface.
+
+Reads and writes go straight to the underlying file, one packet per call.
+A device never reaches the end of file: a `read` returning `Ok(0)` means
+the buffer was empty, so a read loop must not stop on it as it would for a
+regular file. The packet helpers, e.g. [`read_packet`], reject an empty
+buffer instead.
+[`Read::read_buf`][read_buf] is not specialised and falls back to the
+default implementation, which initializes the buffer first; use
+[`read_packet_uninit`] to read into uninitialized memory instead.
+
+Since every `read` and `write` maps to exactly one packet, a device must
+not be wrapped in a [`BufReader`] or [`BufWriter`]: buffering merges and
+splits packets, so their boundaries are lost. `Device` does not implement
+[`BufRead`] for that reason. A buffer shorter than the packet read only
+receives its start, and the kernel discards the rest; size buffers with
+[`recommended_buffer_size`].
+
+```compile_fail
+fn buffered<T: std::io::BufRead>() {}
+buffered::<utuntap::Device>();
+```
+
+Packets written to a device carry no priority for the qdiscs they later
+pass through. The file is not a socket, so `SO_PRIORITY` cannot be set on
+it: `setsockopt` fails with `ENOTSOCK` on Linux, and the utun sockets of
+macOS have no such option. On Linux, packets written to a device enter the
+kernel as if received by its interface, with a priority of 0; classify them
+where they are forwarded instead, e.g. with `tc filter ... action skbedit
+priority` or the `meta priority set` statement of nftables. Packets read
+from a device keep the priority their sender gave them, which the qdisc of
+the interface, `pfifo_fast` by default, uses to pick a band.
+
+Packets cannot be moved between devices with `splice`: the Tun/Tap driver of
+Linux implements neither end of it, so splicing from or to a device through
+a pipe fails with `EINVAL`. A userspace bridge copies each packet once when
+reading it and once when writing it; to copy fewer, larger packets, open
+both devices with the virtio net header and offloads, or leave the
+forwarding to the kernel, e.g. with a bridge or the `mirred` action of `tc`.
+
+[read_buf]: https://doc.rust-lang.org/nightly/std/io/trait.Read.html#method.read_buf
+[`BufReader`]: https://doc.rust-lang.org/nightly/std/io/struct.BufReader.html
+[`BufWriter`]: https://doc.rust-lang.org/nightly/std/io/struct.BufWriter.html
+[`BufRead`]: https://doc.rust-lang.org/nightly/std/io/trait.BufRead.html
+[`recommended_buffer_size`]: struct.Device.html#method.recommended_buffer_size
+[`read_packet_uninit`]: struct.Device.html#method.read_packet_uninit
+[`read_packet`]: struct.Device.html#method.read_packet
+
+# Examples
+
+```no_run
+use std::io::Read;
+use utuntap::tun::OpenOptions;
+
+let mut device = OpenOptions::new().open_device(0).unwrap();
+let mut buffer = [0; 1500];
+let number = device.read(&mut buffer).unwrap();
+println!("{} read {} bytes", device.name(), number);
+```
+*/
+#[derive(Debug)]
+pub struct Device {
+    pub(crate) file: File,
+    pub(crate) name: String,
+    #[cfg(target_os = "linux")]
+    pub(crate) offload: crate::tun::Offload,
+    #[cfg(target_os = "linux")]
+    pub(crate) packet_info: bool,
+    #[cfg(target_os = "linux")]
+    pub(crate) tun_flags: libc::c_short,
+    /// The mode of the device when `Read` and `Write` strip its 4-byte
+    /// header.
+    pub(crate) strip_protocol_info: Option<Mode>,
+    pub(crate) source: DeviceSource,
+    /// How long reads wait for a packet before failing, forever if `None`.
+    pub(crate) read_timeout: Option<std::time::Duration>,
+    /// The MTU [`write_checked`](Self::write_checked) checks against and
+    /// `read_frame` sizes its buffer with, 0 until it is first read.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    mtu: std::sync::atomic::AtomicU32,
+    /// The buffer [`read_frame_borrowed`](Self::read_frame_borrowed) reads
+    /// into, empty until it is first needed.
+    pub(crate) read_buffer: Vec<u8>,
+    /// The `feth` interface of a Tap device emulated on macOS, shared with
+    /// the clones of the device and destroyed with the last of them. It is
+    /// set when the device is opened in Tap mode, which it records.
+    #[cfg(all(target_os = "macos", feature = "macos-feth"))]
+    pub(crate) feth: Option<std::sync::Arc<crate::feth::Feth>>,
+}
+
+/**
+Where the file of a [`Device`] comes from, as reported by [`Device::source`].
+
+This names the platform mechanism behind the device, e.g. for logs when a
+device does not behave as expected.
+
+[`Device`]: struct.Device.html
+[`Device::source`]: struct.Device.html#method.source
+
+# Examples
+
+```no_run
+use utuntap::tun::OpenOptions;
+
+let device = OpenOptions::new().open_device(0).unwrap();
+println!("opened {} through {:?}", device.name(), device.source());
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DeviceSource {
+    /// A device node opened by path: `/dev/net/tun` on Linux, `/dev/tun0`
+    /// or the clone node `/dev/tun` on OpenBSD, `/dev/tun` on illumos and
+    /// Solaris, or the `/dev/bpfN` device bound to a `feth` interface on
+    /// macOS.
+    Node(std::path::PathBuf),
+    /// A kernel control socket, as utun on macOS.
+    ControlSocket {
+        /// The name of the kernel control, e.g. `com.apple.net.utun_control`.
+        control: String,
+        /// The `sc_unit` connected to, one past the number of the interface.
+        unit: u32,
+    },
+    /// A descriptor opened elsewhere, e.g. adopted with `from_fd` or
+    /// received with `recv_over`.
+    Adopted,
+}
+
+/**
+The protocol of a packet carried by a Tun device.
+
+# Examples
+
+```no_run
+use utuntap::{tun::OpenOptions, AddressFamily};
+
+let mut device = OpenOptions::new().open_device(0).unwrap();
+let (family, packet) = device.read_packet(1500).unwrap();
+if family == AddressFamily::Inet6 {
+    println!("IPv6 packet of {} bytes", packet.len());
+}
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressFamily {
+    /// IPv4
+    Inet,
+    /// IPv6
+    Inet6,
+}
+
+impl AddressFamily {
+    /// Guesses the family of a bare IP packet from its version field.
+    pub(crate) fn of_packet(packet: &[u8]) -> Result<Self> {
+        match packet.first().map(|byte| byte >> 4) {
+            Some(4) => Ok(AddressFamily::Inet),
+            Some(6) => Ok(AddressFamily::Inet6),
+            _ => Err(Error::new(ErrorKind::InvalidData, "unknown IP version")),
+        }
+    }
+
+    /// Returns the 4-byte header the platform expects in front of packets
+    /// of this family.
+    fn header(self) -> [u8; 4] {
+        let protocol = match self {
+            AddressFamily::Inet => Protocol::Ipv4,
+            AddressFamily::Inet6 => Protocol::Ipv6,
+        };
+        #[cfg(target_os = "linux")]
+        return protocol.to_packet_info();
+        #[cfg(not(target_os = "linux"))]
+        return protocol
+            .to_address_family_prefix()
+            .expect("IP protocols have an address family");
+    }
+
+    /// Parses the 4-byte header in front of a packet.
+    fn from_header(header: [u8; 4]) -> Result<Self> {
+        #[cfg(target_os = "linux")]
+        let protocol = Protocol::from_packet_info(header);
+        #[cfg(not(target_os = "linux"))]
+        let protocol = Protocol::from_address_family_prefix(header);
+        match protocol {
+            Some(Protocol::Ipv4) => Ok(AddressFamily::Inet),
+            Some(Protocol::Ipv6) => Ok(AddressFamily::Inet6),
+            _ => Err(Error::new(ErrorKind::InvalidData, "unknown protocol")),
+        }
+    }
+}
+
+impl Device {
+    pub(crate) fn new(file: File, name: String) -> Self {
+        Self {
+            file,
+            name,
+            #[cfg(target_os = "linux")]
+            offload: Default::default(),
+            #[cfg(target_os = "linux")]
+            packet_info: false,
+            #[cfg(target_os = "linux")]
+            tun_flags: 0,
+            strip_protocol_info: None,
+            source: DeviceSource::Adopted,
+            read_timeout: None,
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            mtu: Default::default(),
+            read_buffer: Vec::new(),
+            #[cfg(all(target_os = "macos", feature = "macos-feth"))]
+            feth: None,
+        }
+    }
+
+    /// Duplicates the descriptor of the device into a second `Device`
+    /// sharing its options, e.g. for one task to read and another to write.
+    #[cfg(feature = "async-io")]
+    pub(crate) fn try_clone(&self) -> Result<Self> {
+        Ok(Self {
+            file: self.file.try_clone()?,
+            name: self.name.clone(),
+            #[cfg(target_os = "linux")]
+            offload: self.offload,
+            #[cfg(target_os = "linux")]
+            packet_info: self.packet_info,
+            #[cfg(target_os = "linux")]
+            tun_flags: self.tun_flags,
+            strip_protocol_info: self.strip_protocol_info,
+            source: self.source.clone(),
+            read_timeout: self.read_timeout,
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            mtu: self.mtu.load(std::sync::atomic::Ordering::Relaxed).into(),
+            read_buffer: Vec::new(),
+            #[cfg(all(target_os = "macos", feature = "macos-feth"))]
+            feth: self.feth.clone(),
+        })
+    }
+
+    /// Wraps a descriptor opened elsewhere, asking the kernel for the
+    /// options which shape its packets.
+    fn adopt(file: File, name: String) -> Self {
+        #[allow(unused_mut)]
+        let mut device = Self::new(file, name);
+
+        #[cfg(target_os = "linux")]
+        {
+            use crate::interface::linux::{get_packet_info, Request, IFF_NO_PI};
+
+            let flags = Request::get_tuntap(device.file.as_raw_fd()).map(|request| request.flags());
+            // A cleared IFF_NO_PI from TUNGETIFF is reliable, a set one may
+            // be IFF_NOFILTER, so older kernels default to no packet info.
+            device.packet_info = get_packet_info(&device.name)
+                .unwrap_or_else(|_| flags.as_ref().is_ok_and(|flags| flags & IFF_NO_PI == 0));
+            device.tun_flags = with_packet_info(flags.unwrap_or(0), device.packet_info);
+        }
+
+        device
+    }
+
+    /// Reads the flags the device is attached with back from the kernel,
+    /// once it was opened with or without packet info.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn load_tun_flags(&mut self, packet_info: bool) -> Result<()> {
+        let request = crate::interface::linux::Request::get_tuntap(self.file.as_raw_fd())?;
+        self.tun_flags = with_packet_info(request.flags(), packet_info);
+        Ok(())
+    }
+
+    /**
+    Adopts an already opened Tun/Tap file descriptor, e.g. one inherited
+    through systemd socket activation or from a privileged parent process.
+
+    The kernel does not tell which interface a descriptor is attached to on
+    every platform, so the caller supplies its `name`.
+
+    # Safety
+
+    `fd` must be an open file descriptor of a Tun/Tap device which is not
+    owned by anything else: the returned `Device` closes it when dropped, so
+    nothing else may use or close it afterwards. Prefer [`from_owned_fd`]
+    when an [`OwnedFd`][owned] is at hand.
+
+    [`from_owned_fd`]: struct.Device.html#method.from_owned_fd
+    [owned]: https://doc.rust-lang.org/nightly/std/os/fd/struct.OwnedFd.html
+
+    # Examples
+
+    ```no_run
+    use utuntap::Device;
+
+    // The descriptor 3 was passed by the service manager.
+    let device = unsafe { Device::from_fd(3, "tun0".to_string()) };
+    ```
+    */
+    pub unsafe fn from_fd(fd: RawFd, name: String) -> Self {
+        Self::adopt(File::from_raw_fd(fd), name)
+    }
+
+    /**
+    Adopts an owned Tun/Tap file descriptor, associating it with the
+    interface `name`.
+
+    # Examples
+
+    ```no_run
+    use utuntap::{tun::OpenOptions, Device};
+
+    let (file, filename) = OpenOptions::new().open(0).unwrap();
+    let device = Device::from_owned_fd(file.into(), filename);
+    ```
+    */
+    pub fn from_owned_fd(fd: OwnedFd, name: String) -> Self {
+        Self::adopt(File::from(fd), name)
+    }
+
+    /**
+    Adopts a connected utun control socket, e.g. the one the packet tunnel
+    provider of NetworkExtension hands over, asking the kernel for the name
+    of its interface with `UTUN_OPT_IFNAME`.
+
+    # Errors
+
+    * [`InvalidInput`]: `fd` is a socket, but not one connected to a kernel
+      control, or the control is not utun.
+
+    Other errors are reported by the OS, e.g. `ENOTSOCK` when `fd` is not a
+    socket. On error, `fd` is left open and still belongs to the caller.
+
+    # Safety
+
+    `fd` must be an open file descriptor which is not owned by anything else:
+    once adopted, the returned `Device` closes it when dropped.
+
+    This method is only available on macOS.
+
+    # Examples
+
+    ```no_run
+    use utuntap::Device;
+
+    // The descriptor was found by the packet tunnel provider.
+    let device = unsafe { Device::from_utun_fd(5).unwrap() };
+    println!("adopted {}", device.name());
+    ```
+
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    #[cfg(target_os = "macos")]
+    pub unsafe fn from_utun_fd(fd: RawFd) -> Result<Self> {
+        use libc::{getpeername, getsockopt, sockaddr, sockaddr_ctl, socklen_t, SYSPROTO_CONTROL};
+
+        const AF_SYS_CONTROL: u16 = 2;
+        const UTUN_OPT_IFNAME: libc::c_int = 2;
+
+        let not_utun = || Error::new(ErrorKind::InvalidInput, "not a utun control socket");
+
+        let mut peer: sockaddr_ctl = mem::zeroed();
+        let mut length = mem::size_of_val(&peer) as socklen_t;
+        crate::retry_eintr(|| {
+            let peer_ptr = &mut peer as *mut sockaddr_ctl;
+            if getpeername(fd, peer_ptr as *mut sockaddr, &mut length) < 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(())
+        })?;
+        if libc::c_int::from(peer.sc_family) != libc::AF_SYSTEM || peer.ss_sysaddr != AF_SYS_CONTROL
+        {
+            return Err(not_utun());
+        }
+
+        let mut name = [0u8; libc::IFNAMSIZ];
+        let mut length = name.len() as socklen_t;
+        let err = getsockopt(
+            fd,
+            SYSPROTO_CONTROL,
+            UTUN_OPT_IFNAME,
+            name.as_mut_ptr() as *mut libc::c_void,
+            &mut length,
+        );
+        if err < 0 {
+            // Other kernel controls do not know the option.
+            return match Error::last_os_error() {
+                error if error.raw_os_error() == Some(libc::ENOPROTOOPT) => Err(not_utun()),
+                error => Err(error),
+            };
+        }
+        let length = name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(name.len());
+        let name = std::str::from_utf8(&name[..length]).map_err(|_| not_utun())?;
+        if !name.starts_with("utun") {
+            return Err(not_utun());
+        }
+
+        Ok(Self::adopt(File::from_raw_fd(fd), name.to_string()))
+    }
+
+    /**
+    Sends the descriptor of the device over the Unix socket `sock` with
+    `SCM_RIGHTS`, e.g. from a privileged process which opened the device to
+    an unprivileged worker.
+
+    The device stays open in the sending process; the receiver gets its own
+    descriptor of the same device with [`recv_over`]. Only the descriptor is
+    sent, not the name of the interface.
+
+    # Examples
+
+    ```no_run
+    use std::os::unix::net::UnixStream;
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new().open_device(0).unwrap();
+    let sock = UnixStream::connect("/run/worker.sock").unwrap();
+    device.send_over(&sock).unwrap();
+    ```
+
+    [`recv_over`]: struct.Device.html#method.recv_over
+    */
+    pub fn send_over(&self, sock: &UnixStream) -> Result<()> {
+        let fd = self.file.as_raw_fd();
+        // At least one byte of data has to go along with the descriptor.
+        let mut data = [0u8];
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+        let mut control =
+            vec![0u8; unsafe { libc::CMSG_SPACE(mem::size_of::<RawFd>() as _) } as usize];
+        let mut message: libc::msghdr = unsafe { mem::zeroed() };
+        message.msg_iov = &mut iov;
+        message.msg_iovlen = 1;
+        message.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        message.msg_controllen = control.len() as _;
+        unsafe {
+            let header = libc::CMSG_FIRSTHDR(&message);
+            (*header).cmsg_level = libc::SOL_SOCKET;
+            (*header).cmsg_type = libc::SCM_RIGHTS;
+            (*header).cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as _) as _;
+            std::ptr::write_unaligned(libc::CMSG_DATA(header) as *mut RawFd, fd);
+        }
+        if unsafe { libc::sendmsg(sock.as_raw_fd(), &message, 0) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /**
+    Receives a device sent with [`send_over`] from the Unix socket `sock`,
+    associating it with the interface `name`.
+
+    # Errors
+
+    This function returns [`InvalidData`] when the message carries no
+    descriptor.
+
+    # Examples
+
+    ```no_run
+    use std::os::unix::net::UnixListener;
+    use utuntap::Device;
+
+    let listener = UnixListener::bind("/run/worker.sock").unwrap();
+    let (sock, _) = listener.accept().unwrap();
+    let device = Device::recv_over(&sock, "tun0".to_string()).unwrap();
+    ```
+
+    [`send_over`]: struct.Device.html#method.send_over
+    [`InvalidData`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidData
+    */
+    pub fn recv_over(sock: &UnixStream, name: String) -> Result<Self> {
+        let mut data = [0u8];
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+        let mut control =
+            vec![0u8; unsafe { libc::CMSG_SPACE(mem::size_of::<RawFd>() as _) } as usize];
+        let mut message: libc::msghdr = unsafe { mem::zeroed() };
+        message.msg_iov = &mut iov;
+        message.msg_iovlen = 1;
+        message.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        message.msg_controllen = control.len() as _;
+
+        #[cfg(any(target_os = "linux", target_os = "openbsd"))]
+        let flags = libc::MSG_CMSG_CLOEXEC;
+        #[cfg(not(any(target_os = "linux", target_os = "openbsd")))]
+        let flags = 0;
+        if unsafe { libc::recvmsg(sock.as_raw_fd(), &mut message, flags) } < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        let header = unsafe { libc::CMSG_FIRSTHDR(&message) };
+        if header.is_null()
+            || message.msg_flags & libc::MSG_CTRUNC != 0
+            || unsafe {
+                (*header).cmsg_level != libc::SOL_SOCKET || (*header).cmsg_type != libc::SCM_RIGHTS
+            }
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "the message carries no file descriptor",
+            ));
+        }
+        let fd = unsafe { std::ptr::read_unaligned(libc::CMSG_DATA(header) as *const RawFd) };
+        let file = unsafe { File::from_raw_fd(fd) };
+
+        #[cfg(not(any(target_os = "linux", target_os = "openbsd")))]
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(Self::adopt(file, name))
+    }
+
+    /// Returns the name of the interface, e.g. `tun0`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the underlying device file.
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Returns where the file of the device comes from.
+    pub fn source(&self) -> &DeviceSource {
+        &self.source
+    }
+
+    /**
+    Returns the permissions of the file of the device, e.g. to tell why
+    another user cannot open it.
+
+    These are the permissions of the node the file was opened from, e.g.
+    `/dev/net/tun` on Linux, which decide who may open a device at all. Who
+    may attach to a persistent interface is decided by its owner and group
+    instead, set with `TUNSETOWNER` and `TUNSETGROUP`. On macOS, utun
+    devices are sockets, whose permissions mean little.
+
+    # Examples
+
+    ```no_run
+    use std::os::unix::fs::PermissionsExt;
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new().open_device(0).unwrap();
+    println!("{:o}", device.permissions().unwrap().mode() & 0o777);
+    ```
+    */
+    pub fn permissions(&self) -> Result<std::fs::Permissions> {
+        Ok(self.file.metadata()?.permissions())
+    }
+
+    /**
+    Borrows the device as a reader and a writer at once, e.g. to answer each
+    packet while still holding the reader.
+
+    Both halves are `&Device`, which implements [`Read`] and [`Write`] like
+    `&File` does, so the protocol information header is still stripped and
+    the read timeout still applies. Since the device is `Sync`, the halves
+    may also be moved to scoped threads.
+
+    # Examples
+
+    ```no_run
+    use std::io::{Read, Write};
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    let (mut reader, mut writer) = device.split_mut();
+    let mut buffer = [0; 1500];
+    let number = reader.read(&mut buffer).unwrap();
+    writer.write(&buffer[..number]).unwrap();
+    ```
+    */
+    pub fn split_mut(&mut self) -> (impl Read + '_, impl Write + '_) {
+        (&*self, &*self)
+    }
+
+    /**
+    Splits the device into its file and the name of its interface, the pair
+    returned by [`tun::OpenOptions::open`].
+
+    The `feth` interface of a Tap device emulated on macOS is then left to
+    the caller, as it is by `open`.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new().open_device(0).unwrap();
+    let (file, filename) = device.into_parts();
+    ```
+
+    [`tun::OpenOptions::open`]: tun/struct.OpenOptions.html#method.open
+    */
+    pub fn into_parts(self) -> (File, String) {
+        // The caller takes over the interface along with the file.
+        #[cfg(all(target_os = "macos", feature = "macos-feth"))]
+        mem::forget(self.feth);
+        (self.file, self.name)
+    }
+
+    /**
+    Closes the device, returning the error `close` reports, which dropping
+    it ignores.
+
+    The descriptor is released whatever the outcome, so the call is never
+    retried, not even after `EINTR`: on Linux, the descriptor number may
+    already belong to another file by then. Dropping the device remains
+    fine whenever close errors do not matter. When other descriptors of the
+    device are open, e.g. made with [`try_clone`] on its [`file`], this only
+    closes this one.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new().open_device(0).unwrap();
+    if let Err(error) = device.close() {
+        eprintln!("failed to close the device: {}", error);
+    }
+    ```
+
+    [`try_clone`]: https://doc.rust-lang.org/nightly/std/fs/struct.File.html#method.try_clone
+    [`file`]: struct.Device.html#method.file
+    */
+    pub fn close(self) -> Result<()> {
+        use std::os::unix::io::IntoRawFd;
+
+        let fd = self.file.into_raw_fd();
+        if unsafe { libc::close(fd) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /**
+    Returns the offloads enabled when the device was opened.
+
+    This is the intersection of the offloads requested with
+    [`tun::OpenOptions::offload`] and those supported by the kernel. It is
+    empty for devices which were not opened by this crate.
+
+    This method is only available on Linux.
+
+    [`tun::OpenOptions::offload`]: tun/struct.OpenOptions.html#method.offload
+    */
+    #[cfg(target_os = "linux")]
+    pub fn offload(&self) -> crate::tun::Offload {
+        self.offload
+    }
+
+    /**
+    Returns the `IFF_*` flags the device is attached with, as `TUNGETIFF`
+    reported them once it was opened: `IFF_TUN` or `IFF_TAP`, `IFF_NO_PI`,
+    `IFF_VNET_HDR`, `IFF_MULTI_QUEUE` and so on.
+
+    The kernel leaves out the flags it does not honor, so these tell which
+    options took effect. `TUNGETIFF` also reports `IFF_NOFILTER`, which
+    shares its value with `IFF_NO_PI`, whenever no packet filter is
+    attached; the `IFF_NO_PI` bit returned here is corrected to tell
+    whether packets really come without packet info. The flags are 0 for a
+    descriptor which does not answer `TUNGETIFF`.
+
+    Unlike [`flags`], these are not interface flags. This method is only
+    available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new().packet_info(false).open_device(0).unwrap();
+    assert!(device.tun_flags() & libc::IFF_NO_PI as libc::c_short != 0);
+    ```
+
+    [`flags`]: #method.flags
+    */
+    #[cfg(target_os = "linux")]
+    pub fn tun_flags(&self) -> libc::c_short {
+        self.tun_flags
+    }
+
+    /**
+    Lends the descriptor of the device as a [`BorrowedDevice`].
+
+    [`BorrowedDevice`]: struct.BorrowedDevice.html
+    */
+    pub fn as_borrowed(&self) -> crate::BorrowedDevice<'_> {
+        crate::BorrowedDevice::new(self.file.as_fd(), self.name.clone())
+    }
+
+    /**
+    Returns whether the device is in non-blocking mode, as read with
+    `F_GETFL`.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new().nonblock(true).open_device(0).unwrap();
+    assert!(device.is_nonblocking().unwrap());
+    ```
+    */
+    pub fn is_nonblocking(&self) -> Result<bool> {
+        let flags = unsafe { libc::fcntl(self.file.as_raw_fd(), libc::F_GETFL) };
+        if flags < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(flags & libc::O_NONBLOCK != 0)
+    }
+
+    /**
+    Switches the device in or out of non-blocking mode with `F_SETFL`,
+    whatever the device was opened with.
+
+    The same call works on every platform, whether the descriptor is a
+    character device, as on Linux and OpenBSD, or the utun control socket of
+    macOS, and it agrees with the [`nonblock`] option of the builders. In
+    non-blocking mode, reads fail with [`WouldBlock`] when no packet is
+    waiting.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new().open_device(0).unwrap();
+    device.set_nonblocking(true).unwrap();
+    ```
+
+    [`nonblock`]: tun/struct.OpenOptions.html#method.nonblock
+    [`WouldBlock`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.WouldBlock
+    */
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        let fd = self.file.as_raw_fd();
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+        if flags < 0 {
+            return Err(Error::last_os_error());
+        }
+        let flags = if nonblocking {
+            flags | libc::O_NONBLOCK
+        } else {
+            flags & !libc::O_NONBLOCK
+        };
+        if unsafe { libc::fcntl(fd, libc::F_SETFL, flags) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /**
+    Switches the device to signal-driven I/O: sets `O_ASYNC` with `F_SETFL`,
+    then makes `owner` receive `SIGIO` with `F_SETOWN`, whenever a packet
+    is waiting. `None` stands for the current process, a negative `owner`
+    for a process group. [`clear_signal_driven`] switches it back.
+
+    The default action of `SIGIO` terminates the process, so a handler has
+    to be installed first. This leaves `O_NONBLOCK` as it is: reactors
+    driven by `SIGIO` usually want it, see [`set_nonblocking`]. It works on
+    the utun socket of macOS and on the Tun/Tap devices of Linux, whose
+    driver supports `O_ASYNC`; elsewhere the flag may be accepted without
+    any signal ever being sent.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    // A `SIGIO` handler must be installed before.
+    let device = OpenOptions::new().open_device(0).unwrap();
+    device.set_signal_driven(None).unwrap();
+    ```
+
+    [`clear_signal_driven`]: struct.Device.html#method.clear_signal_driven
+    [`set_nonblocking`]: struct.Device.html#method.set_nonblocking
+    */
+    pub fn set_signal_driven(&self, owner: Option<libc::pid_t>) -> Result<()> {
+        set_signal_driven(&self.file, owner)
+    }
+
+    /**
+    Switches the device out of signal-driven I/O, clearing `O_ASYNC` with
+    `F_SETFL`, so that no more `SIGIO` is sent, whether it was set by
+    [`set_signal_driven`] or when opening. The owner is left as it is.
+
+    [`set_signal_driven`]: struct.Device.html#method.set_signal_driven
+    */
+    pub fn clear_signal_driven(&self) -> Result<()> {
+        set_async(&self.file, false)
+    }
+
+    /// Returns how long reads wait for a packet, as set by
+    /// [`set_read_timeout`](Self::set_read_timeout), or `None` when they
+    /// wait forever.
+    pub fn read_timeout(&self) -> Option<std::time::Duration> {
+        self.read_timeout
+    }
+
+    /**
+    Sets how long reads wait for a packet before failing with [`TimedOut`],
+    or lets them wait forever with `None`, the default.
+
+    Device files are no sockets, so `SO_RCVTIMEO` does not apply to them:
+    the device waits with `poll` before each read instead. This covers the
+    reads of `Device` and its packet helpers, not reads of the bare file,
+    e.g. from [`file`](Self::file). A device in non-blocking mode still
+    fails with [`WouldBlock`] once the timeout is over, never before.
+
+    # Errors
+
+    A zero `timeout` is rejected with [`InvalidInput`], as by
+    [`TcpStream::set_read_timeout`].
+
+    # Examples
+
+    ```no_run
+    use std::io::Read;
+    use std::time::Duration;
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    device.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    let mut buffer = [0; 1500];
+    match device.read(&mut buffer) {
+        Err(error) if error.kind() == std::io::ErrorKind::TimedOut => println!("idle"),
+        result => println!("{:?}", result),
+    }
+    ```
+
+    [`TimedOut`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.TimedOut
+    [`WouldBlock`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.WouldBlock
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    [`TcpStream::set_read_timeout`]: https://doc.rust-lang.org/nightly/std/net/struct.TcpStream.html#method.set_read_timeout
+    */
+    pub fn set_read_timeout(&mut self, timeout: Option<std::time::Duration>) -> Result<()> {
+        if timeout == Some(std::time::Duration::ZERO) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "cannot set a 0 duration timeout",
+            ));
+        }
+        self.read_timeout = timeout;
+        Ok(())
+    }
+
+    /// Waits for a packet to read for at most the read timeout, if any.
+    fn wait_readable(&self) -> Result<()> {
+        let Some(timeout) = self.read_timeout else {
+            return Ok(());
+        };
+        #[cfg(all(target_os = "macos", feature = "macos-feth"))]
+        if self.feth.as_ref().is_some_and(|feth| feth.has_pending()) {
+            return Ok(());
+        }
+        // Round up, so that a timeout below a millisecond still waits.
+        let millis = timeout.as_nanos().div_ceil(1_000_000);
+        let millis = millis.min(libc::c_int::MAX as u128) as libc::c_int;
+        let mut pollfd = libc::pollfd {
+            fd: self.file.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ready = crate::retry_eintr(|| match unsafe { libc::poll(&mut pollfd, 1, millis) } {
+            ready if ready < 0 => Err(Error::last_os_error()),
+            ready => Ok(ready),
+        })?;
+        if ready == 0 {
+            return Err(Error::new(
+                ErrorKind::TimedOut,
+                "no packet arrived before the read timeout",
+            ));
+        }
+        Ok(())
+    }
+
+    /**
+    Adds the device to the epoll instance `epfd` with `EPOLL_CTL_ADD`,
+    waiting for `events`, e.g. `EPOLLIN`, and tagging them with `token`.
+
+    The device is switched to non-blocking mode first, so a readiness loop
+    can read until [`WouldBlock`] without hanging, which edge-triggered
+    `EPOLLET` requires. This covers the single-descriptor case without a
+    reactor crate.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use std::io::Read;
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+    device.register_epoll(epfd, libc::EPOLLIN as u32, 7).unwrap();
+
+    let mut events = [libc::epoll_event { events: 0, u64: 0 }; 8];
+    let number = unsafe { libc::epoll_wait(epfd, events.as_mut_ptr(), 8, -1) };
+    let mut buffer = [0; 1500];
+    for event in &events[..number as usize] {
+        if event.u64 == 7 {
+            let number = device.read(&mut buffer).unwrap();
+        }
+    }
+    ```
+
+    [`WouldBlock`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.WouldBlock
+    */
+    #[cfg(target_os = "linux")]
+    pub fn register_epoll(&self, epfd: RawFd, events: u32, token: u64) -> Result<()> {
+        self.set_nonblocking(true)?;
+        self.epoll_ctl(epfd, libc::EPOLL_CTL_ADD, events, token)
+    }
+
+    /**
+    Changes the `events` and `token` the device was registered with in the
+    epoll instance `epfd`, with `EPOLL_CTL_MOD`.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new().open_device(0).unwrap();
+    let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+    device.register_epoll(epfd, libc::EPOLLIN as u32, 7).unwrap();
+    let events = libc::EPOLLIN | libc::EPOLLOUT;
+    device.modify_epoll(epfd, events as u32, 7).unwrap();
+    ```
+    */
+    #[cfg(target_os = "linux")]
+    pub fn modify_epoll(&self, epfd: RawFd, events: u32, token: u64) -> Result<()> {
+        self.epoll_ctl(epfd, libc::EPOLL_CTL_MOD, events, token)
+    }
+
+    /**
+    Removes the device from the epoll instance `epfd` with `EPOLL_CTL_DEL`.
+
+    The device stays in non-blocking mode.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new().open_device(0).unwrap();
+    let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+    device.register_epoll(epfd, libc::EPOLLIN as u32, 7).unwrap();
+    device.deregister_epoll(epfd).unwrap();
+    ```
+    */
+    #[cfg(target_os = "linux")]
+    pub fn deregister_epoll(&self, epfd: RawFd) -> Result<()> {
+        self.epoll_ctl(epfd, libc::EPOLL_CTL_DEL, 0, 0)
+    }
+
+    /// Issues `epoll_ctl` for the device with the operation `op`.
+    #[cfg(target_os = "linux")]
+    fn epoll_ctl(&self, epfd: RawFd, op: libc::c_int, events: u32, token: u64) -> Result<()> {
+        let mut event = libc::epoll_event { events, u64: token };
+        let err = unsafe { libc::epoll_ctl(epfd, op, self.file.as_raw_fd(), &mut event) };
+        if err < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /**
+    Returns the MTU of the interface.
+
+    This method is only available on Linux and macOS.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new().open_device(0).unwrap();
+    device.set_mtu(1420).unwrap();
+    assert_eq!(device.mtu().unwrap(), 1420);
+    ```
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn mtu(&self) -> Result<u32> {
+        crate::interface::get_mtu(&self.name)
+    }
+
+    /**
+    Sets the MTU of the interface.
+
+    This method is only available on Linux and macOS.
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn set_mtu(&self, mtu: u32) -> Result<()> {
+        crate::interface::set_mtu(&self.name, mtu)?;
+        self.mtu.store(mtu, std::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
+
+    /**
+    Returns the flags of the interface.
+
+    This method is only available on Linux and macOS.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new().open_device(0).unwrap();
+    device.set_up().unwrap();
+    assert!(device.flags().unwrap().is_up());
+    ```
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn flags(&self) -> Result<crate::interface::InterfaceFlags> {
+        crate::interface::get_flags(&self.name)
+    }
+
+    /**
+    Replaces the flags of the interface.
+
+    This method is only available on Linux and macOS.
+
+    # Examples
+
+    ```no_run
+    use utuntap::{interface::InterfaceFlags, tun::OpenOptions};
+
+    let device = OpenOptions::new().open_device(0).unwrap();
+    let flags = device.flags().unwrap();
+    device.set_flags(flags | InterfaceFlags::PROMISC).unwrap();
+    ```
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn set_flags(&self, flags: crate::interface::InterfaceFlags) -> Result<()> {
+        crate::interface::set_flags(&self.name, flags)
+    }
+
+    /**
+    Brings the interface up.
+
+    This method is only available on Linux and macOS.
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn set_up(&self) -> Result<()> {
+        crate::interface::set_up(&self.name)
+    }
+
+    /**
+    Brings the interface down.
+
+    This method is only available on Linux and macOS.
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn set_down(&self) -> Result<()> {
+        crate::interface::set_down(&self.name)
+    }
+
+    /**
+    Stops the interface from handing new packets to the device, while those
+    already queued can still be read, e.g. to drain a backlog gracefully.
+
+    The interface is brought down on both platforms, which needs
+    `CAP_NET_ADMIN` or root, like [`set_down`]. The kernel drops the routes
+    through a down interface; re-add them after [`resume`] if needed. On
+    macOS, `shutdown(fd, SHUT_RD)` on the utun socket is not used, as it
+    cannot be undone.
+
+    This method is only available on Linux and macOS.
+
+    # Examples
+
+    ```no_run
+    use std::io::{ErrorKind, Read};
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().nonblock(true).open_device(0).unwrap();
+    device.pause().unwrap();
+    let mut buffer = [0; 1504];
+    loop {
+        match device.read(&mut buffer) {
+            Ok(number) => println!("draining {} bytes", number),
+            Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+            Err(error) => panic!("{}", error),
+        }
+    }
+    device.resume().unwrap();
+    ```
+
+    [`set_down`]: #method.set_down
+    [`resume`]: #method.resume
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn pause(&self) -> Result<()> {
+        self.set_down()
+    }
+
+    /**
+    Lets the interface hand packets to the device again after [`pause`], by
+    bringing it up.
+
+    This method is only available on Linux and macOS.
+
+    [`pause`]: #method.pause
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn resume(&self) -> Result<()> {
+        self.set_up()
+    }
+
+    /**
+    Returns the size of a read buffer which fits any packet of the device.
+
+    This is the MTU of the interface plus the overhead read along with each
+    packet: the 4-byte packet info or address family header, the Ethernet
+    header of Tap devices and the virtio net header on Linux. When offloads
+    were negotiated, packets may be as large as 64 KiB regardless of the
+    MTU, and that size is returned instead.
+
+    This method is only available on Linux and macOS.
+
+    # Examples
+
+    ```no_run
+    use std::io::Read;
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    let mut buffer = vec![0; device.recommended_buffer_size().unwrap()];
+    let number = device.read(&mut buffer).unwrap();
+    ```
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn recommended_buffer_size(&self) -> Result<usize> {
+        #[allow(unused_mut)]
+        let mut size = self.mtu()? as usize;
+
+        #[cfg(target_os = "linux")]
+        {
+            use crate::interface::linux::{Request, IFF_VNET_HDR};
+
+            if !self.offload.is_empty() {
+                size = u16::MAX as usize;
+            }
+            let flags = Request::get_tuntap(self.file.as_raw_fd())?.flags();
+            if Mode::from_iff(flags) == Some(Mode::Tap) {
+                size += ETHERNET_HEADER_LEN;
+            }
+            if flags & IFF_VNET_HDR != 0 {
+                size += crate::tun::get_vnet_hdr_size(&self.file)? as usize;
+            }
+        }
+        #[cfg(all(target_os = "macos", feature = "macos-feth"))]
+        if self.feth.is_some() {
+            size += ETHERNET_HEADER_LEN;
+        }
+
+        if self.has_header() {
+            size += 4;
+        }
+        Ok(size)
+    }
+
+    /**
+    Asks the kernel whether the device is a Tun or a Tap device.
+
+    This works on devices adopted with [`from_fd`] or [`from_owned_fd`] too,
+    so the caller does not have to remember how the device was created.
+
+    # Errors
+
+    This function returns [`Unsupported`] on platforms without `TUNGETIFF`,
+    i.e. everywhere but Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::{Device, Mode};
+
+    let device = unsafe { Device::from_fd(3, "tun0".to_string()) };
+    match device.mode().unwrap() {
+        Mode::Tun => println!("IP packets"),
+        Mode::Tap => println!("Ethernet frames"),
+    }
+    ```
+
+    [`from_fd`]: struct.Device.html#method.from_fd
+    [`from_owned_fd`]: struct.Device.html#method.from_owned_fd
+    [`Unsupported`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.Unsupported
+    */
+    pub fn mode(&self) -> Result<Mode> {
+        mode_of(self.file.as_fd())
+    }
+
+    /**
+    Reads one packet into a newly allocated buffer of capacity `cap`.
+
+    The packet is read straight into the spare capacity of the buffer, so no
+    time is spent zeroing memory which is about to be overwritten. The
+    returned vector holds exactly the bytes of the packet.
+
+    # Errors
+
+    This function returns [`InvalidInput`] when `cap` is 0, as nothing could
+    be read into the buffer and an empty packet would look like the end of
+    file.
+
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    let packet = device.read_packet_uninit(1500).unwrap();
+    ```
+    */
+    pub fn read_packet_uninit(&mut self, cap: usize) -> Result<Vec<u8>> {
+        if cap == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "cannot read a packet into an empty buffer",
+            ));
+        }
+        self.wait_readable()?;
+        #[cfg(all(target_os = "macos", feature = "macos-feth"))]
+        if let Some(feth) = &self.feth {
+            let mut buffer = vec![0; cap];
+            let number = feth.read(&self.file, &mut buffer)?;
+            buffer.truncate(number);
+            return Ok(buffer);
+        }
+        let mut buffer = Vec::<u8>::with_capacity(cap);
+        let number = unsafe {
+            libc::read(
+                self.file.as_raw_fd(),
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.capacity(),
+            )
+        };
+        if number < 0 {
+            return Err(Error::last_os_error());
+        }
+        unsafe { buffer.set_len(number as usize) };
+        Ok(buffer)
+    }
+
+    /**
+    Reads one packet into a buffer the device keeps across reads, returning
+    the packet borrowed from it.
+
+    This is the read path which never allocates once the buffer exists, for
+    single-threaded readers; [`read_packet_uninit`] returns packets the
+    caller owns instead. The buffer is allocated by the first read, sized
+    with [`recommended_buffer_size`] on Linux and macOS and to 64 KiB
+    elsewhere, unless the device was opened with [`buffer_capacity`]. The
+    returned packet borrows the device, so it cannot be held across the next
+    read nor any other use of the device. A packet longer than the buffer is
+    truncated to it.
+
+    ```compile_fail
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    let first = device.read_frame_borrowed().unwrap();
+    let second = device.read_frame_borrowed().unwrap();
+    assert_ne!(first, second);
+    ```
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    loop {
+        let packet = device.read_frame_borrowed().unwrap();
+        println!("read {} bytes", packet.len());
+    }
+    ```
+
+    [`read_packet_uninit`]: struct.Device.html#method.read_packet_uninit
+    [`recommended_buffer_size`]: struct.Device.html#method.recommended_buffer_size
+    [`buffer_capacity`]: tun/struct.OpenOptions.html#method.buffer_capacity
+    */
+    pub fn read_frame_borrowed(&mut self) -> Result<&[u8]> {
+        if self.read_buffer.is_empty() {
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            let size = self.recommended_buffer_size()?;
+            #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+            let size = u16::MAX as usize;
+            self.read_buffer = vec![0; size];
+        }
+        // The buffer is taken out for `read` to borrow the whole device.
+        let mut buffer = mem::take(&mut self.read_buffer);
+        let result = self.read(&mut buffer);
+        self.read_buffer = buffer;
+        Ok(&self.read_buffer[..result?])
+    }
+
+    /**
+    Reads one packet into `buf` together with the time the kernel received
+    it, when the platform records one.
+
+    No supported platform does so today, and the timestamp is always
+    `None`: the Tun/Tap files of Linux and the BSDs are character devices,
+    which cannot be read with `recvmsg` and carry no `SO_TIMESTAMPNS`
+    ancillary data, and the utun control socket of macOS queues packets
+    without timestamps. The software timestamping that ethtool reports for
+    Tun/Tap interfaces applies to sockets on the host, not to this file.
+    Take [`SystemTime::now`] after the read for an approximation, which
+    includes the time the packet waited in the queue.
+
+    # Examples
+
+    ```no_run
+    use std::time::SystemTime;
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    let mut buffer = [0; 1500];
+    let (number, timestamp) = device.read_timestamped(&mut buffer).unwrap();
+    let received = timestamp.unwrap_or_else(SystemTime::now);
+    ```
+
+    [`SystemTime::now`]: https://doc.rust-lang.org/nightly/std/time/struct.SystemTime.html#method.now
+    */
+    pub fn read_timestamped(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<(usize, Option<std::time::SystemTime>)> {
+        Ok((self.read(buf)?, None))
+    }
+
+    /**
+    Reads the start of the next packet into `buf` without consuming it, so
+    the next read returns the packet again, e.g. to dispatch on its IP
+    version or EtherType first. Like [`read`], this strips the header in
+    front of the packet when the [`strip_protocol_info`] option is set.
+
+    Only the utun control socket of macOS supports this, with
+    `recv(MSG_PEEK)`. The Tun/Tap files of Linux and the BSDs are character
+    devices, which cannot be peeked, and so is the BPF device behind a Tap
+    device on macOS: this fails with [`Unsupported`] there, without touching
+    the queue. Read the packet into a buffer of your own instead.
+
+    # Examples
+
+    ```no_run
+    use std::io::Read;
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    let mut start = [0; 8];
+    device.peek(&mut start).unwrap();
+    let mut packet = [0; 1500];
+    let number = device.read(&mut packet).unwrap();
+    assert_eq!(packet[..8], start);
+    ```
+
+    [`read`]: https://doc.rust-lang.org/nightly/std/io/trait.Read.html#tymethod.read
+    [`strip_protocol_info`]: tun/struct.OpenOptions.html#method.strip_protocol_info
+    [`Unsupported`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.Unsupported
+    */
+    pub fn peek(&self, buf: &mut [u8]) -> Result<usize> {
+        #[cfg(target_os = "macos")]
+        {
+            let mut header = [0u8; 4];
+            let mut iov = [
+                libc::iovec {
+                    iov_base: header.as_mut_ptr() as *mut libc::c_void,
+                    iov_len: header.len(),
+                },
+                libc::iovec {
+                    iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+                    iov_len: buf.len(),
+                },
+            ];
+            let strip = self.strip_protocol_info.is_some();
+            let iov = if strip { &mut iov[..] } else { &mut iov[1..] };
+            let mut message: libc::msghdr = unsafe { mem::zeroed() };
+            message.msg_iov = iov.as_mut_ptr();
+            message.msg_iovlen = iov.len() as _;
+            let number = crate::retry_eintr(|| {
+                let number =
+                    unsafe { libc::recvmsg(self.as_raw_fd(), &mut message, libc::MSG_PEEK) };
+                if number < 0 {
+                    return Err(match Error::last_os_error() {
+                        error if error.raw_os_error() == Some(libc::ENOTSOCK) => {
+                            Error::new(ErrorKind::Unsupported, "the device cannot be peeked")
+                        }
+                        error => error,
+                    });
+                }
+                Ok(number as usize)
+            })?;
+            if !strip {
+                return Ok(number);
+            }
+            if number < header.len() {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "truncated packet header",
+                ));
+            }
+            Ok(number - header.len())
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = buf;
+            Err(Error::new(
+                ErrorKind::Unsupported,
+                "character devices cannot be peeked",
+            ))
+        }
+    }
+
+    /**
+    Reads one packet with a single `readv`, its first 4 bytes into `prefix`
+    and the rest into `payload`, returning how many bytes went into each.
+
+    This suits devices whose packets carry the packet info or address family
+    header: the payload lands at the start of its own buffer, with no need
+    to shift it past the header afterwards. Pass a slice like `buf[4..]` as
+    the payload to keep room for a header in front of it. The prefix is read
+    as is, whatever the [`strip_protocol_info`] option.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    let mut prefix = [0; 4];
+    let mut payload = [0; 1500];
+    let (_, len) = device.read_prefixed(&mut prefix, &mut payload).unwrap();
+    println!("{:?} in front of {} bytes", prefix, len);
+    ```
+
+    [`strip_protocol_info`]: tun/struct.OpenOptions.html#method.strip_protocol_info
+    */
+    pub fn read_prefixed(
+        &mut self,
+        prefix: &mut [u8; 4],
+        payload: &mut [u8],
+    ) -> Result<(usize, usize)> {
+        self.wait_readable()?;
+        let number = self
+            .file
+            .read_vectored(&mut [IoSliceMut::new(prefix), IoSliceMut::new(payload)])?;
+        Ok((number.min(4), number.saturating_sub(4)))
+    }
+
+    /**
+    Reads one packet of a device opened with `vnet_hdr`, returning its
+    virtio net header apart from the packet.
+
+    The header, of the size set on the device, and the packet are read with a
+    single `readv`, the header into a buffer on the stack, and the header is
+    parsed in the byte order set with [`tun::set_vnet_little_endian`]. Any
+    packet information in front is dropped. The packet buffer is as large as
+    [`recommended_buffer_size`] allows for.
+
+    # Errors
+
+    This function returns [`InvalidInput`] when the device carries no virtio
+    net header, and [`InvalidData`] when the read is shorter than the header.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::{tun, vnet};
+
+    let mut device = tun::OpenOptions::new().vnet_hdr(true).open_device(0).unwrap();
+    let (hdr, packet) = device.read_vnet().unwrap();
+    for packet in vnet::segment(&hdr, &packet).unwrap() {
+        // Handle each MTU-sized packet.
+    }
+    ```
+
+    [`tun::set_vnet_little_endian`]: tun/fn.set_vnet_little_endian.html
+    [`recommended_buffer_size`]: struct.Device.html#method.recommended_buffer_size
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    [`InvalidData`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidData
+    */
+    #[cfg(target_os = "linux")]
+    pub fn read_vnet(&mut self) -> Result<(crate::vnet::VnetHdr, Vec<u8>)> {
+        use crate::vnet::VnetHdr;
+
+        let (size, endianness) = self.vnet_layout()?;
+        let info_len = if self.packet_info { 4 } else { 0 };
+        let mut info = [0u8; 4];
+        let mut header = [0u8; MAX_VNET_HDR_LEN];
+        let cap = self.recommended_buffer_size()? - size - info_len;
+        let mut packet = Vec::<u8>::with_capacity(cap);
+        let iov = [
+            libc::iovec {
+                iov_base: info.as_mut_ptr() as *mut libc::c_void,
+                iov_len: info_len,
+            },
+            libc::iovec {
+                iov_base: header.as_mut_ptr() as *mut libc::c_void,
+                iov_len: size,
+            },
+            libc::iovec {
+                iov_base: packet.as_mut_ptr() as *mut libc::c_void,
+                iov_len: packet.capacity(),
+            },
+        ];
+        self.wait_readable()?;
+        let number = unsafe { libc::readv(self.file.as_raw_fd(), iov.as_ptr(), 3) };
+        if number < 0 {
+            return Err(Error::last_os_error());
+        }
+        let number = number as usize;
+        if number < info_len + size {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "the packet is shorter than the virtio net header",
+            ));
+        }
+        unsafe { packet.set_len(number - info_len - size) };
+        let hdr = VnetHdr::parse(&header[..size], endianness).expect("the header is complete");
+        Ok((hdr, packet))
+    }
+
+    /**
+    Writes `payload` behind the virtio net header `hdr` to a device opened
+    with `vnet_hdr`, returning the number of bytes of `payload` written.
+
+    This is the counterpart of [`read_vnet`]: the header is serialized in the
+    byte order and padded to the size set on the device, and written with the
+    packet in a single `writev`, behind the packet information if the device
+    carries it.
+
+    # Errors
+
+    This function returns [`InvalidInput`] when the device carries no virtio
+    net header.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::{tun, vnet::VnetHdr};
+
+    let mut device = tun::OpenOptions::new().vnet_hdr(true).open_device(0).unwrap();
+    let packet = [0x45, 0, 0, 20];
+    device.write_vnet(&VnetHdr::default(), &packet).unwrap();
+    ```
+
+    [`read_vnet`]: struct.Device.html#method.read_vnet
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    #[cfg(target_os = "linux")]
+    pub fn write_vnet(&mut self, hdr: &crate::vnet::VnetHdr, payload: &[u8]) -> Result<usize> {
+        use crate::vnet::VnetHdr;
+
+        let (size, endianness) = self.vnet_layout()?;
+        let info = if self.packet_info {
+            Some(Self::header_of(self.mode()?, payload)?)
+        } else {
+            None
+        };
+        let mut header = [0u8; MAX_VNET_HDR_LEN];
+        header[..VnetHdr::SIZE].copy_from_slice(&hdr.to_bytes(endianness));
+        let info = info.as_ref().map_or(&[][..], |info| &info[..]);
+        let number = self.file.write_vectored(&[
+            IoSlice::new(info),
+            IoSlice::new(&header[..size]),
+            IoSlice::new(payload),
+        ])?;
+        Ok(number.saturating_sub(info.len() + size))
+    }
+
+    /// Returns the size and byte order of the virtio net header of the
+    /// device.
+    #[cfg(target_os = "linux")]
+    fn vnet_layout(&self) -> Result<(usize, crate::vnet::Endianness)> {
+        use crate::interface::linux::{Request, IFF_VNET_HDR};
+
+        if Request::get_tuntap(self.file.as_raw_fd())?.flags() & IFF_VNET_HDR == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the device carries no virtio net header",
+            ));
+        }
+        let size = crate::tun::get_vnet_hdr_size(&self.file)? as usize;
+        if !(crate::vnet::VnetHdr::SIZE..=MAX_VNET_HDR_LEN).contains(&size) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "unexpected virtio net header size",
+            ));
+        }
+        Ok((size, crate::tun::get_vnet_endianness(&self.file)?))
+    }
+
+    /// Returns whether packets of the device are prefixed with a 4-byte
+    /// header: the packet information on Linux, the address family on
+    /// macOS and OpenBSD. illumos and Solaris packets carry none.
+    pub(crate) fn has_header(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        return self.packet_info;
+        #[cfg(any(target_os = "illumos", target_os = "solaris"))]
+        return false;
+        // Frames of the BPF device behind a `feth` interface carry none.
+        #[cfg(all(target_os = "macos", feature = "macos-feth"))]
+        return self.feth.is_none();
+        #[cfg(not(any(
+            target_os = "linux",
+            target_os = "illumos",
+            target_os = "solaris",
+            all(target_os = "macos", feature = "macos-feth")
+        )))]
+        return true;
+    }
+
+    /// Returns the 4-byte header which the device expects in front of
+    /// `packet`, a packet of a device of the given `mode`.
+    fn header_of(mode: Mode, packet: &[u8]) -> Result<[u8; 4]> {
+        match mode {
+            Mode::Tun => Ok(AddressFamily::of_packet(packet)?.header()),
+            Mode::Tap => {
+                // struct tun_pi: no flags, then the EtherType of the frame
+                let ethertype = packet.get(12..14).unwrap_or(&[0, 0]);
+                Ok([0, 0, ethertype[0], ethertype[1]])
+            }
+        }
+    }
+
+    /// Reads one Ethernet frame of at most `cap` bytes from a Tap device,
+    /// stripping the packet information on Linux.
+    #[cfg(feature = "smoltcp")]
+    pub(crate) fn read_ethernet(&mut self, cap: usize) -> Result<Vec<u8>> {
+        #[cfg(target_os = "linux")]
+        if self.packet_info {
+            let mut frame = self.read_packet_uninit(cap + 4)?;
+            frame.drain(..frame.len().min(4));
+            return Ok(frame);
+        }
+        self.read_packet_uninit(cap)
+    }
+
+    /// Writes one Ethernet `frame` to a Tap device, prefixed with the packet
+    /// information on Linux.
+    #[cfg(any(feature = "etherparse", feature = "smoltcp"))]
+    pub(crate) fn write_ethernet(&mut self, frame: &[u8]) -> Result<usize> {
+        #[cfg(target_os = "linux")]
+        if self.packet_info {
+            let header = Self::header_of(Mode::Tap, frame)?;
+            let number = self
+                .file
+                .write_vectored(&[IoSlice::new(&header), IoSlice::new(frame)])?;
+            return Ok(number.saturating_sub(header.len()));
+        }
+        self.file.write(frame)
+    }
+
+    /**
+    Reads one Ethernet frame from a Tap device into `buffer` and parses its
+    headers.
+
+    The buffer is resized in place to fit a frame as large as the MTU
+    allows, so it can be reused across calls without reallocating once it
+    has grown. It then holds the frame read, without the packet information
+    of Linux. The MTU is read once and cached, as by [`write_checked`].
+
+    This method is only available with the `etherparse` feature.
+
+    # Errors
+
+    This function returns [`InvalidData`] when the frame cannot be parsed.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    let mut buffer = Vec::new();
+    let headers = device.read_frame(&mut buffer).unwrap();
+    println!("{:?} carrying {} bytes", headers.link, headers.payload.len());
+    ```
+
+    [`InvalidData`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidData
+    [`write_checked`]: struct.Device.html#method.write_checked
+    */
+    #[cfg(feature = "etherparse")]
+    pub fn read_frame<'a>(
+        &mut self,
+        buffer: &'a mut Vec<u8>,
+    ) -> Result<etherparse::PacketHeaders<'a>> {
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        let mtu = self.cached_mtu().map_or(1500, |mtu| mtu as usize);
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        let mtu = 1500;
+        // The packet information of Linux is stripped here unless `read`
+        // does so already.
+        #[cfg(target_os = "linux")]
+        let header = if self.packet_info && self.strip_protocol_info.is_none() {
+            4
+        } else {
+            0
+        };
+        #[cfg(not(target_os = "linux"))]
+        let header = 0;
+        // The MTU excludes the Ethernet header and an optional VLAN tag.
+        buffer.resize(header + mtu + 18, 0);
+        let number = self.read(buffer)?;
+        buffer.truncate(number);
+        buffer.drain(..header.min(number));
+        etherparse::PacketHeaders::from_ethernet_slice(buffer)
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))
+    }
+
+    /**
+    Writes one Ethernet frame built by `build` to a Tap device, adding the
+    packet information of Linux. Returns the number of bytes of the frame
+    written.
+
+    `build` serializes the frame into the given buffer, typically by calling
+    `write` on an `etherparse::PacketBuilder`.
+
+    This method is only available with the `etherparse` feature.
+
+    # Errors
+
+    This function returns [`InvalidInput`] when `build` fails.
+
+    # Examples
+
+    ```no_run
+    use etherparse::PacketBuilder;
+    use utuntap::tap::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    let builder = PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12])
+        .ipv4([10, 10, 10, 1], [10, 10, 10, 2], 64)
+        .udp(2424, 4242);
+    device
+        .write_frame(|buffer| builder.write(buffer, &[1; 10]))
+        .unwrap();
+    ```
+
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    #[cfg(feature = "etherparse")]
+    pub fn write_frame<F>(&mut self, build: F) -> Result<usize>
+    where
+        F: FnOnce(&mut Vec<u8>) -> std::result::Result<(), etherparse::WriteError>,
+    {
+        let mut frame = Vec::new();
+        build(&mut frame).map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+        self.write_ethernet(&frame)
+    }
+
+    /// Returns the MTU of the interface, reading it only when it is not
+    /// cached yet.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn cached_mtu(&self) -> Result<u32> {
+        use std::sync::atomic::Ordering;
+
+        let mut mtu = self.mtu.load(Ordering::Relaxed);
+        if mtu == 0 {
+            mtu = self.mtu()?;
+            self.mtu.store(mtu, Ordering::Relaxed);
+        }
+        Ok(mtu)
+    }
+
+    /**
+    Writes `buf` like [`write`], but fails with [`InvalidInput`] instead of
+    issuing the `write` when the packet is larger than the MTU allows.
+
+    The kernels do not agree on oversized packets: Linux takes them and may
+    drop them later on the way, macOS may truncate or refuse them. This turns
+    them into one error that names the sizes. The allowance for `buf` is the
+    MTU, plus the 4-byte header when `buf` carries it, plus the Ethernet
+    header and a VLAN tag, 18 bytes, on Tap devices. Frames with a virtio
+    net header are written unchecked, as segmentation offload lets them
+    exceed the MTU.
+
+    The MTU is read once and cached; [`set_mtu`] updates the cache, but an
+    MTU changed by other means is only seen by a new `Device`.
+
+    This method is only available on Linux and macOS.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new()
+        .packet_info(false)
+        .open_device(0)
+        .unwrap();
+    device.set_mtu(1280).unwrap();
+    assert!(device.write_checked(&[0x45; 1500]).is_err());
+    ```
+
+    [`write`]: https://doc.rust-lang.org/nightly/std/io/trait.Write.html#tymethod.write
+    [`set_mtu`]: struct.Device.html#method.set_mtu
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn write_checked(&mut self, buf: &[u8]) -> Result<usize> {
+        #[cfg(target_os = "linux")]
+        let mode = match Mode::from_iff(self.tun_flags) {
+            _ if self.tun_flags & crate::interface::linux::IFF_VNET_HDR != 0 => {
+                return self.write(buf)
+            }
+            Some(mode) => mode,
+            None => self.mode()?,
+        };
+        // Only a device opened through a `feth` interface is a Tap device,
+        // whatever the name of an adopted or renamed one.
+        #[cfg(all(target_os = "macos", feature = "macos-feth"))]
+        let mode = if self.feth.is_some() {
+            Mode::Tap
+        } else {
+            Mode::Tun
+        };
+        #[cfg(all(target_os = "macos", not(feature = "macos-feth")))]
+        let mode = Mode::Tun;
+
+        let mtu = self.cached_mtu()?;
+        let mut limit = mtu as usize;
+        // Tap devices only carry a header with the packet info of Linux.
+        let has_header = self.has_header() && (cfg!(target_os = "linux") || mode == Mode::Tun);
+        if self.strip_protocol_info.is_none() && has_header {
+            limit += 4;
+        }
+        if mode == Mode::Tap {
+            limit += 18;
+        }
+        if buf.len() > limit {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "a packet of {} bytes exceeds the {} bytes the MTU of {} allows",
+                    buf.len(),
+                    limit,
+                    mtu
+                ),
+            ));
+        }
+        self.write(buf)
+    }
+
+    /**
+    Writes `buf` like [`write`], but fails with [`InvalidInput`] instead of
+    issuing the `write` when `buf` lacks the packet information the device
+    was opened with.
+
+    A device opened with [`packet_info`] expects each packet to start with a
+    4-byte `struct tun_pi`. A bare packet written to it is taken as such a
+    header followed by a shorter packet, which the kernel silently drops.
+    This checks that `buf` holds at least the header and, on Tun devices,
+    that its protocol is the EtherType of IPv4, IPv6 or ARP, the protocols
+    [`Protocol`] knows. The kernel ignores the protocol of Tap frames, so
+    only the length of those is checked. Devices without packet information,
+    or whose header is added by [`strip_protocol_info`], are written to
+    unchecked.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().packet_info(true).open_device(0).unwrap();
+    let packet = [0x45, 0, 0, 20, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2];
+    // The packet information is missing.
+    assert!(device.write_guarded(&packet).is_err());
+    ```
+
+    [`write`]: https://doc.rust-lang.org/nightly/std/io/trait.Write.html#tymethod.write
+    [`packet_info`]: tun/struct.OpenOptions.html#method.packet_info
+    [`strip_protocol_info`]: tun/struct.OpenOptions.html#method.strip_protocol_info
+    [`Protocol`]: packet_info/enum.Protocol.html
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    #[cfg(target_os = "linux")]
+    pub fn write_guarded(&mut self, buf: &[u8]) -> Result<usize> {
+        if !self.packet_info || self.strip_protocol_info.is_some() {
+            return self.write(buf);
+        }
+        let Some(&header) = buf.first_chunk::<4>() else {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "a packet of {} bytes cannot hold the packet information of {}",
+                    buf.len(),
+                    self.name
+                ),
+            ));
+        };
+        let mode = match Mode::from_iff(self.tun_flags) {
+            Some(mode) => mode,
+            None => self.mode()?,
+        };
+        if mode == Mode::Tun && Protocol::from_packet_info(header).is_none() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "unknown protocol {:#06x} in the packet information, {} expects it in front \
+                     of packets",
+                    u16::from_be_bytes([header[2], header[3]]),
+                    self.name
+                ),
+            ));
+        }
+        self.write(buf)
+    }
+
+    /**
+    Writes the IP `packet` of the given `family` to a Tun device, prefixed
+    with whatever the platform expects, in a single `write`.
+
+    On Linux, the packet information header is prepended only when the device
+    was opened with it; macOS and OpenBSD always expect the address family in
+    front of the packet. Returns the number of bytes of `packet` written.
+
+    # Examples
+
+    ```no_run
+    use utuntap::{tun::OpenOptions, AddressFamily};
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    let packet = [0x45, 0, 0, 20, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2];
+    device.write_packet(AddressFamily::Inet, &packet).unwrap();
+    ```
+    */
+    pub fn write_packet(&mut self, family: AddressFamily, packet: &[u8]) -> Result<usize> {
+        if !self.has_header() {
+            return self.file.write(packet);
+        }
+        let header = family.header();
+        let number = self
+            .file
+            .write_vectored(&[IoSlice::new(&header), IoSlice::new(packet)])?;
+        Ok(number.saturating_sub(header.len()))
+    }
+
+    /**
+    Writes the IP `packet` to a Tun device like [`write_packet`], taking its
+    family from the version in the first nibble.
+
+    This builds the header the platform expects without the caller having
+    to track the family: the packet information with the EtherType of the
+    packet on Linux, when the device was opened with it, or the address
+    family on macOS and OpenBSD. A wrong EtherType makes the kernel drop the
+    packet silently, which this rules out. Ethernet frames of Tap devices
+    are written as they are with [`write`], or with the packet information
+    prepended by the [`strip_protocol_info`] option.
+
+    # Errors
+
+    This function returns [`InvalidData`] when `packet` is neither an IPv4
+    nor an IPv6 packet.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().packet_info(true).open_device(0).unwrap();
+    let packet = [0x45, 0, 0, 20, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2];
+    device.write_ip(&packet).unwrap();
+    ```
+
+    [`write_packet`]: struct.Device.html#method.write_packet
+    [`write`]: https://doc.rust-lang.org/nightly/std/io/trait.Write.html#tymethod.write
+    [`strip_protocol_info`]: tun/struct.OpenOptions.html#method.strip_protocol_info
+    [`InvalidData`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidData
+    */
+    pub fn write_ip(&mut self, packet: &[u8]) -> Result<usize> {
+        self.write_packet(AddressFamily::of_packet(packet)?, packet)
+    }
+
+    /**
+    Writes `packets` in order until the device would block, returning how
+    many of them were written.
+
+    Each packet is handed to the device in a single `write`, including any
+    header the device expects, and Tun/Tap writes either take a whole packet
+    or fail, so no packet is ever partially written. This is meant for
+    draining a send queue to a non-blocking device: the packets past the
+    returned count can be queued again once the device is writable.
+
+    # Errors
+
+    An error other than [`WouldBlock`] is returned only when no packet was
+    written; otherwise the count so far is returned and the error shows up
+    again on the next write.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().nonblock(true).open_device(0).unwrap();
+    let queue: Vec<Vec<u8>> = Vec::new();
+    let packets: Vec<&[u8]> = queue.iter().map(Vec::as_slice).collect();
+    let written = device.write_batch(&packets).unwrap();
+    println!("{} packets left to send", packets.len() - written);
+    ```
+
+    [`WouldBlock`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.WouldBlock
+    */
+    pub fn write_batch(&mut self, packets: &[&[u8]]) -> Result<usize> {
+        for (count, packet) in packets.iter().enumerate() {
+            match self.write(packet) {
+                Ok(_) => {}
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(count),
+                Err(error) if count == 0 => return Err(error),
+                Err(_) => return Ok(count),
+            }
+        }
+        Ok(packets.len())
+    }
+
+    /**
+    Writes `buf` as exactly one packet, failing unless the device took all of
+    it.
+
+    Unlike [`write_all`], this never writes the rest of a packet in a second
+    `write`, which the device would take as a packet of its own. An
+    interrupted write is retried, as nothing was written then.
+
+    # Errors
+
+    This function returns [`WouldBlock`] when a non-blocking device cannot
+    take the packet yet, so it can be written again once the device is
+    writable, [`WriteZero`] when the device took only part of the packet,
+    and [`InvalidInput`] when `buf` is empty.
+
+    # Examples
+
+    ```no_run
+    use std::io::ErrorKind;
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().nonblock(true).open_device(0).unwrap();
+    let packet = [0x45, 0, 0, 20];
+    match device.write_all_packet(&packet) {
+        Err(error) if error.kind() == ErrorKind::WouldBlock => println!("try again later"),
+        result => result.unwrap(),
+    }
+    ```
+
+    [`write_all`]: https://doc.rust-lang.org/nightly/std/io/trait.Write.html#method.write_all
+    [`WouldBlock`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.WouldBlock
+    [`WriteZero`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.WriteZero
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    pub fn write_all_packet(&mut self, buf: &[u8]) -> Result<()> {
+        if buf.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "cannot write an empty packet",
+            ));
+        }
+        loop {
+            match self.write(buf) {
+                Ok(number) if number == buf.len() => return Ok(()),
+                Ok(_) => {
+                    return Err(Error::new(
+                        ErrorKind::WriteZero,
+                        "the device took only part of the packet",
+                    ))
+                }
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /**
+    Reads one IP packet of at most `cap` bytes from a Tun device, stripping
+    the platform header and returning the family of the packet with it.
+
+    When the device carries no header, the family is taken from the version
+    field of the packet.
+
+    # Errors
+
+    This function returns [`InvalidData`] when the family of the packet is
+    neither IPv4 nor IPv6, and [`InvalidInput`] when `cap` is 0.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new().open_device(0).unwrap();
+    let (family, packet) = device.read_packet(1500).unwrap();
+    println!("{:?} packet of {} bytes", family, packet.len());
+    ```
+
+    [`InvalidData`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidData
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    pub fn read_packet(&mut self, cap: usize) -> Result<(AddressFamily, Vec<u8>)> {
+        if !self.has_header() || cap == 0 {
+            let packet = self.read_packet_uninit(cap)?;
+            return Ok((AddressFamily::of_packet(&packet)?, packet));
+        }
+        let mut packet = self.read_packet_uninit(cap + 4)?;
+        if packet.len() < 4 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "truncated packet header",
+            ));
+        }
+        let family = AddressFamily::from_header([packet[0], packet[1], packet[2], packet[3]])?;
+        packet.drain(..4);
+        Ok((family, packet))
+    }
+}
+
+/// Replaces the `IFF_NO_PI` bit of `flags` from `TUNGETIFF`, which may be
+/// `IFF_NOFILTER`, with whether the device carries packet info.
+#[cfg(target_os = "linux")]
+fn with_packet_info(flags: libc::c_short, packet_info: bool) -> libc::c_short {
+    use crate::interface::linux::IFF_NO_PI;
+
+    if packet_info {
+        flags & !IFF_NO_PI
+    } else {
+        flags | IFF_NO_PI
+    }
+}
+
+/// Switches `file` to signal-driven I/O, as [`Device::set_signal_driven`]
+/// does.
+pub(crate) fn set_signal_driven(file: &File, owner: Option<libc::pid_t>) -> Result<()> {
+    set_async(file, true)?;
+    // Linux makes the caller the owner when `O_ASYNC` is set on a Tun/Tap
+    // device, so the owner is set afterwards.
+    let owner = owner.unwrap_or_else(|| unsafe { libc::getpid() });
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_SETOWN, owner) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Sets or clears `O_ASYNC` on `file`.
+fn set_async(file: &File, enabled: bool) -> Result<()> {
+    let fd = file.as_raw_fd();
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags < 0 {
+        return Err(Error::last_os_error());
+    }
+    let flags = if enabled {
+        flags | libc::O_ASYNC
+    } else {
+        flags & !libc::O_ASYNC
+    };
+    if unsafe { libc::fcntl(fd, libc::F_SETFL, flags) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Asks the kernel whether `fd` is a Tun or a Tap device.
+pub(crate) fn mode_of(fd: BorrowedFd<'_>) -> Result<Mode> {
+    #[cfg(target_os = "linux")]
+    {
+        let flags = crate::interface::linux::Request::get_tuntap(fd.as_raw_fd())?.flags();
+        Mode::from_iff(flags)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "unknown device mode"))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = fd;
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "the device mode cannot be queried on this platform",
+        ))
+    }
+}
+
+impl Read for Device {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (&*self).read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> Result<usize> {
+        (&*self).read_vectored(bufs)
+    }
+}
+
+impl Read for &Device {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.wait_readable()?;
+        #[cfg(all(target_os = "macos", feature = "macos-feth"))]
+        if let Some(feth) = &self.feth {
+            return feth.read(&self.file, buf);
+        }
+        let mut file = &self.file;
+        if self.strip_protocol_info.is_none() {
+            return file.read(buf);
+        }
+        let mut header = [0; 4];
+        let number =
+            file.read_vectored(&mut [IoSliceMut::new(&mut header), IoSliceMut::new(buf)])?;
+        if number < header.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "truncated packet header",
+            ));
+        }
+        Ok(number - header.len())
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> Result<usize> {
+        if self.strip_protocol_info.is_some() {
+            self.wait_readable()?;
+            // The header is read into a buffer of its own in front of
+            // `bufs`, with the same single `readv`.
+            let mut header = [0; 4];
+            let mut iovecs = Vec::with_capacity(bufs.len() + 1);
+            iovecs.push(IoSliceMut::new(&mut header));
+            iovecs.extend(bufs.iter_mut().map(|buf| IoSliceMut::new(buf)));
+            let number = (&self.file).read_vectored(&mut iovecs)?;
+            if number < header.len() {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "truncated packet header",
+                ));
+            }
+            return Ok(number - header.len());
+        }
+        self.wait_readable()?;
+        #[cfg(all(target_os = "macos", feature = "macos-feth"))]
+        if let Some(feth) = &self.feth {
+            return feth.read_vectored(&self.file, bufs);
+        }
+        (&self.file).read_vectored(bufs)
+    }
+}
+
+impl Write for Device {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (&*self).write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+        (&*self).write_vectored(bufs)
+    }
+
+    /// Does nothing, see the implementation for `&Device`.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for &Device {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let mut file = &self.file;
+        let mode = match self.strip_protocol_info {
+            Some(mode) => mode,
+            None => return file.write(buf),
+        };
+        let header = Device::header_of(mode, buf)?;
+        let number = file.write_vectored(&[IoSlice::new(&header), IoSlice::new(buf)])?;
+        Ok(number.saturating_sub(header.len()))
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+        let mode = match self.strip_protocol_info {
+            Some(mode) => mode,
+            None => return (&self.file).write_vectored(bufs),
+        };
+        // The header depends on the first 14 bytes at most, the Ethernet
+        // header of a frame, which may span several slices.
+        let mut start = [0; 14];
+        let mut length = 0;
+        for buf in bufs {
+            let number = buf.len().min(start.len() - length);
+            start[length..length + number].copy_from_slice(&buf[..number]);
+            length += number;
+        }
+        let header = Device::header_of(mode, &start[..length])?;
+        // The packet goes out whole with a single `writev`.
+        let mut iovecs = Vec::with_capacity(bufs.len() + 1);
+        iovecs.push(IoSlice::new(&header));
+        iovecs.extend_from_slice(bufs);
+        let number = (&self.file).write_vectored(&iovecs)?;
+        Ok(number.saturating_sub(header.len()))
+    }
+
+    /// Does nothing: packets are written whole by [`write`](Self::write), so
+    /// there is nothing to flush, and devices do not support `fsync`.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl From<Device> for (File, String) {
+    fn from(device: Device) -> Self {
+        device.into_parts()
+    }
+}
+
+impl AsRawFd for Device {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl AsFd for Device {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
@@ -0,0 +1,187 @@
+//! Tap devices emulated on macOS with fake ethernet interfaces
+//!
+//! Frames are read and written through a BPF device bound to a `feth`
+//! interface. Reads of a BPF device take a buffer of exactly the size
+//! `BIOCGBLEN` reports and return as many frames as are waiting, each
+//! prefixed with a `struct bpf_hdr`, so [`Feth`] keeps them and hands them
+//! out one at a time.
+
+use crate::interface::macos::{self, Request};
+use libc::{c_uint, c_ulong, ioctl};
+use std::fs::File;
+use std::io::{Error, ErrorKind, IoSliceMut, Read, Result};
+use std::os::fd::AsRawFd;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const BIOCGBLEN: c_ulong = 0x40044266;
+const BIOCSETIF: c_ulong = 0x8020426c;
+const BIOCIMMEDIATE: c_ulong = 0x80044270;
+const BIOCSHDRCMPLT: c_ulong = 0x80044275;
+const BIOCSSEESENT: c_ulong = 0x80044277;
+
+/// How many `/dev/bpfN` nodes are tried, the default of `debug.bpf_maxdevices`.
+const BPF_NODES: u32 = 256;
+
+/// Offsets in `struct bpf_hdr`, whose timestamp is a `struct timeval32`.
+const BH_CAPLEN: usize = 8;
+const BH_HDRLEN: usize = 16;
+const BPF_HDR_LEN: usize = 18;
+/// `BPF_ALIGNMENT`, the size of an `int32_t`.
+const BPF_ALIGNMENT: usize = 4;
+
+/// The `feth` interface of an emulated Tap device, destroyed when dropped,
+/// along with the BPF records read but not returned yet.
+#[derive(Debug)]
+pub(crate) struct Feth {
+    name: String,
+    records: Mutex<Records>,
+}
+
+/// A BPF buffer and the range of it which holds records not yet returned.
+#[derive(Debug)]
+struct Records {
+    buffer: Vec<u8>,
+    start: usize,
+    end: usize,
+}
+
+impl Feth {
+    /// Takes over the interface `name`, destroying it when dropped.
+    pub(crate) fn new(name: String) -> Self {
+        Self {
+            name,
+            records: Mutex::new(Records {
+                buffer: Vec::new(),
+                start: 0,
+                end: 0,
+            }),
+        }
+    }
+
+    /// Returns whether frames already read from the BPF device are waiting
+    /// to be returned, so that reading does not have to wait for more.
+    pub(crate) fn has_pending(&self) -> bool {
+        let records = self
+            .records
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        records.start < records.end
+    }
+
+    /// Copies the next frame into `buf`, reading from `file` when none is
+    /// left. A frame longer than `buf` is truncated to it.
+    pub(crate) fn read(&self, file: &File, buf: &mut [u8]) -> Result<usize> {
+        self.read_vectored(file, &mut [IoSliceMut::new(buf)])
+    }
+
+    /// Scatters the next frame across `bufs`, as [`read`](Self::read) does.
+    pub(crate) fn read_vectored(
+        &self,
+        mut file: &File,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Result<usize> {
+        let mut records = self
+            .records
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let Records { buffer, start, end } = &mut *records;
+        if buffer.is_empty() {
+            *buffer = vec![0; buffer_len(file)?];
+        }
+        if *start >= *end {
+            *end = file.read(buffer)?;
+            *start = 0;
+            if *end == 0 {
+                return Ok(0);
+            }
+        }
+        let record = &buffer[*start..*end];
+        if record.len() < BPF_HDR_LEN {
+            *start = *end;
+            return Err(Error::new(ErrorKind::InvalidData, "truncated bpf header"));
+        }
+        let caplen = u32::from_ne_bytes(record[BH_CAPLEN..BH_CAPLEN + 4].try_into().unwrap());
+        let caplen = caplen as usize;
+        let hdrlen = u16::from_ne_bytes([record[BH_HDRLEN], record[BH_HDRLEN + 1]]) as usize;
+        let Some(frame) = record.get(hdrlen..hdrlen + caplen) else {
+            *start = *end;
+            return Err(Error::new(ErrorKind::InvalidData, "truncated bpf record"));
+        };
+        let mut number = 0;
+        for buf in bufs {
+            let length = buf.len().min(frame.len() - number);
+            buf[..length].copy_from_slice(&frame[number..number + length]);
+            number += length;
+        }
+        // BPF_WORDALIGN: records start on a 4-byte boundary.
+        *start += (hdrlen + caplen).next_multiple_of(BPF_ALIGNMENT);
+        Ok(number)
+    }
+}
+
+impl Drop for Feth {
+    fn drop(&mut self) {
+        let _ = macos::destroy(&self.name);
+    }
+}
+
+/// Returns the size of the buffers reads of the BPF device `file` take,
+/// with `BIOCGBLEN`.
+fn buffer_len(file: &File) -> Result<usize> {
+    let mut length: c_uint = 0;
+    crate::retry_eintr(|| {
+        let err = unsafe { ioctl(file.as_raw_fd(), BIOCGBLEN, &mut length) };
+        crate::trace::check("BIOCGBLEN", "bpf", err)
+    })?;
+    Ok(length as usize)
+}
+
+/// Opens the first free BPF device, trying `/dev/bpf0` to `/dev/bpf255` in
+/// turn, as macOS has no cloning `/dev/bpf`, and returns it with its node.
+fn open_bpf(read: bool, write: bool) -> Result<(File, PathBuf)> {
+    let mut options = std::fs::OpenOptions::new();
+    options.read(read).write(write);
+    let mut last_error = None;
+    for number in 0..BPF_NODES {
+        let path = format!("/dev/bpf{}", number);
+        match crate::trace::traced("open", &path, options.open(&path)) {
+            Ok(file) => return Ok((file, path.into())),
+            // Taken by another process, try the next one.
+            Err(error) if error.raw_os_error() == Some(libc::EBUSY) => last_error = Some(error),
+            Err(error) if error.kind() == ErrorKind::NotFound => break,
+            Err(error) => return Err(error),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| Error::new(ErrorKind::NotFound, "no bpf device found")))
+}
+
+/// Opens a BPF device and binds it to the interface `name`, so that reads
+/// return the frames the interface receives and writes send bare frames
+/// out of it. The node of the BPF device is returned along with it.
+pub(crate) fn bind(name: &str, read: bool, write: bool, nonblock: bool) -> Result<(File, PathBuf)> {
+    let (file, node) = open_bpf(read, write)?;
+    let fd = file.as_raw_fd();
+
+    let mut request = Request::new(name)?;
+    crate::trace::traced("BIOCSETIF", name, request.ioctl(fd, BIOCSETIF))?;
+    // Frames are returned as soon as they arrive, written frames keep the
+    // source address they carry, and frames sent out of the interface, e.g.
+    // the ones written here, are not read back.
+    for (request, value) in [(BIOCIMMEDIATE, 1), (BIOCSHDRCMPLT, 1), (BIOCSSEESENT, 0)] {
+        let mut value: c_uint = value;
+        crate::retry_eintr(|| {
+            let err = unsafe { ioctl(fd, request, &mut value) };
+            crate::trace::check("ioctl", name, err)
+        })?;
+    }
+
+    if nonblock {
+        crate::retry_eintr(|| {
+            let err = unsafe { libc::fcntl(fd, libc::F_SETFL, libc::O_NONBLOCK) };
+            crate::trace::check("fcntl", name, err)
+        })?;
+    }
+
+    Ok((file, node))
+}
@@ -0,0 +1,84 @@
+//! A device which removes its interface when dropped
+
+use crate::Device;
+use std::ops::{Deref, DerefMut};
+
+/**
+A [`Device`] whose interface is removed when the guard is dropped, even when
+unwinding from a panic.
+
+On Linux, dropping the guard clears the persistent flag of the interface
+with `TUNSETPERSIST` before closing the device, so the kernel removes the
+interface as soon as no other file holds it. Elsewhere the device is only
+closed. Errors while cleaning up are ignored, as there is no caller left to
+report them to.
+
+The guard dereferences to the device, so it can be used in place of it.
+
+[`Device`]: struct.Device.html
+
+# Examples
+
+```no_run
+use std::io::Write;
+use utuntap::{tun::OpenOptions, DeviceGuard};
+
+let mut device = DeviceGuard::new(OpenOptions::new().open_device(None).unwrap());
+device.write(&[0; 20]).unwrap();
+// The interface is gone once `device` goes out of scope.
+```
+*/
+#[derive(Debug)]
+pub struct DeviceGuard {
+    device: Option<Device>,
+}
+
+impl DeviceGuard {
+    /// Guards `device`, removing its interface on drop.
+    pub fn new(device: Device) -> Self {
+        Self {
+            device: Some(device),
+        }
+    }
+
+    /// Defuses the guard, returning the device with its interface left
+    /// untouched.
+    pub fn into_inner(mut self) -> Device {
+        self.device
+            .take()
+            .expect("the guard holds a device until dropped")
+    }
+}
+
+impl Deref for DeviceGuard {
+    type Target = Device;
+
+    fn deref(&self) -> &Device {
+        self.device
+            .as_ref()
+            .expect("the guard holds a device until dropped")
+    }
+}
+
+impl DerefMut for DeviceGuard {
+    fn deref_mut(&mut self) -> &mut Device {
+        self.device
+            .as_mut()
+            .expect("the guard holds a device until dropped")
+    }
+}
+
+impl From<Device> for DeviceGuard {
+    fn from(device: Device) -> Self {
+        Self::new(device)
+    }
+}
+
+impl Drop for DeviceGuard {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        if let Some(device) = &self.device {
+            let _ = crate::tun::set_persist(device.file(), false);
+        }
+    }
+}
@@ -0,0 +1,1383 @@
+use super::{InterfaceConfigurator, InterfaceFlags, OperState, RingParams, Stats};
+use crate::ioctl::{tun_ioctl, TUNGETIFF, TUNSETIFF, TUNSETQUEUE};
+use libc::{
+    c_int, c_short, c_ulong, ifreq, AF_INET, IFNAMSIZ, SIOCGIFFLAGS, SIOCGIFINDEX, SIOCGIFMTU,
+    SIOCSIFADDR, SIOCSIFFLAGS, SIOCSIFMTU, SIOCSIFNAME, SIOCSIFNETMASK, SOCK_CLOEXEC, SOCK_DGRAM,
+};
+use std::io::{Error, ErrorKind, Result};
+use std::mem;
+use std::net::Ipv4Addr;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::{Path, PathBuf};
+
+pub(crate) const IFF_TUN: c_short = 0x0001;
+pub(crate) const IFF_TAP: c_short = 0x0002;
+pub(crate) const IFF_NAPI: c_short = 0x0010;
+pub(crate) const IFF_NAPI_FRAGS: c_short = 0x0020;
+pub(crate) const IFF_MULTI_QUEUE: c_short = 0x0100;
+pub(crate) const IFF_DETACH_QUEUE: c_short = 0x0400;
+pub(crate) const IFF_PERSIST: c_short = 0x0800;
+pub(crate) const IFF_NO_PI: c_short = 0x1000;
+pub(crate) const IFF_VNET_HDR: c_short = 0x4000;
+pub(crate) const IFF_TUN_EXCL: c_short = 0x8000u16 as c_short;
+
+/**
+An `ifreq` addressed to a named interface, the argument of most interface
+`ioctl`s.
+
+This is exported as [`ioctl::IfReq`] for requests this crate does not wrap:
+the name is checked and set by [`new`], the payload is written through the
+union returned by [`ifreq_mut`], and the request is issued with
+[`ioctl::ifreq_ioctl`].
+
+[`ioctl::IfReq`]: ../ioctl/struct.IfReq.html
+[`ioctl::ifreq_ioctl`]: ../ioctl/fn.ifreq_ioctl.html
+[`new`]: #method.new
+[`ifreq_mut`]: #method.ifreq_mut
+*/
+pub struct Request {
+    ifreq: ifreq,
+}
+
+impl Request {
+    /// Creates a request for the interface `name`.
+    ///
+    /// An empty `name` lets the kernel pick one where the request allows it.
+    /// A name of `IFNAMSIZ` (16) bytes or longer, or containing a NUL, is
+    /// rejected with `InvalidInput`.
+    pub fn new(name: &str) -> Result<Self> {
+        if name.len() >= IFNAMSIZ || name.bytes().any(|byte| byte == 0) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid interface name",
+            ));
+        }
+        let mut ifreq: ifreq = unsafe { mem::zeroed() };
+        for (dst, src) in ifreq.ifr_name.iter_mut().zip(name.bytes()) {
+            *dst = src as _;
+        }
+        Ok(Self { ifreq })
+    }
+
+    /// Creates a request for the interface `name` carrying `flags`.
+    pub fn with_flags(name: &str, flags: c_short) -> Result<Self> {
+        let mut request = Self::new(name)?;
+        request.ifreq.ifr_ifru.ifru_flags = flags;
+        Ok(request)
+    }
+
+    /// Returns the interface name carried by the request.
+    pub fn name(&self) -> Result<String> {
+        let bytes: Vec<u8> = self
+            .ifreq
+            .ifr_name
+            .iter()
+            .take_while(|&&byte| byte != 0)
+            .map(|&byte| byte as u8)
+            .collect();
+        String::from_utf8(bytes).map_err(|error| Error::new(ErrorKind::InvalidData, error))
+    }
+
+    /// Copies the interface name carried by the request into `buf`,
+    /// returning its length.
+    pub(crate) fn copy_name(&self, buf: &mut [u8]) -> Result<usize> {
+        let len = self
+            .ifreq
+            .ifr_name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(IFNAMSIZ);
+        let buf = buf
+            .get_mut(..len)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "the name buffer is too short"))?;
+        for (dst, &src) in buf.iter_mut().zip(self.ifreq.ifr_name.iter()) {
+            *dst = src as u8;
+        }
+        Ok(len)
+    }
+
+    /// Attaches `fd` to the Tun/Tap interface described by the request,
+    /// leaving the name of the interface as resolved by the kernel in it.
+    pub(crate) fn set_tuntap(mut self, fd: RawFd) -> Result<Self> {
+        unsafe { tun_ioctl(fd, TUNSETIFF, &mut self.ifreq)? };
+        Ok(self)
+    }
+
+    /// Detaches the queue `fd` from its multi-queue interface, which then
+    /// stops sending packets to it, while packets written to it still go
+    /// through.
+    pub(crate) fn detach_queue(fd: RawFd) -> Result<()> {
+        let mut ifreq: ifreq = unsafe { mem::zeroed() };
+        ifreq.ifr_ifru.ifru_flags = IFF_DETACH_QUEUE;
+        unsafe { tun_ioctl(fd, TUNSETQUEUE, &mut ifreq) }
+    }
+
+    /// Queries the name and flags of the Tun/Tap interface `fd` is attached
+    /// to.
+    pub(crate) fn get_tuntap(fd: RawFd) -> Result<Self> {
+        let mut ifreq: ifreq = unsafe { mem::zeroed() };
+        unsafe { tun_ioctl(fd, TUNGETIFF, &mut ifreq)? };
+        Ok(Self { ifreq })
+    }
+
+    /// Returns the flags carried by the request.
+    pub fn flags(&self) -> c_short {
+        unsafe { self.ifreq.ifr_ifru.ifru_flags }
+    }
+
+    /// Sets the flags carried by the request.
+    pub fn set_flags(&mut self, flags: c_short) {
+        self.ifreq.ifr_ifru.ifru_flags = flags;
+    }
+
+    /// Returns the underlying `ifreq`, e.g. to read the payload filled in by
+    /// the kernel.
+    pub fn ifreq(&self) -> &ifreq {
+        &self.ifreq
+    }
+
+    /// Returns the underlying `ifreq`, e.g. to write the payload of a
+    /// request.
+    pub fn ifreq_mut(&mut self) -> &mut ifreq {
+        &mut self.ifreq
+    }
+
+    /// Points the request at `data`, the argument of requests like
+    /// `SIOCETHTOOL`.
+    pub(crate) fn set_data(&mut self, data: *mut libc::c_void) {
+        self.ifreq.ifr_ifru.ifru_data = data as *mut libc::c_char;
+    }
+
+    /// Issues the socket `request` on `fd` with `self` as its argument.
+    pub(crate) fn ioctl(&mut self, fd: RawFd, request: c_ulong) -> Result<()> {
+        let ifreq = &mut self.ifreq as *mut ifreq;
+        crate::retry_eintr(|| {
+            let err = unsafe { libc::ioctl(fd, request as _, ifreq) };
+            if err < 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Opens the datagram socket interface `ioctl`s are issued on.
+pub(crate) fn config_socket() -> Result<OwnedFd> {
+    let fd = unsafe { libc::socket(AF_INET, SOCK_DGRAM | SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+pub(super) fn sysfs_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "invalid interface name",
+        ));
+    }
+    Ok(Path::new("/sys/class/net").join(name))
+}
+
+fn read_counter(path: &Path) -> Result<u64> {
+    std::fs::read_to_string(path)?
+        .trim()
+        .parse()
+        .map_err(|error| Error::new(ErrorKind::InvalidData, error))
+}
+
+/**
+Lists the names of the Tun/Tap interfaces on the system, e.g. `tun0`.
+
+The interfaces are found in `/sys/class/net`, where only Tun/Tap interfaces
+have a `tun_flags` attribute. Like [`get_stats`], this shows the interfaces
+of the network namespace sysfs was mounted in.
+
+# Examples
+
+```no_run
+use utuntap::interface::list_devices;
+
+for name in list_devices().unwrap() {
+    println!("{}", name);
+}
+```
+
+[`get_stats`]: fn.get_stats.html
+*/
+pub fn list_devices() -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir("/sys/class/net")? {
+        let entry = entry?;
+        if entry.path().join("tun_flags").exists() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/**
+Reads the traffic counters of the interface `name`.
+
+The counters are read from `/sys/class/net/<name>/statistics`.
+
+# Errors
+
+* [`NotFound`]: The interface does not exist.
+* [`InvalidInput`]: `name` is not a valid interface name.
+
+# Examples
+
+```no_run
+use utuntap::interface::get_stats;
+
+let stats = get_stats("tun0").unwrap();
+println!("received {} bytes", stats.rx_bytes);
+```
+
+[`NotFound`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.NotFound
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn get_stats(name: &str) -> Result<Stats> {
+    let statistics = sysfs_path(name)?.join("statistics");
+    let counter = |file: &str| read_counter(&statistics.join(file));
+    Ok(Stats {
+        rx_bytes: counter("rx_bytes")?,
+        tx_bytes: counter("tx_bytes")?,
+        rx_packets: counter("rx_packets")?,
+        tx_packets: counter("tx_packets")?,
+        rx_dropped: counter("rx_dropped")?,
+        tx_dropped: counter("tx_dropped")?,
+    })
+}
+
+/**
+Reads how many packets the kernel dropped because the queue of the Tun/Tap
+interface `name` was full, i.e. because the reader of the device did not
+keep up.
+
+For a Tun/Tap interface, the kernel transmits the packets userspace reads,
+and receives the packets userspace writes. Packets on their way to the
+reader are dropped once its queue holds `txqueuelen` packets, and counted
+in `tx_dropped`, which is what this returns. Packets written by userspace
+that the kernel discards are counted in `rx_dropped` instead, as
+[`get_stats`] reports. Compare two readings to tell whether drops happen
+right now.
+
+The counter is read from `/sys/class/net/<name>/statistics/tx_dropped`,
+which also counts packets dropped while no file is attached to the
+interface.
+
+# Errors
+
+* [`NotFound`]: The interface does not exist.
+* [`InvalidInput`]: `name` is not a valid interface name.
+
+# Examples
+
+```no_run
+use utuntap::interface::get_queue_drops;
+
+let before = get_queue_drops("tun0").unwrap();
+// ... process packets for a while ...
+if get_queue_drops("tun0").unwrap() > before {
+    println!("the reader of tun0 falls behind");
+}
+```
+
+[`get_stats`]: fn.get_stats.html
+[`NotFound`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.NotFound
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn get_queue_drops(name: &str) -> Result<u64> {
+    read_counter(&sysfs_path(name)?.join("statistics/tx_dropped"))
+}
+
+/**
+Waits until the interface `name` shows up in `/sys/class/net`, checking
+every 10 milliseconds for at most `timeout`.
+
+A device opened with `TUNSETIFF` has its interface right away, but its
+sysfs entries may lag behind, e.g. when a persistent device is reopened or
+an interface is moved between network namespaces. Helpers reading sysfs,
+like [`get_stats`], fail with [`NotFound`] in the meantime.
+
+Like the other helpers reading sysfs, this sees the interfaces of the
+network namespace sysfs was mounted in, not necessarily the one of the
+calling thread. [`interface_exists`] checks the same without waiting.
+
+# Errors
+
+* [`TimedOut`]: The interface did not show up within `timeout`.
+* [`InvalidInput`]: `name` is not a valid interface name.
+
+# Examples
+
+```no_run
+use std::time::Duration;
+use utuntap::interface::{get_stats, wait_for_interface};
+
+wait_for_interface("tun0", Duration::from_secs(1)).unwrap();
+let stats = get_stats("tun0").unwrap();
+```
+
+[`get_stats`]: fn.get_stats.html
+[`interface_exists`]: fn.interface_exists.html
+[`NotFound`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.NotFound
+[`TimedOut`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.TimedOut
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn wait_for_interface(name: &str, timeout: std::time::Duration) -> Result<()> {
+    const INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+
+    let path = sysfs_path(name)?;
+    let deadline = std::time::Instant::now() + timeout;
+    loop {
+        if path.exists() {
+            return Ok(());
+        }
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::new(
+                ErrorKind::TimedOut,
+                format!("{} did not show up in time", name),
+            ));
+        }
+        std::thread::sleep(remaining.min(INTERVAL));
+    }
+}
+
+/**
+Reads the operational state of the interface `name`.
+
+The state is read from `/sys/class/net/<name>/operstate`. Tun/Tap drivers do
+not report a link state of their own, so an interface which is up and has a
+carrier stays [`Unknown`] until the carrier first changes, e.g. when a
+persistent device is attached again, and is [`Up`] from then on. It is
+[`Down`] while it is down, or while no file is attached to a persistent
+device, as the carrier is lost when the last file is closed.
+
+# Errors
+
+* [`NotFound`]: The interface does not exist.
+* [`InvalidInput`]: `name` is not a valid interface name.
+* [`InvalidData`]: The kernel reports a state this crate does not know.
+
+# Examples
+
+```no_run
+use utuntap::interface::{get_operstate, OperState};
+
+match get_operstate("tun0").unwrap() {
+    OperState::Up | OperState::Unknown => println!("tun0 passes packets"),
+    state => println!("tun0 is {:?}", state),
+}
+```
+
+[`Unknown`]: enum.OperState.html#variant.Unknown
+[`Up`]: enum.OperState.html#variant.Up
+[`Down`]: enum.OperState.html#variant.Down
+[`NotFound`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.NotFound
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+[`InvalidData`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidData
+*/
+pub fn get_operstate(name: &str) -> Result<OperState> {
+    let state = std::fs::read_to_string(sysfs_path(name)?.join("operstate"))?;
+    Ok(match state.trim() {
+        "unknown" => OperState::Unknown,
+        "notpresent" => OperState::NotPresent,
+        "down" => OperState::Down,
+        "lowerlayerdown" => OperState::LowerLayerDown,
+        "testing" => OperState::Testing,
+        "dormant" => OperState::Dormant,
+        "up" => OperState::Up,
+        _ => {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "unknown operational state",
+            ))
+        }
+    })
+}
+
+/**
+Reads the alias of the interface `name`, the description shown by
+`ip link show`, or `None` when it has none.
+
+The alias is read from `/sys/class/net/<name>/ifalias`.
+
+# Errors
+
+* [`NotFound`]: The interface does not exist.
+* [`InvalidInput`]: `name` is not a valid interface name.
+
+# Examples
+
+```no_run
+use utuntap::interface::get_alias;
+
+if let Some(alias) = get_alias("tun0").unwrap() {
+    println!("tun0 is {}", alias);
+}
+```
+
+[`NotFound`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.NotFound
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn get_alias(name: &str) -> Result<Option<String>> {
+    let alias = std::fs::read_to_string(sysfs_path(name)?.join("ifalias"))?;
+    let alias = alias.strip_suffix('\n').unwrap_or(&alias);
+    Ok((!alias.is_empty()).then(|| alias.to_string()))
+}
+
+/**
+Sets the alias of the interface `name` to `alias`, or removes it when
+`alias` is empty.
+
+The alias is written to `/sys/class/net/<name>/ifalias`, which needs
+`CAP_NET_ADMIN`.
+
+# Errors
+
+* [`NotFound`]: The interface does not exist.
+* [`PermissionDenied`]: The process lacks `CAP_NET_ADMIN`.
+* [`InvalidInput`]: `name` is not a valid interface name, or `alias` holds
+  a newline or is 256 bytes or longer.
+
+# Examples
+
+```no_run
+use utuntap::interface::set_alias;
+
+set_alias("tun0", "uplink to the lab").unwrap();
+```
+
+[`NotFound`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.NotFound
+[`PermissionDenied`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.PermissionDenied
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn set_alias(name: &str, alias: &str) -> Result<()> {
+    // IFALIASZ counts the terminating NUL.
+    if alias.len() >= 256 || alias.contains('\n') {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "invalid interface alias",
+        ));
+    }
+    let path = sysfs_path(name)?.join("ifalias");
+    if !path.exists() {
+        return Err(Error::new(ErrorKind::NotFound, "interface not found"));
+    }
+    // An empty write never reaches the kernel; a lone newline clears it.
+    std::fs::write(path, format!("{}\n", alias))
+}
+
+/**
+Reads the MTU of the interface `name` with `SIOCGIFMTU`.
+
+Unlike `/sys/class/net`, which shows the interfaces of the namespace sysfs
+was mounted in, the `ioctl` sees the network namespace of the calling
+thread.
+
+# Errors
+
+* [`InvalidInput`]: `name` is not a valid interface name.
+
+Other errors are reported by the OS, e.g. `ENODEV` when the interface does
+not exist.
+
+# Examples
+
+```no_run
+use utuntap::interface::get_mtu;
+
+println!("tun0 carries up to {} bytes", get_mtu("tun0").unwrap());
+```
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn get_mtu(name: &str) -> Result<u32> {
+    InterfaceConfigurator::new()?.get_mtu(name)
+}
+
+/**
+Derives the IPv6 link-local address of the interface `name` from its
+Ethernet address, read with `SIOCGIFHWADDR`, as [`eui64_link_local`] does.
+
+The address is computed, not read: the interface need not be up nor have
+IPv6 enabled, and the address it actually has may differ when the kernel
+generates link-local addresses otherwise, e.g. with `addrgenmode none`.
+
+This function is only available on Linux.
+
+# Errors
+
+* [`InvalidInput`]: `name` is not a valid interface name, or the interface
+  has no Ethernet address, as Tun interfaces.
+
+Other errors are reported by the OS, e.g. `ENODEV` when the interface does
+not exist.
+
+# Examples
+
+```no_run
+use utuntap::interface::link_local_v6;
+
+println!("tap0 is at {}", link_local_v6("tap0").unwrap());
+```
+
+[`eui64_link_local`]: fn.eui64_link_local.html
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn link_local_v6(name: &str) -> Result<std::net::Ipv6Addr> {
+    let socket = config_socket()?;
+    let mut request = Request::new(name)?;
+    request.ioctl(socket.as_raw_fd(), libc::SIOCGIFHWADDR)?;
+    let hwaddr = unsafe { request.ifreq.ifr_ifru.ifru_hwaddr };
+    if hwaddr.sa_family != libc::ARPHRD_ETHER {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} has no Ethernet address", name),
+        ));
+    }
+    let mut mac = [0u8; 6];
+    for (dst, &src) in mac.iter_mut().zip(hwaddr.sa_data.iter()) {
+        *dst = src as u8;
+    }
+    Ok(super::eui64_link_local(mac))
+}
+
+/**
+Sets the Ethernet address of the interface `name` with `SIOCSIFHWADDR`, as
+`ip link set <name> address <mac>` does, e.g. to give a Tap interface a
+deterministic address instead of a random one.
+
+Tap interfaces take a new address while up. This requires `CAP_NET_ADMIN`.
+
+This function is only available on Linux and macOS.
+
+# Errors
+
+* [`InvalidInput`]: `name` is not a valid interface name, or `mac` is a
+  multicast address.
+
+Other errors are reported by the OS, e.g. `EINVAL` for interfaces without
+an Ethernet address, as Tun interfaces, or `EADDRNOTAVAIL` for the zero
+address.
+
+# Examples
+
+```no_run
+use utuntap::interface::set_mac;
+
+set_mac("tap0", [0x02, 0x00, 0x00, 0x00, 0x00, 0x01]).unwrap();
+```
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn set_mac(name: &str, mac: [u8; 6]) -> Result<()> {
+    super::check_unicast(mac)?;
+    let mut request = Request::new(name)?;
+    let hwaddr = unsafe { &mut request.ifreq.ifr_ifru.ifru_hwaddr };
+    hwaddr.sa_family = libc::ARPHRD_ETHER;
+    for (dst, &src) in hwaddr.sa_data.iter_mut().zip(mac.iter()) {
+        *dst = src as libc::c_char;
+    }
+    request.ioctl(config_socket()?.as_raw_fd(), libc::SIOCSIFHWADDR)
+}
+
+/**
+Reads the flags of the interface `name` with `SIOCGIFFLAGS`.
+
+# Examples
+
+```no_run
+use utuntap::interface::get_flags;
+
+let up = get_flags("tun0").unwrap().is_up();
+```
+*/
+pub fn get_flags(name: &str) -> Result<InterfaceFlags> {
+    InterfaceConfigurator::new()?.get_flags(name)
+}
+
+/**
+Replaces the flags of the interface `name` with `SIOCSIFFLAGS`.
+
+Flags the kernel does not let userspace change are ignored by it. Read the
+current flags with [`get_flags`] first to change only some of them.
+
+[`get_flags`]: fn.get_flags.html
+*/
+pub fn set_flags(name: &str, flags: InterfaceFlags) -> Result<()> {
+    InterfaceConfigurator::new()?.set_flags(name, flags)
+}
+
+/**
+Brings the interface `name` up, as `ip link set <name> up` does.
+
+# Examples
+
+```no_run
+use utuntap::interface::set_up;
+
+set_up("tun0").unwrap();
+```
+*/
+pub fn set_up(name: &str) -> Result<()> {
+    InterfaceConfigurator::new()?.set_up(name)
+}
+
+/**
+Brings the interface `name` down, as `ip link set <name> down` does.
+*/
+pub fn set_down(name: &str) -> Result<()> {
+    InterfaceConfigurator::new()?.set_down(name)
+}
+
+/**
+Sets the MTU of the interface `name` with `SIOCSIFMTU`.
+
+# Errors
+
+* [`InvalidInput`]: The MTU is out of the range the interface accepts.
+
+# Examples
+
+```no_run
+use utuntap::interface::set_mtu;
+
+set_mtu("tun0", 1420).unwrap();
+```
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn set_mtu(name: &str, mtu: u32) -> Result<()> {
+    InterfaceConfigurator::new()?.set_mtu(name, mtu)
+}
+
+/**
+Sets the IPv4 address and netmask of the interface `name` with `SIOCSIFADDR`
+and `SIOCSIFNETMASK`, replacing its primary address, as `ip addr add` does
+for the first address. This needs `CAP_NET_ADMIN`.
+
+# Errors
+
+* [`InvalidInput`]: `name` is not a valid interface name.
+
+Other errors are reported by the OS, e.g. `EINVAL` for an invalid netmask.
+
+# Examples
+
+```no_run
+use std::net::Ipv4Addr;
+use utuntap::interface::set_address;
+
+set_address("tun0", Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(255, 255, 255, 0)).unwrap();
+```
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn set_address(name: &str, address: Ipv4Addr, netmask: Ipv4Addr) -> Result<()> {
+    InterfaceConfigurator::new()?.set_address(name, address, netmask)
+}
+
+/**
+Adds the IPv4 address `address` with the netmask `netmask` to the interface
+`name` with an rtnetlink `RTM_NEWADDR` request, as `ip addr add` does.
+
+Unlike [`set_address`], which replaces the primary address, this keeps the
+addresses the interface already has: an address in the subnet of an
+existing one becomes a secondary address. This needs `CAP_NET_ADMIN`.
+
+# Errors
+
+* [`InvalidInput`]: `name` is not a valid interface name, or `netmask` is
+  not contiguous.
+
+Other errors are reported by the OS, e.g. `EEXIST` when the interface
+already has `address`, or `ENODEV` when it does not exist.
+
+# Examples
+
+```no_run
+use std::net::Ipv4Addr;
+use utuntap::interface;
+
+let netmask = Ipv4Addr::new(255, 255, 255, 0);
+interface::set_address("tun0", Ipv4Addr::new(10, 0, 0, 1), netmask).unwrap();
+interface::add_address("tun0", Ipv4Addr::new(10, 0, 1, 1), netmask).unwrap();
+```
+
+[`set_address`]: fn.set_address.html
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn add_address(name: &str, address: Ipv4Addr, netmask: Ipv4Addr) -> Result<()> {
+    let mask = u32::from(netmask);
+    if mask.leading_ones() + mask.trailing_zeros() != 32 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "netmask is not contiguous",
+        ));
+    }
+    let mut request = Request::new(name)?;
+    request.ioctl(config_socket()?.as_raw_fd(), SIOCGIFINDEX)?;
+    let index = unsafe { request.ifreq.ifr_ifru.ifru_ifindex };
+
+    // struct nlmsghdr, struct ifaddrmsg and the IFA_LOCAL and IFA_ADDRESS
+    // attributes, both holding `address`
+    let len = NLMSG_HDRLEN + IFADDRMSG_LEN + 2 * (RTA_HDRLEN + 4);
+    let mut message = vec![0u8; len];
+    let flags = libc::NLM_F_REQUEST | libc::NLM_F_ACK | libc::NLM_F_CREATE | libc::NLM_F_EXCL;
+    message[0..4].copy_from_slice(&(len as u32).to_ne_bytes());
+    message[4..6].copy_from_slice(&libc::RTM_NEWADDR.to_ne_bytes());
+    message[6..8].copy_from_slice(&(flags as u16).to_ne_bytes());
+    let ifaddrmsg = &mut message[NLMSG_HDRLEN..];
+    ifaddrmsg[0] = AF_INET as u8;
+    ifaddrmsg[1] = mask.leading_ones() as u8;
+    ifaddrmsg[4..8].copy_from_slice(&(index as u32).to_ne_bytes());
+    let attributes = message[NLMSG_HDRLEN + IFADDRMSG_LEN..].chunks_exact_mut(RTA_HDRLEN + 4);
+    for (attribute, kind) in attributes.zip([libc::IFA_LOCAL, libc::IFA_ADDRESS]) {
+        attribute[0..2].copy_from_slice(&((RTA_HDRLEN + 4) as u16).to_ne_bytes());
+        attribute[2..4].copy_from_slice(&kind.to_ne_bytes());
+        attribute[4..8].copy_from_slice(&address.octets());
+    }
+    rtnetlink(&message)?;
+    Ok(())
+}
+
+/**
+Renames the interface `old` to `new` with `SIOCSIFNAME`, e.g. to give a
+device opened without a number a descriptive name.
+
+The interface usually has to be down: the kernel refuses to rename a
+running interface with `EBUSY`. A [`Device`] opened on `old` keeps working,
+but its [`name`] still reports `old`.
+
+# Errors
+
+* [`InvalidInput`]: `old` or `new` is not a valid interface name; names must
+  be shorter than `IFNAMSIZ` (16 bytes).
+
+Other errors are reported by the OS, e.g. `EEXIST` when `new` is taken.
+
+# Examples
+
+```no_run
+use utuntap::{interface, tun};
+
+let (_file, name) = tun::OpenOptions::new().open(None).unwrap();
+interface::rename(&name, "uplink").unwrap();
+```
+
+[`Device`]: ../struct.Device.html
+[`name`]: ../struct.Device.html#method.name
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn rename(old: &str, new: &str) -> Result<()> {
+    InterfaceConfigurator::new()?.rename(old, new)
+}
+
+/// Enslaves the interface of the index in `ifr_ifindex` to a bridge.
+const SIOCBRADDIF: c_ulong = 0x89a2;
+/// Releases the interface of the index in `ifr_ifindex` from a bridge.
+const SIOCBRDELIF: c_ulong = 0x89a3;
+
+/**
+Adds the interface `name`, typically a Tap device, to the bridge `bridge`
+with `SIOCBRADDIF`, as `ip link set <name> master <bridge>` does.
+
+The bridge must already exist, e.g. created with `ip link add br0 type
+bridge`. This needs `CAP_NET_ADMIN`.
+
+# Errors
+
+* [`InvalidInput`]: `name` or `bridge` is not a valid interface name.
+
+Other errors are reported by the OS, e.g. `ENODEV` when either interface
+does not exist, or `EOPNOTSUPP` when `bridge` is not a bridge.
+
+# Examples
+
+```no_run
+use utuntap::{interface, tap};
+
+let device = tap::OpenOptions::new().open_device(0).unwrap();
+interface::add_to_bridge(device.name(), "br0").unwrap();
+```
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn add_to_bridge(name: &str, bridge: &str) -> Result<()> {
+    InterfaceConfigurator::new()?.add_to_bridge(name, bridge)
+}
+
+/**
+Removes the interface `name` from the bridge `bridge` with `SIOCBRDELIF`,
+as `ip link set <name> nomaster` does.
+
+# Errors
+
+The errors are those of [`add_to_bridge`]; the OS reports `EINVAL` when
+`name` is not a port of `bridge`.
+
+[`add_to_bridge`]: fn.add_to_bridge.html
+*/
+pub fn remove_from_bridge(name: &str, bridge: &str) -> Result<()> {
+    InterfaceConfigurator::new()?.remove_from_bridge(name, bridge)
+}
+
+impl InterfaceConfigurator {
+    /// Reads the MTU of the interface `name`, as [`get_mtu`] does.
+    ///
+    /// [`get_mtu`]: fn.get_mtu.html
+    pub fn get_mtu(&self, name: &str) -> Result<u32> {
+        let mut request = Request::new(name)?;
+        request.ioctl(self.socket.as_raw_fd(), SIOCGIFMTU)?;
+        Ok(unsafe { request.ifreq.ifr_ifru.ifru_mtu } as u32)
+    }
+
+    /// Sets the MTU of the interface `name`, as [`set_mtu`] does.
+    ///
+    /// [`set_mtu`]: fn.set_mtu.html
+    pub fn set_mtu(&self, name: &str, mtu: u32) -> Result<()> {
+        let mut request = Request::new(name)?;
+        request.ifreq.ifr_ifru.ifru_mtu =
+            c_int::try_from(mtu).map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+        request.ioctl(self.socket.as_raw_fd(), SIOCSIFMTU)
+    }
+
+    /// Reads the flags of the interface `name`, as [`get_flags`] does.
+    ///
+    /// [`get_flags`]: fn.get_flags.html
+    pub fn get_flags(&self, name: &str) -> Result<InterfaceFlags> {
+        let mut request = Request::new(name)?;
+        request.ioctl(self.socket.as_raw_fd(), SIOCGIFFLAGS)?;
+        Ok(InterfaceFlags::from_bits_retain(c_int::from(
+            request.flags() as u16,
+        )))
+    }
+
+    /// Replaces the flags of the interface `name`, as [`set_flags`] does.
+    ///
+    /// [`set_flags`]: fn.set_flags.html
+    pub fn set_flags(&self, name: &str, flags: InterfaceFlags) -> Result<()> {
+        let mut request = Request::new(name)?;
+        request.ifreq.ifr_ifru.ifru_flags = flags.bits() as c_short;
+        request.ioctl(self.socket.as_raw_fd(), SIOCSIFFLAGS)
+    }
+
+    /// Sets the IPv4 address and netmask of the interface `name`, as
+    /// [`set_address`] does.
+    ///
+    /// [`set_address`]: fn.set_address.html
+    pub fn set_address(&self, name: &str, address: Ipv4Addr, netmask: Ipv4Addr) -> Result<()> {
+        for (request, address) in [(SIOCSIFADDR, address), (SIOCSIFNETMASK, netmask)] {
+            let mut ifreq = Request::new(name)?;
+            let sockaddr = libc::sockaddr_in {
+                sin_family: AF_INET as _,
+                sin_port: 0,
+                sin_addr: libc::in_addr {
+                    s_addr: u32::from_ne_bytes(address.octets()),
+                },
+                sin_zero: [0; 8],
+            };
+            unsafe {
+                let ifru_addr = &mut ifreq.ifreq.ifr_ifru.ifru_addr as *mut libc::sockaddr;
+                (ifru_addr as *mut libc::sockaddr_in).write(sockaddr);
+            }
+            ifreq.ioctl(self.socket.as_raw_fd(), request)?;
+        }
+        Ok(())
+    }
+
+    /// Renames the interface `old` to `new`, as [`rename`] does.
+    ///
+    /// [`rename`]: fn.rename.html
+    pub fn rename(&self, old: &str, new: &str) -> Result<()> {
+        if new.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid interface name",
+            ));
+        }
+        let newname = Request::new(new)?.ifreq.ifr_name;
+        let mut request = Request::new(old)?;
+        request.ifreq.ifr_ifru.ifru_newname = newname;
+        request.ioctl(self.socket.as_raw_fd(), SIOCSIFNAME)
+    }
+
+    /// Adds the interface `name` to the bridge `bridge`, as
+    /// [`add_to_bridge`] does.
+    ///
+    /// [`add_to_bridge`]: fn.add_to_bridge.html
+    pub fn add_to_bridge(&self, name: &str, bridge: &str) -> Result<()> {
+        self.bridge_ioctl(name, bridge, SIOCBRADDIF)
+    }
+
+    /// Removes the interface `name` from the bridge `bridge`, as
+    /// [`remove_from_bridge`] does.
+    ///
+    /// [`remove_from_bridge`]: fn.remove_from_bridge.html
+    pub fn remove_from_bridge(&self, name: &str, bridge: &str) -> Result<()> {
+        self.bridge_ioctl(name, bridge, SIOCBRDELIF)
+    }
+
+    fn bridge_ioctl(&self, name: &str, bridge: &str, request: c_ulong) -> Result<()> {
+        let mut index = Request::new(name)?;
+        index.ioctl(self.socket.as_raw_fd(), SIOCGIFINDEX)?;
+        let mut bridge = Request::new(bridge)?;
+        bridge.ifreq.ifr_ifru.ifru_ifindex = unsafe { index.ifreq.ifr_ifru.ifru_ifindex };
+        bridge.ioctl(self.socket.as_raw_fd(), request)
+    }
+}
+
+/// Length of the names of `ETH_SS_FEATURES`.
+const ETH_GSTRING_LEN: usize = 32;
+const ETH_SS_FEATURES: u32 = 4;
+const ETHTOOL_GSTRINGS: u32 = 0x1b;
+const ETHTOOL_GSSET_INFO: u32 = 0x37;
+const ETHTOOL_GFEATURES: u32 = 0x3a;
+const ETHTOOL_GRINGPARAM: u32 = 0x10;
+const ETHTOOL_SRINGPARAM: u32 = 0x11;
+
+/// Issues the ethtool command at the start of `data` on the interface
+/// `name`.
+fn ethtool<T>(name: &str, data: &mut [T]) -> Result<()> {
+    let socket = config_socket()?;
+    let mut request = Request::new(name)?;
+    request.set_data(data.as_mut_ptr() as *mut libc::c_void);
+    request.ioctl(socket.as_raw_fd(), libc::SIOCETHTOOL)
+}
+
+/// Returns the names of the features active on the interface `name`, as
+/// `ethtool -k` lists them, e.g. `tx-tcp-segmentation`.
+pub(crate) fn get_active_features(name: &str) -> Result<Vec<String>> {
+    // struct ethtool_sset_info with room for one count
+    let mut sset_info = [ETHTOOL_GSSET_INFO, 0, 1 << ETH_SS_FEATURES, 0, 0];
+    ethtool(name, &mut sset_info)?;
+    let count = sset_info[4] as usize;
+
+    // struct ethtool_gstrings followed by the names
+    let mut strings = vec![0u32; 3 + count * ETH_GSTRING_LEN / 4];
+    strings[..3].copy_from_slice(&[ETHTOOL_GSTRINGS, ETH_SS_FEATURES, count as u32]);
+    ethtool(name, &mut strings)?;
+    let names: Vec<u8> = strings[3..]
+        .iter()
+        .flat_map(|word| word.to_ne_bytes())
+        .collect();
+
+    // struct ethtool_gfeatures followed by blocks of available, requested,
+    // active and never changed features
+    let blocks = count.div_ceil(32);
+    let mut features = vec![0u32; 2 + blocks * 4];
+    features[..2].copy_from_slice(&[ETHTOOL_GFEATURES, blocks as u32]);
+    ethtool(name, &mut features)?;
+
+    Ok((0..count)
+        .filter(|index| features[2 + index / 32 * 4 + 2] & (1 << (index % 32)) != 0)
+        .map(|index| {
+            let name = &names[index * ETH_GSTRING_LEN..(index + 1) * ETH_GSTRING_LEN];
+            let len = name
+                .iter()
+                .position(|&byte| byte == 0)
+                .unwrap_or(name.len());
+            String::from_utf8_lossy(&name[..len]).into_owned()
+        })
+        .collect())
+}
+
+/**
+Reads the sizes of the receive and transmit rings of the interface `name`,
+as `ethtool -g` shows them.
+
+The Tun/Tap driver has no rings, so this fails with [`Unsupported`] on
+Tun/Tap interfaces; it is meant for the interfaces they are bridged or
+routed to.
+
+This function is only available on Linux.
+
+# Errors
+
+* [`Unsupported`]: The driver of the interface has no ring parameters.
+* [`InvalidInput`]: `name` is not a valid interface name.
+
+Other errors are reported by the OS, e.g. `ENODEV` when the interface does
+not exist.
+
+# Examples
+
+```no_run
+use utuntap::interface::get_ring_params;
+
+let rings = get_ring_params("eth0").unwrap();
+println!("rx {}/{}, tx {}/{}", rings.rx, rings.rx_max, rings.tx, rings.tx_max);
+```
+
+[`Unsupported`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.Unsupported
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn get_ring_params(name: &str) -> Result<RingParams> {
+    let ringparam = get_ringparam(name)?;
+    Ok(RingParams {
+        rx: ringparam[5],
+        tx: ringparam[8],
+        rx_max: ringparam[1],
+        tx_max: ringparam[4],
+    })
+}
+
+/**
+Sets the sizes of the receive and transmit rings of the interface `name`,
+as `ethtool -G <name> rx <rx> tx <tx>` does. The other rings keep their
+size.
+
+This requires `CAP_NET_ADMIN`. The Tun/Tap driver has no rings, so this
+fails with [`Unsupported`] on Tun/Tap interfaces.
+
+This function is only available on Linux.
+
+# Errors
+
+* [`Unsupported`]: The driver of the interface has no ring parameters.
+* [`InvalidInput`]: `name` is not a valid interface name, or a size is
+  beyond what the driver supports, as [`get_ring_params`] tells.
+
+Other errors are reported by the OS, e.g. `EPERM` without `CAP_NET_ADMIN`.
+
+# Examples
+
+```no_run
+use utuntap::interface::{get_ring_params, set_ring_params};
+
+let rings = get_ring_params("eth0").unwrap();
+set_ring_params("eth0", rings.rx_max, rings.tx_max).unwrap();
+```
+
+[`get_ring_params`]: fn.get_ring_params.html
+[`Unsupported`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.Unsupported
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn set_ring_params(name: &str, rx: u32, tx: u32) -> Result<()> {
+    let mut ringparam = get_ringparam(name)?;
+    ringparam[0] = ETHTOOL_SRINGPARAM;
+    ringparam[5] = rx;
+    ringparam[8] = tx;
+    ethtool(name, &mut ringparam).map_err(|error| rings_unsupported(name, error))
+}
+
+/// Reads the `struct ethtool_ringparam` of the interface `name`: the
+/// command, the largest then the current sizes of the rx, rx mini, rx
+/// jumbo and tx rings.
+fn get_ringparam(name: &str) -> Result<[u32; 9]> {
+    let mut ringparam = [0; 9];
+    ringparam[0] = ETHTOOL_GRINGPARAM;
+    ethtool(name, &mut ringparam).map_err(|error| rings_unsupported(name, error))?;
+    Ok(ringparam)
+}
+
+/// Turns the `EOPNOTSUPP` of drivers without ring parameters into an error
+/// of kind `Unsupported` naming the interface.
+fn rings_unsupported(name: &str, error: Error) -> Error {
+    if error.raw_os_error() != Some(libc::EOPNOTSUPP) {
+        return error;
+    }
+    Error::new(
+        ErrorKind::Unsupported,
+        format!("the driver of {} has no ring parameters", name),
+    )
+}
+
+/**
+Returns the kind of the root queueing discipline of the interface `name`,
+as `tc qdisc show dev <name>` shows it, e.g. `"fq_codel"` or `"pfifo_fast"`.
+
+Packets written to a Tun/Tap interface by the network stack wait in this
+qdisc while the device is not read fast enough, and are dropped once it is
+full, which [`get_queue_drops`] does not count. An interface which is down
+has the `"noop"` qdisc, which drops everything.
+
+This function is only available on Linux.
+
+# Errors
+
+* [`InvalidInput`]: `name` is not a valid interface name.
+
+Other errors are reported by the OS, e.g. `ENODEV` when the interface does
+not exist.
+
+# Examples
+
+```no_run
+use utuntap::interface::get_qdisc;
+
+println!("tun0 queues with {}", get_qdisc("tun0").unwrap());
+```
+
+[`get_queue_drops`]: fn.get_queue_drops.html
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn get_qdisc(name: &str) -> Result<String> {
+    // The kernel only answers a single qdisc request asking for an echo,
+    // and never with the builtin noop qdisc: an acknowledgement comes first
+    // then.
+    let flags = libc::NLM_F_REQUEST | libc::NLM_F_ECHO | libc::NLM_F_ACK;
+    let message = qdisc_message(name, libc::RTM_GETQDISC, flags, &[])?;
+    let reply = rtnetlink(&message)?;
+    if c_int::from(u16::from_ne_bytes([reply[4], reply[5]])) == libc::NLMSG_ERROR {
+        return Ok("noop".to_string());
+    }
+    let len = (u32::from_ne_bytes(reply[0..4].try_into().unwrap()) as usize).min(reply.len());
+    if len < NLMSG_HDRLEN + TCMSG_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "invalid rtnetlink reply",
+        ));
+    }
+    let kind = attributes(&reply[NLMSG_HDRLEN + TCMSG_LEN..len])
+        .find(|&(kind, _)| kind == libc::TCA_KIND)
+        .map(|(_, kind)| kind.split(|&byte| byte == 0).next().unwrap_or_default())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "qdisc without a kind"))?;
+    Ok(String::from_utf8_lossy(kind).into_owned())
+}
+
+/**
+Replaces the root queueing discipline of the interface `name` with a
+`pfifo` queue of `packets` packets, as `tc qdisc replace dev <name> root
+pfifo limit <packets>` does.
+
+The default qdisc holds up to the transmit queue length of the interface,
+500 packets for Tun/Tap interfaces. A longer queue absorbs bursts while the
+device is read slowly, at the cost of latency. This requires
+`CAP_NET_ADMIN`; the default qdisc comes back with `tc qdisc del dev <name>
+root`.
+
+This function is only available on Linux.
+
+# Errors
+
+* [`InvalidInput`]: `name` is not a valid interface name.
+
+Other errors are reported by the OS, e.g. `EPERM` without `CAP_NET_ADMIN`,
+or `ENODEV` when the interface does not exist.
+
+# Examples
+
+```no_run
+use utuntap::interface::set_pfifo_limit;
+
+set_pfifo_limit("tun0", 4096).unwrap();
+```
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn set_pfifo_limit(name: &str, packets: u32) -> Result<()> {
+    let flags = libc::NLM_F_REQUEST | libc::NLM_F_ACK | libc::NLM_F_CREATE | libc::NLM_F_REPLACE;
+    let attributes: [(u16, &[u8]); 2] = [
+        (libc::TCA_KIND, b"pfifo\0"),
+        // struct tc_fifo_qopt
+        (libc::TCA_OPTIONS, &packets.to_ne_bytes()),
+    ];
+    rtnetlink(&qdisc_message(
+        name,
+        libc::RTM_NEWQDISC,
+        flags,
+        &attributes,
+    )?)?;
+    Ok(())
+}
+
+/// Builds the rtnetlink request `kind` about the root qdisc of the
+/// interface `name`, carrying `attributes`.
+fn qdisc_message(
+    name: &str,
+    kind: u16,
+    flags: c_int,
+    attributes: &[(u16, &[u8])],
+) -> Result<Vec<u8>> {
+    let mut request = Request::new(name)?;
+    request.ioctl(config_socket()?.as_raw_fd(), SIOCGIFINDEX)?;
+    let index = unsafe { request.ifreq.ifr_ifru.ifru_ifindex };
+
+    // struct nlmsghdr, struct tcmsg and the attributes
+    let mut message = vec![0u8; NLMSG_HDRLEN + TCMSG_LEN];
+    message[4..6].copy_from_slice(&kind.to_ne_bytes());
+    message[6..8].copy_from_slice(&(flags as u16).to_ne_bytes());
+    let tcmsg = &mut message[NLMSG_HDRLEN..];
+    tcmsg[0] = libc::AF_UNSPEC as u8;
+    tcmsg[4..8].copy_from_slice(&index.to_ne_bytes());
+    tcmsg[12..16].copy_from_slice(&TC_H_ROOT.to_ne_bytes());
+    for &(kind, payload) in attributes {
+        let attribute_len = RTA_HDRLEN + payload.len();
+        message.extend_from_slice(&(attribute_len as u16).to_ne_bytes());
+        message.extend_from_slice(&kind.to_ne_bytes());
+        message.extend_from_slice(payload);
+        message.resize(message.len().next_multiple_of(4), 0);
+    }
+    let len = message.len() as u32;
+    message[0..4].copy_from_slice(&len.to_ne_bytes());
+    Ok(message)
+}
+
+/// `IFLA_TUN_TYPE` and `IFLA_TUN_PI` from `linux/if_link.h`, in the
+/// `IFLA_INFO_DATA` of Tun/Tap links since Linux 4.15.
+const IFLA_TUN_TYPE: u16 = 3;
+const IFLA_TUN_PI: u16 = 4;
+
+/// Lengths of `struct nlmsghdr`, `struct ifinfomsg`, `struct ifaddrmsg`,
+/// `struct rtattr` and `struct tcmsg`.
+const NLMSG_HDRLEN: usize = 16;
+const IFINFOMSG_LEN: usize = 16;
+const IFADDRMSG_LEN: usize = 8;
+const RTA_HDRLEN: usize = 4;
+const TCMSG_LEN: usize = 20;
+
+/// `TC_H_ROOT` from `linux/pkt_sched.h`, the parent of root qdiscs.
+const TC_H_ROOT: u32 = 0xffff_ffff;
+
+/// Returns the netlink attributes in `data` as `(type, payload)` pairs.
+fn attributes(mut data: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if data.len() < RTA_HDRLEN {
+            return None;
+        }
+        let len = u16::from_ne_bytes([data[0], data[1]]) as usize;
+        let kind = u16::from_ne_bytes([data[2], data[3]]) & 0x3fff;
+        if len < RTA_HDRLEN || len > data.len() {
+            return None;
+        }
+        let payload = &data[RTA_HDRLEN..len];
+        data = &data[len.next_multiple_of(4).min(data.len())..];
+        Some((kind, payload))
+    })
+}
+
+/**
+Asks rtnetlink whether the Tun/Tap interface `name` carries packet info.
+
+`TUNGETIFF` cannot tell: the kernel reports `IFF_NOFILTER`, which shares its
+value with `IFF_NO_PI`, whenever no socket filter is attached. Like the
+other `ioctl`s, the request goes to the network namespace of the calling
+thread.
+*/
+pub(crate) fn get_packet_info(name: &str) -> Result<bool> {
+    tun_attribute(name, IFLA_TUN_PI)?
+        .map(|pi| pi != 0)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::Unsupported,
+                "the kernel does not report the packet info of interfaces",
+            )
+        })
+}
+
+/// Asks rtnetlink for the mode of the existing interface `name`, `None`
+/// when it is not a Tun/Tap interface or the kernel does not tell.
+pub(crate) fn get_mode(name: &str) -> Result<Option<crate::Mode>> {
+    let kind = tun_attribute(name, IFLA_TUN_TYPE)?;
+    Ok(kind.and_then(|kind| crate::Mode::from_iff(kind as c_short)))
+}
+
+/// Returns the first byte of the attribute `kind` in the `IFLA_INFO_DATA`
+/// rtnetlink reports for the interface `name`, if any.
+fn tun_attribute(name: &str, kind: u16) -> Result<Option<u8>> {
+    if name.is_empty() || name.len() >= IFNAMSIZ || name.contains('\0') {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "invalid interface name",
+        ));
+    }
+
+    // struct nlmsghdr, struct ifinfomsg and an IFLA_IFNAME attribute
+    let attribute_len = RTA_HDRLEN + name.len() + 1;
+    let len = NLMSG_HDRLEN + IFINFOMSG_LEN + attribute_len.next_multiple_of(4);
+    let mut message = vec![0u8; len];
+    message[0..4].copy_from_slice(&(len as u32).to_ne_bytes());
+    message[4..6].copy_from_slice(&libc::RTM_GETLINK.to_ne_bytes());
+    message[6..8].copy_from_slice(&(libc::NLM_F_REQUEST as u16).to_ne_bytes());
+    let attribute = &mut message[NLMSG_HDRLEN + IFINFOMSG_LEN..];
+    attribute[0..2].copy_from_slice(&(attribute_len as u16).to_ne_bytes());
+    attribute[2..4].copy_from_slice(&libc::IFLA_IFNAME.to_ne_bytes());
+    attribute[4..4 + name.len()].copy_from_slice(name.as_bytes());
+
+    let reply = rtnetlink(&message)?;
+    let len = (u32::from_ne_bytes(reply[0..4].try_into().unwrap()) as usize).min(reply.len());
+    if len < NLMSG_HDRLEN + IFINFOMSG_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "invalid rtnetlink reply",
+        ));
+    }
+
+    let value = attributes(&reply[NLMSG_HDRLEN + IFINFOMSG_LEN..len])
+        .filter(|&(kind, _)| kind == libc::IFLA_LINKINFO)
+        .flat_map(|(_, info)| attributes(info))
+        .filter(|&(kind, _)| kind == libc::IFLA_INFO_DATA)
+        .flat_map(|(_, data)| attributes(data))
+        .find(|&(found, _)| found == kind)
+        .and_then(|(_, value)| value.first().copied());
+    Ok(value)
+}
+
+/// Sends the rtnetlink request `message` and returns the first message of
+/// the reply, failing with the error the kernel reports in it, if any.
+fn rtnetlink(message: &[u8]) -> Result<Vec<u8>> {
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | SOCK_CLOEXEC,
+            libc::NETLINK_ROUTE,
+        )
+    };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+    crate::retry_eintr(|| {
+        let sent = unsafe {
+            libc::send(
+                socket.as_raw_fd(),
+                message.as_ptr() as *const libc::c_void,
+                message.len(),
+                0,
+            )
+        };
+        if sent < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    })?;
+    let mut reply = vec![0u8; 16384];
+    let received = crate::retry_eintr(|| {
+        let received = unsafe {
+            libc::recv(
+                socket.as_raw_fd(),
+                reply.as_mut_ptr() as *mut libc::c_void,
+                reply.len(),
+                0,
+            )
+        };
+        if received < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(received)
+    })?;
+    reply.truncate(received as usize);
+    if reply.len() < NLMSG_HDRLEN + 4 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "invalid rtnetlink reply",
+        ));
+    }
+    let kind = u16::from_ne_bytes([reply[4], reply[5]]);
+    if c_int::from(kind) == libc::NLMSG_ERROR {
+        // An error of 0 acknowledges a request made with `NLM_F_ACK`.
+        let errno = i32::from_ne_bytes(reply[16..20].try_into().unwrap());
+        if errno != 0 {
+            return Err(Error::from_raw_os_error(-errno));
+        }
+    }
+    Ok(reply)
+}
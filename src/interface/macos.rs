@@ -0,0 +1,275 @@
+use super::{InterfaceConfigurator, InterfaceFlags};
+use libc::{c_ulong, c_void, ioctl, socket, AF_INET, SOCK_DGRAM};
+use std::io::{Error, ErrorKind, Result};
+use std::net::Ipv4Addr;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+const IFNAMSIZ: usize = 16;
+
+#[cfg(feature = "macos-feth")]
+pub(crate) const SIOCIFCREATE2: c_ulong = 0xc020697a;
+#[cfg(feature = "macos-feth")]
+pub(crate) const SIOCIFDESTROY: c_ulong = 0x80206979;
+const SIOCSIFFLAGS: c_ulong = 0x80206910;
+const SIOCGIFFLAGS: c_ulong = 0xc0206911;
+const SIOCGIFMTU: c_ulong = 0xc0206933;
+const SIOCSIFMTU: c_ulong = 0x80206934;
+const SIOCAIFADDR: c_ulong = 0x8040691a;
+const SIOCSIFLLADDR: c_ulong = 0x8020693c;
+
+/// The `struct ifreq` of macOS, which `libc` does not provide.
+#[repr(C)]
+pub(crate) struct Request {
+    pub(crate) ifr_name: [u8; IFNAMSIZ],
+    pub(crate) ifr_ifru: [u8; 16],
+}
+
+impl Request {
+    /// Creates a request for the interface `name`.
+    pub(crate) fn new(name: &str) -> Result<Self> {
+        if name.len() >= IFNAMSIZ || name.bytes().any(|byte| byte == 0) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid interface name",
+            ));
+        }
+        let mut ifr_name = [0u8; IFNAMSIZ];
+        ifr_name[..name.len()].copy_from_slice(name.as_bytes());
+        Ok(Self {
+            ifr_name,
+            ifr_ifru: [0; 16],
+        })
+    }
+
+    /// Returns the interface name carried by the request.
+    #[cfg(feature = "macos-feth")]
+    pub(crate) fn name(&self) -> Result<String> {
+        let length = self
+            .ifr_name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(IFNAMSIZ);
+        String::from_utf8(self.ifr_name[..length].to_vec())
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))
+    }
+
+    /// Issues `request` on `fd` with `self` as its argument.
+    pub(crate) fn ioctl(&mut self, fd: RawFd, request: c_ulong) -> Result<()> {
+        let request_ptr = self as *mut Self as *mut c_void;
+        crate::retry_eintr(|| {
+            let err = unsafe { ioctl(fd, request, request_ptr) };
+            if err < 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Opens the datagram socket interface `ioctl`s are issued on.
+pub(crate) fn config_socket() -> Result<OwnedFd> {
+    let fd = unsafe { socket(AF_INET, SOCK_DGRAM, 0) };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Creates the fake ethernet interface `name`, returning the name assigned
+/// by the kernel. Passing `feth` without a unit lets the kernel pick one.
+#[cfg(feature = "macos-feth")]
+pub(crate) fn create_feth(name: &str) -> Result<String> {
+    let socket = config_socket()?;
+    let mut request = Request::new(name)?;
+    request.ioctl(socket.as_raw_fd(), SIOCIFCREATE2)?;
+    request.name()
+}
+
+/// Destroys the cloned interface `name`.
+#[cfg(feature = "macos-feth")]
+pub(crate) fn destroy(name: &str) -> Result<()> {
+    let socket = config_socket()?;
+    Request::new(name)?.ioctl(socket.as_raw_fd(), SIOCIFDESTROY)
+}
+
+/**
+Reads the MTU of the interface `name` with `SIOCGIFMTU`.
+
+# Errors
+
+* [`InvalidInput`]: `name` is not a valid interface name.
+
+Other errors are reported by the OS, e.g. `ENXIO` when the interface does
+not exist.
+
+# Examples
+
+```no_run
+use utuntap::interface::get_mtu;
+
+println!("utun0 carries up to {} bytes", get_mtu("utun0").unwrap());
+```
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn get_mtu(name: &str) -> Result<u32> {
+    InterfaceConfigurator::new()?.get_mtu(name)
+}
+
+/**
+Reads the flags of the interface `name` with `SIOCGIFFLAGS`.
+*/
+pub fn get_flags(name: &str) -> Result<InterfaceFlags> {
+    InterfaceConfigurator::new()?.get_flags(name)
+}
+
+/// Replaces the flags of the interface `name` with `SIOCSIFFLAGS`.
+pub fn set_flags(name: &str, flags: InterfaceFlags) -> Result<()> {
+    InterfaceConfigurator::new()?.set_flags(name, flags)
+}
+
+/// Brings the interface `name` up, as `ifconfig <name> up` does.
+pub fn set_up(name: &str) -> Result<()> {
+    InterfaceConfigurator::new()?.set_up(name)
+}
+
+/// Brings the interface `name` down, as `ifconfig <name> down` does.
+pub fn set_down(name: &str) -> Result<()> {
+    InterfaceConfigurator::new()?.set_down(name)
+}
+
+/// Sets the MTU of the interface `name` with `SIOCSIFMTU`.
+pub fn set_mtu(name: &str, mtu: u32) -> Result<()> {
+    InterfaceConfigurator::new()?.set_mtu(name, mtu)
+}
+
+/**
+Sets the Ethernet address of the interface `name` with `SIOCSIFLLADDR`, as
+`ifconfig <name> lladdr <mac>` does, e.g. to give the `feth` interface of a
+Tap device a deterministic address.
+
+This requires root privileges. Utun interfaces have no Ethernet address.
+
+# Errors
+
+* [`InvalidInput`]: `name` is not a valid interface name, or `mac` is a
+  multicast address.
+
+Other errors are reported by the OS, e.g. `EOPNOTSUPP` for interfaces
+without an Ethernet address.
+
+# Examples
+
+```no_run
+use utuntap::interface::set_mac;
+
+set_mac("feth0", [0x02, 0x00, 0x00, 0x00, 0x00, 0x01]).unwrap();
+```
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn set_mac(name: &str, mac: [u8; 6]) -> Result<()> {
+    super::check_unicast(mac)?;
+    let mut request = Request::new(name)?;
+    // struct sockaddr: its length, AF_LINK, then the address
+    request.ifr_ifru[0] = mac.len() as u8;
+    request.ifr_ifru[1] = libc::AF_LINK as u8;
+    request.ifr_ifru[2..8].copy_from_slice(&mac);
+    request.ioctl(config_socket()?.as_raw_fd(), SIOCSIFLLADDR)
+}
+
+/**
+Adds the IPv4 address `address` with the netmask `netmask` to the interface
+`name` with `SIOCAIFADDR`, as `ifconfig <name> alias` does, keeping the
+addresses the interface already has.
+
+No destination address is given, so on a point-to-point interface such as
+`utun` no route to a peer is installed.
+
+# Errors
+
+* [`InvalidInput`]: `name` is not a valid interface name.
+
+Other errors are reported by the OS, e.g. `ENXIO` when the interface does
+not exist.
+
+# Examples
+
+```no_run
+use std::net::Ipv4Addr;
+use utuntap::interface::add_address;
+
+add_address("utun0", Ipv4Addr::new(10, 0, 1, 1), Ipv4Addr::new(255, 255, 255, 0)).unwrap();
+```
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn add_address(name: &str, address: Ipv4Addr, netmask: Ipv4Addr) -> Result<()> {
+    let request = Request::new(name)?;
+    // struct ifaliasreq: the name, then the address, the broadcast or
+    // destination address, left unset, and the netmask
+    let mut ifaliasreq = [0u8; IFNAMSIZ + 3 * 16];
+    ifaliasreq[..IFNAMSIZ].copy_from_slice(&request.ifr_name);
+    for (offset, address) in [(IFNAMSIZ, address), (IFNAMSIZ + 32, netmask)] {
+        // struct sockaddr_in: sin_len, sin_family, sin_port and sin_addr
+        ifaliasreq[offset] = 16;
+        ifaliasreq[offset + 1] = AF_INET as u8;
+        ifaliasreq[offset + 4..offset + 8].copy_from_slice(&address.octets());
+    }
+    let socket = config_socket()?;
+    crate::retry_eintr(|| {
+        let err = unsafe {
+            ioctl(
+                socket.as_raw_fd(),
+                SIOCAIFADDR,
+                ifaliasreq.as_mut_ptr() as *mut c_void,
+            )
+        };
+        if err < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    })
+}
+
+impl InterfaceConfigurator {
+    /// Reads the MTU of the interface `name`, as [`get_mtu`] does.
+    ///
+    /// [`get_mtu`]: fn.get_mtu.html
+    pub fn get_mtu(&self, name: &str) -> Result<u32> {
+        let mut request = Request::new(name)?;
+        request.ioctl(self.socket.as_raw_fd(), SIOCGIFMTU)?;
+        let mtu = libc::c_int::from_ne_bytes(request.ifr_ifru[..4].try_into().unwrap());
+        Ok(mtu as u32)
+    }
+
+    /// Sets the MTU of the interface `name`, as [`set_mtu`] does.
+    ///
+    /// [`set_mtu`]: fn.set_mtu.html
+    pub fn set_mtu(&self, name: &str, mtu: u32) -> Result<()> {
+        let mut request = Request::new(name)?;
+        let mtu = libc::c_int::try_from(mtu)
+            .map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+        request.ifr_ifru[..4].copy_from_slice(&mtu.to_ne_bytes());
+        request.ioctl(self.socket.as_raw_fd(), SIOCSIFMTU)
+    }
+
+    /// Reads the flags of the interface `name`, as [`get_flags`] does.
+    ///
+    /// [`get_flags`]: fn.get_flags.html
+    pub fn get_flags(&self, name: &str) -> Result<InterfaceFlags> {
+        let mut request = Request::new(name)?;
+        request.ioctl(self.socket.as_raw_fd(), SIOCGIFFLAGS)?;
+        let flags = u16::from_ne_bytes([request.ifr_ifru[0], request.ifr_ifru[1]]);
+        Ok(InterfaceFlags::from_bits_retain(flags.into()))
+    }
+
+    /// Replaces the flags of the interface `name`, as [`set_flags`] does.
+    ///
+    /// [`set_flags`]: fn.set_flags.html
+    pub fn set_flags(&self, name: &str, flags: InterfaceFlags) -> Result<()> {
+        let mut request = Request::new(name)?;
+        request.ifr_ifru[..2].copy_from_slice(&(flags.bits() as u16).to_ne_bytes());
+        request.ioctl(self.socket.as_raw_fd(), SIOCSIFFLAGS)
+    }
+}
@@ -0,0 +1,570 @@
+//! Helpers to inspect network interfaces backing Tun/Tap devices
+
+#[cfg(target_os = "linux")]
+pub(crate) mod linux;
+#[cfg(target_os = "macos")]
+pub(crate) mod macos;
+#[cfg(target_os = "openbsd")]
+mod openbsd;
+
+#[cfg(target_os = "linux")]
+pub use linux::{
+    add_address, add_to_bridge, get_alias, get_flags, get_mtu, get_operstate, get_qdisc,
+    get_queue_drops, get_ring_params, get_stats, link_local_v6, list_devices, remove_from_bridge,
+    rename, set_address, set_alias, set_down, set_flags, set_mac, set_mtu, set_pfifo_limit,
+    set_ring_params, set_up, wait_for_interface,
+};
+#[cfg(target_os = "macos")]
+pub use macos::{add_address, get_flags, get_mtu, set_down, set_flags, set_mac, set_mtu, set_up};
+#[cfg(target_os = "openbsd")]
+pub use openbsd::{add_to_group, remove_from_group};
+
+/**
+Configures network interfaces through one socket held open across calls.
+
+Each function of this module, e.g. [`set_mtu`], opens and closes a socket
+to issue its `ioctl` on. Configuring a freshly created device usually takes
+a few of them in a row, which a configurator issues on the same socket
+instead.
+
+This type is only available on Linux and macOS.
+
+# Examples
+
+```no_run
+use utuntap::{interface::InterfaceConfigurator, tun};
+
+let device = tun::OpenOptions::new().open_device(0).unwrap();
+let configurator = InterfaceConfigurator::new().unwrap();
+configurator.set_mtu(device.name(), 1420).unwrap();
+configurator.set_up(device.name()).unwrap();
+```
+
+[`set_mtu`]: fn.set_mtu.html
+*/
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[derive(Debug)]
+pub struct InterfaceConfigurator {
+    socket: std::os::fd::OwnedFd,
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl InterfaceConfigurator {
+    /// Opens the datagram socket the `ioctl`s are issued on.
+    pub fn new() -> std::io::Result<Self> {
+        #[cfg(target_os = "linux")]
+        let socket = linux::config_socket()?;
+        #[cfg(target_os = "macos")]
+        let socket = macos::config_socket()?;
+        Ok(Self { socket })
+    }
+
+    /// Brings the interface `name` up, as [`set_up`] does.
+    ///
+    /// [`set_up`]: fn.set_up.html
+    pub fn set_up(&self, name: &str) -> std::io::Result<()> {
+        let flags = self.get_flags(name)?;
+        self.set_flags(name, flags | InterfaceFlags::UP)
+    }
+
+    /// Brings the interface `name` down, as [`set_down`] does.
+    ///
+    /// [`set_down`]: fn.set_down.html
+    pub fn set_down(&self, name: &str) -> std::io::Result<()> {
+        let flags = self.get_flags(name)?;
+        self.set_flags(name, flags & !InterfaceFlags::UP)
+    }
+}
+
+/**
+Flags of a network interface, as read and written with `SIOCGIFFLAGS` and
+`SIOCSIFFLAGS`.
+
+Flags without a named constant are kept as they are, so the flags read from
+an interface can be modified and written back as a whole. The `IFF_TUN`,
+`IFF_TAP` and `IFF_NO_PI` flags of the Tun/Tap driver are not interface
+flags, and share their values with some of these; see [`Device::mode`]
+instead.
+
+This type is only available on Linux and macOS.
+
+# Examples
+
+```no_run
+use utuntap::interface::{get_flags, set_flags, InterfaceFlags};
+
+let flags = get_flags("tun0").unwrap();
+if !flags.is_promisc() {
+    set_flags("tun0", flags | InterfaceFlags::PROMISC).unwrap();
+}
+```
+
+[`Device::mode`]: ../struct.Device.html#method.mode
+*/
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InterfaceFlags(libc::c_int);
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl InterfaceFlags {
+    /// The interface is administratively up.
+    pub const UP: Self = Self(libc::IFF_UP);
+    /// The interface has a valid broadcast address.
+    pub const BROADCAST: Self = Self(libc::IFF_BROADCAST);
+    /// The interface is a loopback interface.
+    pub const LOOPBACK: Self = Self(libc::IFF_LOOPBACK);
+    /// The interface is a point-to-point link, as Tun interfaces are.
+    pub const POINTOPOINT: Self = Self(libc::IFF_POINTOPOINT);
+    /// The interface is operationally up.
+    pub const RUNNING: Self = Self(libc::IFF_RUNNING);
+    /// The interface does not use ARP.
+    pub const NOARP: Self = Self(libc::IFF_NOARP);
+    /// The interface receives all packets.
+    pub const PROMISC: Self = Self(libc::IFF_PROMISC);
+    /// The interface receives all multicast packets.
+    pub const ALLMULTI: Self = Self(libc::IFF_ALLMULTI);
+    /// The interface supports multicast.
+    pub const MULTICAST: Self = Self(libc::IFF_MULTICAST);
+
+    /// Returns an empty set.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns the raw `IFF_*` flags.
+    pub const fn bits(&self) -> libc::c_int {
+        self.0
+    }
+
+    /// Converts raw `IFF_*` flags, keeping unknown ones.
+    pub const fn from_bits_retain(bits: libc::c_int) -> Self {
+        Self(bits)
+    }
+
+    /// Returns `true` if no flag is set.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if all flags of `other` are set.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Sets the flags of `other`.
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    /// Clears the flags of `other`.
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+
+    /// Returns `true` if the interface is administratively up.
+    pub const fn is_up(&self) -> bool {
+        self.contains(Self::UP)
+    }
+
+    /// Returns `true` if the interface is operationally up.
+    pub const fn is_running(&self) -> bool {
+        self.contains(Self::RUNNING)
+    }
+
+    /// Returns `true` if the interface receives all packets.
+    pub const fn is_promisc(&self) -> bool {
+        self.contains(Self::PROMISC)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl std::ops::BitOr for InterfaceFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl std::ops::BitOrAssign for InterfaceFlags {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl std::ops::BitAnd for InterfaceFlags {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl std::ops::Not for InterfaceFlags {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+/**
+Lists the names of the Tun/Tap interfaces on the system, e.g. `tun0`.
+
+On macOS and OpenBSD, the interfaces are found with `getifaddrs` and told
+apart by their names: `tun`, `tap` and `utun` followed by a number.
+
+# Examples
+
+```no_run
+use utuntap::interface::list_devices;
+
+for name in list_devices().unwrap() {
+    println!("{}", name);
+}
+```
+*/
+#[cfg(any(target_os = "macos", target_os = "openbsd"))]
+pub fn list_devices() -> std::io::Result<Vec<String>> {
+    Ok(interface_names()?
+        .into_iter()
+        .filter(|name| {
+            ["utun", "tun", "tap"]
+                .iter()
+                .any(|prefix| unit_of(name, prefix).is_some())
+        })
+        .collect())
+}
+
+/// Returns the names of all interfaces, as reported by `getifaddrs`.
+#[cfg(any(target_os = "macos", target_os = "openbsd"))]
+fn interface_names() -> std::io::Result<Vec<String>> {
+    use std::ffi::CStr;
+
+    let mut addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut addrs) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let mut names = Vec::new();
+    let mut cursor = addrs;
+    while !cursor.is_null() {
+        let ifaddr = unsafe { &*cursor };
+        let name = unsafe { CStr::from_ptr(ifaddr.ifa_name) }.to_string_lossy();
+        // Interfaces are listed once per address.
+        if !names.iter().any(|known: &String| *known == name) {
+            names.push(name.into_owned());
+        }
+        cursor = ifaddr.ifa_next;
+    }
+    unsafe { libc::freeifaddrs(addrs) };
+    Ok(names)
+}
+
+/**
+Tells whether the interface `name` exists, without opening any device or
+needing any privilege.
+
+On Linux, this checks `/sys/class/net/<name>`, which shows the network
+namespace sysfs was mounted in, like [`wait_for_interface`] which waits for
+the same. On macOS and OpenBSD, `if_nametoindex` is asked. Invalid names
+never exist.
+
+This function is only available on Linux, macOS and OpenBSD.
+
+# Examples
+
+```no_run
+use utuntap::interface::interface_exists;
+
+if !interface_exists("tun0") {
+    println!("tun0 is yet to be created");
+}
+```
+
+[`wait_for_interface`]: fn.wait_for_interface.html
+*/
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "openbsd"))]
+pub fn interface_exists(name: &str) -> bool {
+    #[cfg(target_os = "linux")]
+    return linux::sysfs_path(name).is_ok_and(|path| path.exists());
+    #[cfg(not(target_os = "linux"))]
+    return std::ffi::CString::new(name)
+        .is_ok_and(|name| unsafe { libc::if_nametoindex(name.as_ptr()) } != 0);
+}
+
+/**
+Returns the IPv4 and IPv6 addresses of the interface `name`, each with the
+length of its prefix, e.g. `(10.0.0.1, 24)`.
+
+The addresses are read with `getifaddrs`, in the order it reports them. An
+address reported without a netmask gets the full length, 32 or 128.
+
+This function is only available on Linux, macOS and OpenBSD.
+
+# Errors
+
+* [`NotFound`]: The interface does not exist.
+
+# Examples
+
+```no_run
+use utuntap::interface::get_addresses;
+
+for (address, prefix) in get_addresses("tun0").unwrap() {
+    println!("{}/{}", address, prefix);
+}
+```
+
+[`NotFound`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.NotFound
+*/
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "openbsd"))]
+pub fn get_addresses(name: &str) -> std::io::Result<Vec<(std::net::IpAddr, u8)>> {
+    use std::ffi::CStr;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    let mut addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut addrs) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let mut found = false;
+    let mut addresses = Vec::new();
+    let mut cursor = addrs;
+    while !cursor.is_null() {
+        let ifaddr = unsafe { &*cursor };
+        cursor = ifaddr.ifa_next;
+        if unsafe { CStr::from_ptr(ifaddr.ifa_name) }.to_bytes() != name.as_bytes() {
+            continue;
+        }
+        // Every interface is listed at least once, with its link address.
+        found = true;
+        if ifaddr.ifa_addr.is_null() {
+            continue;
+        }
+        let netmask = ifaddr.ifa_netmask;
+        match libc::c_int::from(unsafe { (*ifaddr.ifa_addr).sa_family }) {
+            libc::AF_INET => {
+                let address = unsafe { *(ifaddr.ifa_addr as *const libc::sockaddr_in) };
+                let prefix = if netmask.is_null() {
+                    32
+                } else {
+                    let mask = unsafe { *(netmask as *const libc::sockaddr_in) };
+                    mask.sin_addr.s_addr.count_ones() as u8
+                };
+                let address = Ipv4Addr::from(u32::from_be(address.sin_addr.s_addr));
+                addresses.push((IpAddr::V4(address), prefix));
+            }
+            libc::AF_INET6 => {
+                let address = unsafe { *(ifaddr.ifa_addr as *const libc::sockaddr_in6) };
+                let prefix = if netmask.is_null() {
+                    128
+                } else {
+                    let mask = unsafe { *(netmask as *const libc::sockaddr_in6) };
+                    let ones = mask.sin6_addr.s6_addr.iter().map(|byte| byte.count_ones());
+                    ones.sum::<u32>() as u8
+                };
+                let address = Ipv6Addr::from(address.sin6_addr.s6_addr);
+                addresses.push((IpAddr::V6(address), prefix));
+            }
+            _ => {}
+        }
+    }
+    unsafe { libc::freeifaddrs(addrs) };
+    if !found {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no such interface",
+        ));
+    }
+    Ok(addresses)
+}
+
+/// Returns the number of the interface `name` if it is `prefix` followed
+/// by a number, e.g. 3 for `utun3` and `utun`.
+#[cfg(any(target_os = "macos", target_os = "openbsd"))]
+fn unit_of(name: &str, prefix: &str) -> Option<u32> {
+    let number = name.strip_prefix(prefix)?;
+    if number.bytes().all(|byte| byte.is_ascii_digit()) {
+        number.parse().ok()
+    } else {
+        None
+    }
+}
+
+/**
+Returns the smallest device number not in use by an interface of the given
+`mode`, e.g. 1 when `tun0` exists but `tun1` does not.
+
+On Linux, each `/sys/class/net/<mode><number>` is checked in turn, which
+shows the network namespace sysfs was mounted in. On macOS and OpenBSD the
+names reported by `getifaddrs` are searched: `utun` interfaces for Tun
+devices on macOS, and `feth` interfaces, which the `macos-feth` feature
+uses, for Tap devices.
+
+The number may be taken by someone else before it is opened; prefer letting
+the OS assign one at open time where the platform supports it.
+
+# Examples
+
+```no_run
+use utuntap::{interface::next_free_number, tun::OpenOptions, Mode};
+
+let number = next_free_number(Mode::Tun).unwrap();
+let (file, filename) = OpenOptions::new().open(number).unwrap();
+```
+*/
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "openbsd"))]
+pub fn next_free_number(mode: crate::Mode) -> std::io::Result<u32> {
+    #[cfg(target_os = "linux")]
+    {
+        let class = std::path::Path::new("/sys/class/net");
+        // Fail rather than report every number as free.
+        std::fs::metadata(class)?;
+        (0..=u32::MAX)
+            .find(|number| !class.join(format!("{}{}", mode, number)).exists())
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::AddrInUse,
+                    "all device numbers are in use",
+                )
+            })
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        #[cfg(target_os = "macos")]
+        let prefix = match mode {
+            crate::Mode::Tun => "utun",
+            crate::Mode::Tap => "feth",
+        };
+        #[cfg(not(target_os = "macos"))]
+        let prefix = mode.to_string();
+        let mut used: Vec<u32> = interface_names()?
+            .iter()
+            .filter_map(|name| unit_of(name, &prefix))
+            .collect();
+        used.sort_unstable();
+        let mut number = 0;
+        for unit in used {
+            if unit == number {
+                number += 1;
+            } else if unit > number {
+                break;
+            }
+        }
+        Ok(number)
+    }
+}
+
+/**
+Derives the IPv6 link-local address of the Ethernet address `mac` with the
+modified EUI-64 of RFC 4291: the universal/local bit of the first byte is
+flipped and `ff:fe` inserted in the middle, giving the interface identifier
+of an address in `fe80::/64`.
+
+This is the address the kernel gives an Ethernet interface unless told to
+generate another one, e.g. with `addrgenmode stable_secret`.
+
+# Examples
+
+```
+use std::net::Ipv6Addr;
+use utuntap::interface::eui64_link_local;
+
+let address = eui64_link_local([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]);
+assert_eq!(address, "fe80::21a:2bff:fe3c:4d5e".parse::<Ipv6Addr>().unwrap());
+let address = eui64_link_local([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+assert_eq!(address, "fe80::ff:fe00:1".parse::<Ipv6Addr>().unwrap());
+```
+*/
+pub fn eui64_link_local(mac: [u8; 6]) -> std::net::Ipv6Addr {
+    let [a, b, c, d, e, f] = mac;
+    let mut octets = [0; 16];
+    octets[..2].copy_from_slice(&[0xfe, 0x80]);
+    octets[8..].copy_from_slice(&[a ^ 0x02, b, c, 0xff, 0xfe, d, e, f]);
+    std::net::Ipv6Addr::from(octets)
+}
+
+/// Refuses `mac` as the address of an interface if it is a multicast
+/// address, as the kernels would, but with an error telling why.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn check_unicast(mac: [u8; 6]) -> std::io::Result<()> {
+    if mac[0] & 0x01 != 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "an interface cannot have a multicast Ethernet address",
+        ));
+    }
+    Ok(())
+}
+
+/**
+Traffic counters of a network interface.
+
+All counters are cumulative since the interface was created.
+*/
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of bytes received by the interface.
+    pub rx_bytes: u64,
+    /// Number of bytes transmitted by the interface.
+    pub tx_bytes: u64,
+    /// Number of packets received by the interface.
+    pub rx_packets: u64,
+    /// Number of packets transmitted by the interface.
+    pub tx_packets: u64,
+    /// Number of received packets dropped by the interface.
+    pub rx_dropped: u64,
+    /// Number of packets dropped by the interface before transmission.
+    pub tx_dropped: u64,
+}
+
+/**
+The operational state of a network interface, as defined by RFC 2863 and
+reported by [`get_operstate`].
+
+This type is only available on Linux.
+
+[`get_operstate`]: fn.get_operstate.html
+*/
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperState {
+    /// The driver does not report a state, as for a new Tun/Tap device
+    /// with a carrier.
+    Unknown,
+    /// Some component of the interface is missing.
+    NotPresent,
+    /// The interface is down or has no carrier.
+    Down,
+    /// The interface is down because of an interface it is stacked on.
+    LowerLayerDown,
+    /// The interface is in test mode.
+    Testing,
+    /// The interface is waiting for an external event to pass packets.
+    Dormant,
+    /// The interface is up and passes packets.
+    Up,
+}
+
+/**
+The sizes of the receive and transmit rings of a network interface, as
+reported by [`get_ring_params`].
+
+This type is only available on Linux.
+
+[`get_ring_params`]: fn.get_ring_params.html
+*/
+#[cfg(target_os = "linux")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RingParams {
+    /// Number of entries of the receive ring.
+    pub rx: u32,
+    /// Number of entries of the transmit ring.
+    pub tx: u32,
+    /// Largest receive ring the driver supports.
+    pub rx_max: u32,
+    /// Largest transmit ring the driver supports.
+    pub tx_max: u32,
+}
@@ -0,0 +1,120 @@
+use libc::{c_uint, c_ulong, c_void, ioctl, socket, AF_INET, SOCK_DGRAM};
+use std::io::{Error, ErrorKind, Result};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+const IFNAMSIZ: usize = 16;
+
+const SIOCAIFGROUP: c_ulong = group_iow(135);
+const SIOCDIFGROUP: c_ulong = group_iow(137);
+
+/// Computes `_IOW('i', number, struct ifgroupreq)`, whose size depends on
+/// the width of pointers.
+const fn group_iow(number: c_ulong) -> c_ulong {
+    const IOC_IN: c_ulong = 0x8000_0000;
+    let size = std::mem::size_of::<GroupRequest>() as c_ulong & 0x1fff;
+    IOC_IN | (size << 16) | ((b'i' as c_ulong) << 8) | number
+}
+
+/// The `struct ifgroupreq` of OpenBSD, which `libc` does not provide.
+#[repr(C)]
+struct GroupRequest {
+    ifgr_name: [u8; IFNAMSIZ],
+    ifgr_len: c_uint,
+    ifgr_ifgru: GroupRequestData,
+}
+
+/// The union of a group name and a pointer to a list of groups.
+#[repr(C)]
+union GroupRequestData {
+    ifgru_group: [u8; IFNAMSIZ],
+    ifgru_groups: *mut c_void,
+}
+
+/// Copies `name` into a NUL-terminated buffer of `IFNAMSIZ` bytes.
+fn to_ifname(name: &str) -> Result<[u8; IFNAMSIZ]> {
+    if name.is_empty() || name.len() >= IFNAMSIZ || name.bytes().any(|byte| byte == 0) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "invalid interface or group name",
+        ));
+    }
+    let mut buffer = [0u8; IFNAMSIZ];
+    buffer[..name.len()].copy_from_slice(name.as_bytes());
+    Ok(buffer)
+}
+
+/// Issues the group `request` for the interface `name` and the group
+/// `group`.
+fn group_ioctl(name: &str, group: &str, request: c_ulong) -> Result<()> {
+    let mut group_request = GroupRequest {
+        ifgr_name: to_ifname(name)?,
+        ifgr_len: 0,
+        ifgr_ifgru: GroupRequestData {
+            ifgru_group: to_ifname(group)?,
+        },
+    };
+    let fd = unsafe { socket(AF_INET, SOCK_DGRAM, 0) };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+    let request_ptr = &mut group_request as *mut GroupRequest as *mut c_void;
+    crate::retry_eintr(|| {
+        if unsafe { ioctl(socket.as_raw_fd(), request, request_ptr) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    })
+}
+
+/**
+Adds the interface `name` to the interface group `group` with
+`SIOCAIFGROUP`, as `ifconfig <name> group <group>` does, e.g. for `pf` rules
+to match the interfaces of the group.
+
+Every `tun` interface already belongs to the `tun` group, and every `tap`
+interface to the `tap` group. This needs root privileges.
+
+This function is only available on OpenBSD.
+
+# Errors
+
+* [`InvalidInput`]: `name` or `group` is empty or longer than 15 bytes.
+
+Other errors are reported by the OS, e.g. `EINVAL` when `group` ends with a
+digit, `EEXIST` when the interface already belongs to the group, or `ENXIO`
+when it does not exist.
+
+# Examples
+
+```no_run
+use utuntap::{interface::add_to_group, tun::OpenOptions};
+
+let (_file, name) = OpenOptions::new().open(0).unwrap();
+add_to_group(&name, "vpn").unwrap();
+```
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn add_to_group(name: &str, group: &str) -> Result<()> {
+    group_ioctl(name, group, SIOCAIFGROUP)
+}
+
+/**
+Removes the interface `name` from the interface group `group` with
+`SIOCDIFGROUP`, as `ifconfig <name> -group <group>` does.
+
+This function is only available on OpenBSD.
+
+# Errors
+
+* [`InvalidInput`]: `name` or `group` is empty or longer than 15 bytes.
+
+Other errors are reported by the OS, e.g. `ENOENT` when the interface does
+not belong to the group.
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+pub fn remove_from_group(name: &str, group: &str) -> Result<()> {
+    group_ioctl(name, group, SIOCDIFGROUP)
+}
@@ -0,0 +1,186 @@
+/*!
+Raw `ioctl` access to Tun/Tap devices on Linux
+
+The kernel offers many more `ioctl`s on a Tun/Tap file than this crate wraps.
+This module exposes the request type and codes so that they can be issued
+directly against the [`std::fs::File`][file] returned by `open`, and the
+[`IfReq`] argument of interface requests issued on a socket.
+
+[`IfReq`]: struct.IfReq.html
+[file]: https://doc.rust-lang.org/nightly/std/io/struct.File.html
+
+# Examples
+
+Making device `tun0` persistent:
+
+```no_run
+use std::os::unix::io::AsRawFd;
+use utuntap::{ioctl, tun::OpenOptions};
+
+let (file, _) = OpenOptions::new().open(0).unwrap();
+unsafe { ioctl::tun_ioctl_with_value(file.as_raw_fd(), ioctl::TUNSETPERSIST, 1).unwrap() };
+```
+*/
+
+use libc::{c_int, c_uint};
+use std::io::{Error, Result};
+use std::mem;
+use std::os::unix::io::RawFd;
+
+/// The type of the `request` argument of `ioctl`, which differs between musl
+/// and glibc.
+///
+/// Only the type differs, not the encoding: the kernel truncates the request
+/// to an `unsigned int`, so a code sign-extended by musl and the same code
+/// zero-extended by glibc issue the same `ioctl` whatever libc the binary
+/// runs against.
+#[cfg(target_env = "musl")]
+pub type RequestId = libc::c_int;
+/// The type of the `request` argument of `ioctl`, which differs between musl
+/// and glibc.
+#[cfg(not(target_env = "musl"))]
+pub type RequestId = libc::c_ulong;
+
+/// Sets the debug message level of the driver, taking the level by value.
+pub const TUNSETDEBUG: RequestId = request_code_write!(b'T', 201, mem::size_of::<c_int>());
+/// Creates or attaches to a Tun/Tap interface, taking an `ifreq`.
+pub const TUNSETIFF: RequestId = request_code_write!(b'T', 202, mem::size_of::<c_int>());
+/// Sets whether the interface outlives the file, taking the flag by value.
+pub const TUNSETPERSIST: RequestId = request_code_write!(b'T', 203, mem::size_of::<c_int>());
+/// Sets the user owning a persistent interface, taking the `uid_t` by value.
+pub const TUNSETOWNER: RequestId = request_code_write!(b'T', 204, mem::size_of::<c_int>());
+/// Sets the ARP hardware type of the interface, taking the type by value.
+pub const TUNSETLINK: RequestId = request_code_write!(b'T', 205, mem::size_of::<c_int>());
+/// Sets the group owning a persistent interface, taking the `gid_t` by value.
+pub const TUNSETGROUP: RequestId = request_code_write!(b'T', 206, mem::size_of::<c_int>());
+/// Gets the `IFF_*` flags supported by the kernel, filling a `c_uint`.
+pub const TUNGETFEATURES: RequestId = request_code_read!(b'T', 207, mem::size_of::<c_uint>());
+/// Sets the offloads accepted by the file, taking the `TUN_F_*` flags by value.
+pub const TUNSETOFFLOAD: RequestId = request_code_write!(b'T', 208, mem::size_of::<c_uint>());
+/// Gets the name and flags of the attached interface, filling an `ifreq`.
+pub const TUNGETIFF: RequestId = request_code_read!(b'T', 210, mem::size_of::<c_uint>());
+/// Sets the send buffer size of the file, taking a `c_int`.
+pub const TUNSETSNDBUF: RequestId = request_code_write!(b'T', 212, mem::size_of::<c_int>());
+/// Gets the size of the virtio net header, filling a `c_int`.
+pub const TUNGETVNETHDRSZ: RequestId = request_code_read!(b'T', 215, mem::size_of::<c_int>());
+/// Sets the size of the virtio net header, taking a `c_int`.
+pub const TUNSETVNETHDRSZ: RequestId = request_code_write!(b'T', 216, mem::size_of::<c_int>());
+/// Attaches or detaches a queue of a multi-queue interface, taking an `ifreq`.
+pub const TUNSETQUEUE: RequestId = request_code_write!(b'T', 217, mem::size_of::<c_int>());
+/// Sets whether the virtio net header is little-endian, taking a `c_int`.
+pub const TUNSETVNETLE: RequestId = request_code_write!(b'T', 220, mem::size_of::<c_int>());
+/// Gets whether the virtio net header is little-endian, filling a `c_int`.
+pub const TUNGETVNETLE: RequestId = request_code_read!(b'T', 221, mem::size_of::<c_int>());
+/// Sets whether the virtio net header is big-endian, taking a `c_int`.
+pub const TUNSETVNETBE: RequestId = request_code_write!(b'T', 222, mem::size_of::<c_int>());
+/// Gets whether the virtio net header is big-endian, filling a `c_int`.
+pub const TUNGETVNETBE: RequestId = request_code_read!(b'T', 223, mem::size_of::<c_int>());
+/// Attaches or detaches the eBPF program steering packets to queues, taking
+/// a `c_int` holding the program's file descriptor.
+pub const TUNSETSTEERINGEBPF: RequestId = request_code_read!(b'T', 224, mem::size_of::<c_int>());
+
+/**
+Issues `request` on the file descriptor `fd` with `arg` as its argument.
+Calls interrupted by a signal are repeated.
+
+# Safety
+
+`arg` must be the type `request` expects, since the kernel reads and writes
+through it according to the request.
+
+# Errors
+
+Returns the OS error reported by `ioctl`.
+*/
+pub unsafe fn tun_ioctl<T>(fd: RawFd, request: RequestId, arg: &mut T) -> Result<()> {
+    crate::retry_eintr(|| {
+        let err = libc::ioctl(fd, request, arg as *mut T);
+        if err < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    })
+}
+
+/**
+Issues `request` on the file descriptor `fd` with the integer `arg` passed by
+value, as requests like [`TUNSETPERSIST`] and [`TUNSETOFFLOAD`] expect.
+Like [`tun_ioctl`], calls interrupted by a signal are repeated.
+
+# Safety
+
+`request` must take its argument by value: a request expecting a pointer
+would dereference `arg` as an address.
+
+# Errors
+
+Returns the OS error reported by `ioctl`.
+
+[`TUNSETPERSIST`]: constant.TUNSETPERSIST.html
+[`TUNSETOFFLOAD`]: constant.TUNSETOFFLOAD.html
+[`tun_ioctl`]: fn.tun_ioctl.html
+*/
+pub unsafe fn tun_ioctl_with_value(
+    fd: RawFd,
+    request: RequestId,
+    arg: libc::c_ulong,
+) -> Result<()> {
+    crate::retry_eintr(|| {
+        let err = libc::ioctl(fd, request, arg);
+        if err < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    })
+}
+
+pub use crate::interface::linux::Request as IfReq;
+
+/**
+Issues the interface `request` on the socket `fd` with `arg` as its argument,
+e.g. `SIOCGIFMTU` for requests this crate has no typed helper for.
+
+Interface requests go to a socket rather than to the device file; any socket
+in the network namespace of the interface will do. Like [`tun_ioctl`], calls
+interrupted by a signal are repeated.
+
+# Safety
+
+The payload of `arg` must be what `request` expects: the kernel reads and
+writes through pointers set in it, e.g. `ifru_data`.
+
+# Errors
+
+Returns the OS error reported by `ioctl`.
+
+# Examples
+
+```no_run
+use std::net::UdpSocket;
+use std::os::unix::io::AsRawFd;
+use utuntap::ioctl::{self, IfReq};
+
+let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+let mut request = IfReq::new("tun0").unwrap();
+unsafe { ioctl::ifreq_ioctl(socket.as_raw_fd(), libc::SIOCGIFMTU, &mut request).unwrap() };
+let mtu = unsafe { request.ifreq().ifr_ifru.ifru_mtu };
+```
+
+[`tun_ioctl`]: fn.tun_ioctl.html
+*/
+pub unsafe fn ifreq_ioctl(fd: RawFd, request: RequestId, arg: &mut IfReq) -> Result<()> {
+    tun_ioctl(fd, request, arg.ifreq_mut())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_codes_match_linux_headers() {
+        // From <linux/if_tun.h>: the kernel only sees these 32 bits.
+        assert_eq!(TUNSETIFF as u32, 0x4004_54ca);
+        assert_eq!(TUNGETIFF as u32, 0x8004_54d2);
+        assert_eq!(TUNGETFEATURES as u32, 0x8004_54cf);
+    }
+}
@@ -7,14 +7,32 @@ use libc::O_NONBLOCK;
 use std::fs::File;
 use std::io::Result;
 
-#[derive(Debug, PartialEq)]
-enum Mode {
+/**
+The kind of a device: level 3 Tun or level 2 Tap.
+
+A `Mode` can be parsed from `"tun"` or `"tap"`, which is handy when the kind
+of device comes from configuration.
+
+# Examples
+
+```
+use utuntap::Mode;
+
+let mode: Mode = "tap".parse().unwrap();
+assert_eq!(mode, Mode::Tap);
+assert_eq!(mode.to_string(), "tap");
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Mode {
+    /// A level 3 device carrying IP packets.
     Tun,
-    #[cfg(not(target_os = "macos"))]
+    /// A level 2 device carrying Ethernet frames. Not supported on macOS.
     Tap,
 }
 
-#[cfg(not(target_os = "macos"))]
 impl std::fmt::Display for Mode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let text = match self {
@@ -25,64 +43,1177 @@ impl std::fmt::Display for Mode {
     }
 }
 
-struct OpenOptions {
+impl std::str::FromStr for Mode {
+    type Err = std::io::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "tun" => Ok(Mode::Tun),
+            "tap" => Ok(Mode::Tap),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("unknown device mode: {:?}", s),
+            )),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Mode {
+    /**
+    Returns the `IFF_TUN` or `IFF_TAP` flag which asks `TUNSETIFF` for the
+    mode.
+
+    This method is only available on Linux.
+    */
+    pub const fn iff_flag(&self) -> libc::c_short {
+        match self {
+            Mode::Tun => interface::linux::IFF_TUN,
+            Mode::Tap => interface::linux::IFF_TAP,
+        }
+    }
+
+    /**
+    Returns the mode of the `IFF_*` flags `flags`, e.g. as reported by
+    `TUNGETIFF`, or `None` when neither `IFF_TUN` nor `IFF_TAP` is set.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```
+    use utuntap::Mode;
+
+    assert_eq!(Mode::from_iff(Mode::Tap.iff_flag() | 0x1000), Some(Mode::Tap));
+    assert_eq!(Mode::from_iff(0x1000), None);
+    ```
+    */
+    pub const fn from_iff(flags: libc::c_short) -> Option<Mode> {
+        if flags & interface::linux::IFF_TUN != 0 {
+            Some(Mode::Tun)
+        } else if flags & interface::linux::IFF_TAP != 0 {
+            Some(Mode::Tap)
+        } else {
+            None
+        }
+    }
+}
+
+/**
+How reads of a device wait for packets, as chosen with the `blocking_mode`
+option of the builders, e.g. [`tun::OpenOptions::blocking_mode`].
+
+[`tun::OpenOptions::blocking_mode`]: tun/struct.OpenOptions.html#method.blocking_mode
+
+# Examples
+
+```no_run
+use std::time::Duration;
+use utuntap::{tun::OpenOptions, BlockingMode};
+
+let device = OpenOptions::new()
+    .blocking_mode(BlockingMode::Timeout(Duration::from_millis(500)))
+    .open_device(0)
+    .unwrap();
+```
+*/
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockingMode {
+    /// Reads wait until a packet arrives.
+    #[default]
+    Blocking,
+    /// Reads fail with `WouldBlock` when no packet is queued, with
+    /// `O_NONBLOCK` set on the file.
+    NonBlocking,
+    /// Reads wait until a packet arrives for at most the given duration,
+    /// then fail with `TimedOut`, as set by [`Device::set_read_timeout`].
+    /// Only [`Device`]s honour the timeout: the bare files returned by
+    /// `open` block.
+    ///
+    /// [`Device`]: struct.Device.html
+    /// [`Device::set_read_timeout`]: struct.Device.html#method.set_read_timeout
+    Timeout(std::time::Duration),
+}
+
+/**
+Whether Tap devices can be opened on the target platform.
+
+This is `false` on macOS, which has no Tap devices, unless the `macos-feth`
+feature emulates them with `feth` interfaces, and `true` everywhere else.
+Opening a Tap device where this is `false` fails with [`Unsupported`].
+
+[`Unsupported`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.Unsupported
+
+# Examples
+
+```
+use utuntap::{Mode, TAP_SUPPORTED};
+
+let modes: &[Mode] = if TAP_SUPPORTED {
+    &[Mode::Tun, Mode::Tap]
+} else {
+    &[Mode::Tun]
+};
+println!("supported modes: {:?}", modes);
+```
+*/
+pub const TAP_SUPPORTED: bool = cfg!(any(not(target_os = "macos"), feature = "macos-feth"));
+
+/**
+Options and flags which can be used to configure how a Tun/Tap device file
+is opened, with the kind of device chosen at runtime.
+
+This builder behaves like [`tun::OpenOptions`] and [`tap::OpenOptions`],
+except that the [`Mode`] is passed to [`new`] instead of being fixed by the
+module.
+
+[`tun::OpenOptions`]: tun/struct.OpenOptions.html
+[`tap::OpenOptions`]: tap/struct.OpenOptions.html
+[`Mode`]: enum.Mode.html
+[`new`]: struct.OpenOptions.html#method.new
+
+# Examples
+
+Opening a device whose kind comes from configuration:
+
+```no_run
+use utuntap::{Mode, OpenOptions};
+
+let mode: Mode = "tun".parse().unwrap();
+let (file, filename) = OpenOptions::new(mode).open(0).unwrap();
+```
+*/
+pub struct OpenOptions {
     mode: Mode,
     read: bool,
     write: bool,
     #[cfg(target_family = "unix")]
     nonblock: bool,
+    #[cfg(target_family = "unix")]
+    read_timeout: Option<std::time::Duration>,
+    /// The process to send `SIGIO` to, if any.
+    #[cfg(target_family = "unix")]
+    signal_owner: Option<libc::pid_t>,
+    buffer_capacity: Option<usize>,
     #[cfg(target_os = "linux")]
     packet_info: bool,
+    #[cfg(target_os = "linux")]
+    name: Option<String>,
+    /// Whether `name` may be given up for a name the kernel picks.
+    #[cfg(target_os = "linux")]
+    preferred_name: bool,
+    #[cfg(target_os = "linux")]
+    vnet_hdr: Option<bool>,
+    #[cfg(target_os = "linux")]
+    offload: tun::Offload,
+    #[cfg(target_os = "linux")]
+    exclusive: bool,
+    /// The node to open instead of trying `TUN_NODES`.
+    #[cfg(target_os = "linux")]
+    device_path: Option<std::path::PathBuf>,
+    #[cfg(target_os = "linux")]
+    napi: bool,
+    #[cfg(target_os = "linux")]
+    napi_frags: bool,
+    strip_protocol_info: bool,
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    mtu: Option<u32>,
+    #[cfg(target_os = "macos")]
+    control_name: Option<String>,
+    #[cfg(target_os = "macos")]
+    any_unit: bool,
 }
 
 impl OpenOptions {
-    fn new() -> Self {
+    /**
+    Creates a blank new set of options for a device of the given `mode`.
+
+    All options are initially set to `false` except read and write.
+
+    # Examples
+
+    ```no_run
+    use utuntap::{Mode, OpenOptions};
+
+    let mut options = OpenOptions::new(Mode::Tap);
+    let (file, filename) = options.open(0).unwrap();
+    ```
+    */
+    pub fn new(mode: Mode) -> Self {
         Self {
-            mode: Mode::Tun,
+            mode,
             read: true,
             write: true,
             #[cfg(target_family = "unix")]
             nonblock: false,
+            #[cfg(target_family = "unix")]
+            read_timeout: None,
+            #[cfg(target_family = "unix")]
+            signal_owner: None,
+            buffer_capacity: None,
             #[cfg(target_os = "linux")]
             packet_info: false,
+            #[cfg(target_os = "linux")]
+            name: None,
+            #[cfg(target_os = "linux")]
+            preferred_name: false,
+            #[cfg(target_os = "linux")]
+            vnet_hdr: None,
+            #[cfg(target_os = "linux")]
+            offload: tun::Offload::empty(),
+            #[cfg(target_os = "linux")]
+            exclusive: false,
+            #[cfg(target_os = "linux")]
+            device_path: None,
+            #[cfg(target_os = "linux")]
+            napi: false,
+            #[cfg(target_os = "linux")]
+            napi_frags: false,
+            strip_protocol_info: false,
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            mtu: None,
+            #[cfg(target_os = "macos")]
+            control_name: None,
+            #[cfg(target_os = "macos")]
+            any_unit: false,
         }
     }
 
-    fn read(&mut self, enabled: bool) -> &mut Self {
+    /**
+    Sets the option for read access.
+
+    This option defaults to `true`.
+    */
+    pub fn read(&mut self, enabled: bool) -> &mut Self {
         self.read = enabled;
         self
     }
 
-    fn write(&mut self, enabled: bool) -> &mut Self {
+    /**
+    Sets the option for write access.
+
+    This option defaults to `true`.
+    */
+    pub fn write(&mut self, enabled: bool) -> &mut Self {
         self.write = enabled;
         self
     }
 
+    /**
+    Sets the option for non-blocking I/O, like [`blocking_mode`] with
+    [`BlockingMode::NonBlocking`] or [`BlockingMode::Blocking`].
+
+    [`blocking_mode`]: struct.OpenOptions.html#method.blocking_mode
+    [`BlockingMode::NonBlocking`]: enum.BlockingMode.html#variant.NonBlocking
+    [`BlockingMode::Blocking`]: enum.BlockingMode.html#variant.Blocking
+    */
+    #[cfg(target_family = "unix")]
+    pub fn nonblock(&mut self, enabled: bool) -> &mut Self {
+        self.blocking_mode(if enabled {
+            BlockingMode::NonBlocking
+        } else {
+            BlockingMode::Blocking
+        })
+    }
+
+    /**
+    Sets how reads of the device wait for packets.
+
+    This option defaults to [`BlockingMode::Blocking`]. A
+    [`BlockingMode::Timeout`] of zero makes opening fail with
+    [`InvalidInput`].
+
+    [`BlockingMode::Blocking`]: enum.BlockingMode.html#variant.Blocking
+    [`BlockingMode::Timeout`]: enum.BlockingMode.html#variant.Timeout
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    #[cfg(target_family = "unix")]
+    pub fn blocking_mode(&mut self, mode: BlockingMode) -> &mut Self {
+        (self.nonblock, self.read_timeout) = match mode {
+            BlockingMode::Blocking => (false, None),
+            BlockingMode::NonBlocking => (true, None),
+            BlockingMode::Timeout(timeout) => (false, Some(timeout)),
+        };
+        self
+    }
+
+    /**
+    Sets the option for signal-driven I/O: `pid`, or the current process if
+    `None`, receives `SIGIO` whenever a packet is waiting, as set by
+    [`Device::set_signal_driven`].
+
+    This option is off by default, and [`clear_signal_driven`] turns it off
+    again.
+
+    [`Device::set_signal_driven`]: struct.Device.html#method.set_signal_driven
+    [`clear_signal_driven`]: struct.OpenOptions.html#method.clear_signal_driven
+    */
     #[cfg(target_family = "unix")]
-    fn nonblock(&mut self, enabled: bool) -> &mut Self {
-        self.nonblock = enabled;
+    pub fn signal_driven(&mut self, pid: Option<libc::pid_t>) -> &mut Self {
+        self.signal_owner = Some(pid.unwrap_or_else(|| unsafe { libc::getpid() }));
+        self
+    }
+
+    /**
+    Turns the option for signal-driven I/O set by [`signal_driven`] off, so
+    that the device is opened without `O_ASYNC`.
+
+    [`signal_driven`]: struct.OpenOptions.html#method.signal_driven
+    */
+    #[cfg(target_family = "unix")]
+    pub fn clear_signal_driven(&mut self) -> &mut Self {
+        self.signal_owner = None;
+        self
+    }
+
+    /**
+    Sets the size of the buffer [`Device::read_frame_borrowed`] reads into,
+    which is then allocated when the device is opened.
+
+    By default, the buffer is sized from the MTU by the first read. A
+    capacity of 0 makes opening fail with [`InvalidInput`].
+
+    [`Device::read_frame_borrowed`]: struct.Device.html#method.read_frame_borrowed
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    pub fn buffer_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.buffer_capacity = Some(capacity);
         self
     }
 
-    fn mode(&mut self, mode: Mode) -> &mut Self {
+    /**
+    Sets the kind of device to open.
+    */
+    pub fn mode(&mut self, mode: Mode) -> &mut Self {
         self.mode = mode;
         self
     }
 
+    /**
+    Sets the option for packet info.
+
+    This option is only available on Linux.
+    */
     #[cfg(target_os = "linux")]
-    fn packet_info(&mut self, enabled: bool) -> &mut Self {
+    pub fn packet_info(&mut self, enabled: bool) -> &mut Self {
         self.packet_info = enabled;
         self
     }
 
+    /**
+    Sets the option for packet info by its inverse, `IFF_NO_PI`.
+
+    This option is only available on Linux.
+    */
     #[cfg(target_os = "linux")]
-    fn open(&mut self, number: u32) -> Result<File> {
-        use std::{
-            io::Error,
-            os::unix::{fs::OpenOptionsExt, io::AsRawFd},
+    pub fn no_packet_info(&mut self, enabled: bool) -> &mut Self {
+        self.packet_info = !enabled;
+        self
+    }
+
+    /**
+    Sets the exact name of the interface to open, instead of deriving it from
+    the device number.
+
+    This option is only available on Linux.
+    */
+    #[cfg(target_os = "linux")]
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.name = Some(name.to_string());
+        self.preferred_name = false;
+        self
+    }
+
+    /**
+    Sets the name to open the interface with if it can be had, letting the
+    kernel pick one otherwise.
+
+    Opening first tries `name` as [`name`] does. When the interface is in
+    use, exists as another kind of interface or cannot be attached to, the
+    device is opened with a name the kernel assigns instead, as with
+    [`ephemeral`]; the name returned tells which. Errors unrelated to the
+    name, e.g. a missing permission, are returned at once.
+
+    This option is only available on Linux.
+
+    [`name`]: struct.OpenOptions.html#method.name
+    [`ephemeral`]: struct.OpenOptions.html#method.ephemeral
+    */
+    #[cfg(target_os = "linux")]
+    pub fn preferred_name(&mut self, name: &str) -> &mut Self {
+        self.name = Some(name.to_string());
+        self.preferred_name = true;
+        self
+    }
+
+    /**
+    Sets the option for the virtio net header.
+
+    This option is only available on Linux.
+    */
+    #[cfg(target_os = "linux")]
+    pub fn vnet_hdr(&mut self, enabled: bool) -> &mut Self {
+        self.vnet_hdr = Some(enabled);
+        self
+    }
+
+    /**
+    Sets the offloads to negotiate with the kernel once the device is open.
+
+    This option implies [`vnet_hdr`], and is only available on Linux.
+
+    [`vnet_hdr`]: struct.OpenOptions.html#method.vnet_hdr
+    */
+    #[cfg(target_os = "linux")]
+    pub fn offload(&mut self, offload: tun::Offload) -> &mut Self {
+        self.offload = offload;
+        self
+    }
+
+    /**
+    Sets the option to fail when the named device already exists.
+
+    This option is only available on Linux.
+    */
+    #[cfg(target_os = "linux")]
+    pub fn exclusive(&mut self, enabled: bool) -> &mut Self {
+        self.exclusive = enabled;
+        self
+    }
+
+    /**
+    Sets the clone device to open, instead of `/dev/net/tun` or, where it
+    does not exist, `/dev/tun`.
+
+    This option is only available on Linux.
+    */
+    #[cfg(target_os = "linux")]
+    pub fn device_path(&mut self, path: impl AsRef<std::path::Path>) -> &mut Self {
+        self.device_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /**
+    Lets the kernel assign the name of the device, e.g. `tun3`.
+
+    This option is only available on Linux.
+    */
+    #[cfg(target_os = "linux")]
+    pub fn ephemeral(&mut self) -> &mut Self {
+        self.name = Some(String::new());
+        self.preferred_name = false;
+        self
+    }
+
+    /**
+    Sets the option for NAPI processing of written packets.
+
+    This option is only available on Linux.
+    */
+    #[cfg(target_os = "linux")]
+    pub fn napi(&mut self, enabled: bool) -> &mut Self {
+        self.napi = enabled;
+        self
+    }
+
+    /**
+    Sets the option for NAPI processing of written fragments.
+
+    This option is only available on Linux.
+    */
+    #[cfg(target_os = "linux")]
+    pub fn napi_frags(&mut self, enabled: bool) -> &mut Self {
+        self.napi_frags = enabled;
+        self
+    }
+
+    /**
+    Sets the option to strip the protocol information from reads and writes
+    of the opened [`Device`].
+
+    [`Device`]: struct.Device.html
+    */
+    pub fn strip_protocol_info(&mut self, enabled: bool) -> &mut Self {
+        self.strip_protocol_info = enabled;
+        self
+    }
+
+    /**
+    Sets the name of the kernel control to connect to instead of the utun
+    control.
+
+    This option is only available on macOS.
+    */
+    #[cfg(target_os = "macos")]
+    pub fn control_name(&mut self, name: &str) -> &mut Self {
+        self.control_name = Some(name.to_string());
+        self
+    }
+
+    /**
+    Sets the MTU to give the interface before the device is returned.
+
+    This option is only available on Linux and macOS.
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn mtu(&mut self, mtu: u32) -> &mut Self {
+        self.mtu = Some(mtu);
+        self
+    }
+
+    /**
+    Lets the kernel pick the first free utun unit, connecting with
+    `sc_unit` 0.
+
+    This option is only available on macOS.
+    */
+    #[cfg(target_os = "macos")]
+    pub fn any_unit(&mut self) -> &mut Self {
+        self.any_unit = true;
+        self
+    }
+
+    /**
+    Opens a device file with the options specified by `self`, returning the
+    file and the name of the device.
+
+    # Arguments
+
+    * `number` - the number of the device, e.g. the "0" of "tun0". On Linux,
+      OpenBSD, illumos and for utun devices on macOS, when it is not set, the OS will
+      assign one for you; the returned name tells which.
+
+    # Errors
+
+    Besides the errors of [`tun::OpenOptions::open`], this function returns
+    [`Unsupported`] when a Tap device is requested on macOS.
+
+    [`tun::OpenOptions::open`]: tun/struct.OpenOptions.html#method.open
+    [`Unsupported`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.Unsupported
+    */
+    pub fn open(&mut self, number: impl Into<Option<u32>>) -> Result<(File, String)> {
+        Ok(self.open_device(number)?.into_parts())
+    }
+
+    /**
+    Opens a device with the options specified by `self`, returning a
+    [`Device`].
+
+    [`Device`]: struct.Device.html
+    */
+    pub fn open_device(&mut self, number: impl Into<Option<u32>>) -> Result<Device> {
+        if self.buffer_capacity == Some(0) {
+            return Err(empty_buffer());
+        }
+        let (file, name, source) = self.open_file(number.into())?;
+        let mut device = Device::new(file, name);
+        device.source = source;
+        // The device destroys the interface when dropped, even if opening
+        // fails below.
+        #[cfg(all(target_os = "macos", feature = "macos-feth"))]
+        if self.mode == Mode::Tap {
+            device.feth = Some(std::sync::Arc::new(feth::Feth::new(device.name.clone())));
+        }
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        if let Some(mtu) = self.mtu {
+            trace::traced(
+                "SIOCSIFMTU",
+                &device.name,
+                interface::set_mtu(&device.name, mtu),
+            )?;
+        }
+        device.set_read_timeout(self.read_timeout)?;
+        #[cfg(target_family = "unix")]
+        if let Some(pid) = self.signal_owner {
+            device.set_signal_driven(Some(pid))?;
+        }
+        if let Some(capacity) = self.buffer_capacity {
+            device.read_buffer = vec![0; capacity];
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            device.packet_info = self.packet_info;
+            device.load_tun_flags(self.packet_info)?;
+        }
+        // Tap devices only carry a header with the packet info of Linux.
+        let has_header =
+            device.has_header() && (cfg!(target_os = "linux") || self.mode == Mode::Tun);
+        if self.strip_protocol_info && has_header {
+            device.strip_protocol_info = Some(self.mode);
+        }
+        #[cfg(target_os = "linux")]
+        if !self.offload.is_empty() {
+            device.offload = trace::traced(
+                "TUNSETOFFLOAD",
+                &device.name,
+                tun::negotiate_offload(&device.file, self.offload),
+            )?;
+        }
+        trace::opened(&device.name);
+
+        Ok(device)
+    }
+
+    /**
+    Opens a device with the options specified by `self`, consuming the
+    builder and returning a [`Device`].
+
+    This is the one-shot counterpart of [`open_device`], for when the options
+    are not reused after opening.
+
+    # Examples
+
+    ```no_run
+    use utuntap::{Mode, OpenOptions};
+
+    let mut options = OpenOptions::new(Mode::Tun);
+    options.packet_info(false);
+    let device = options.open_owned(0).unwrap();
+    println!("opened {}", device.name());
+    ```
+
+    [`Device`]: struct.Device.html
+    [`open_device`]: struct.OpenOptions.html#method.open_device
+    */
+    pub fn open_owned(mut self, number: impl Into<Option<u32>>) -> Result<Device> {
+        self.open_device(number)
+    }
+
+    /**
+    Opens a device in non-blocking mode with the options specified by
+    `self`, returning an [`AsyncDevice`] registered in the async-io reactor.
+
+    This method is only available with the `async-io` feature.
+
+    [`AsyncDevice`]: struct.AsyncDevice.html
+    */
+    #[cfg(feature = "async-io")]
+    pub fn open_async(&mut self, number: impl Into<Option<u32>>) -> Result<AsyncDevice> {
+        self.blocking_mode(BlockingMode::NonBlocking);
+        AsyncDevice::new(self.open_device(number)?)
+    }
+
+    /**
+    Opens a device like [`open_async`] and splits it right away, returning
+    the halves which read and write it along with the name of the device.
+
+    See [`AsyncDevice::split`] for how the halves relate.
+
+    This method is only available with the `async-io` feature.
+
+    [`open_async`]: struct.OpenOptions.html#method.open_async
+    [`AsyncDevice::split`]: struct.AsyncDevice.html#method.split
+    */
+    #[cfg(feature = "async-io")]
+    pub fn open_async_split(
+        &mut self,
+        number: impl Into<Option<u32>>,
+    ) -> Result<(AsyncReadHalf, AsyncWriteHalf, String)> {
+        let device = self.open_async(number)?;
+        let name = device.name().to_string();
+        let (reader, writer) = device.split()?;
+        Ok((reader, writer, name))
+    }
+
+    /**
+    Opens a device file with the options specified by `self`, writing the
+    name of the device into `name_buf` instead of allocating a `String`.
+
+    Returns the file and the length of the name. On Linux, opening a device
+    allocates no memory, unless the path set with [`device_path`] is too
+    long to be turned into a C string on the stack; failing may still
+    allocate the error. Other platforms build the name internally before
+    copying it.
+
+    The options which shape a [`Device`] rather than its file, i.e.
+    [`blocking_mode`] with a timeout, [`buffer_capacity`] and
+    [`strip_protocol_info`], do not apply to the returned file, as with
+    [`open`]. The [`signal_driven`] option does.
+
+    # Errors
+
+    Besides the errors of [`open`], this function returns [`InvalidInput`]
+    when the name does not fit in `name_buf`. The device is closed again
+    then.
+
+    # Examples
+
+    ```no_run
+    use utuntap::{Mode, OpenOptions};
+
+    let mut name = [0; 16];
+    let (file, len) = OpenOptions::new(Mode::Tun).open_into(0, &mut name).unwrap();
+    println!("opened {}", std::str::from_utf8(&name[..len]).unwrap());
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`device_path`]: struct.OpenOptions.html#method.device_path
+    [`Device`]: struct.Device.html
+    [`blocking_mode`]: struct.OpenOptions.html#method.blocking_mode
+    [`buffer_capacity`]: struct.OpenOptions.html#method.buffer_capacity
+    [`strip_protocol_info`]: struct.OpenOptions.html#method.strip_protocol_info
+    [`signal_driven`]: struct.OpenOptions.html#method.signal_driven
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    pub fn open_into(
+        &mut self,
+        number: impl Into<Option<u32>>,
+        name_buf: &mut [u8],
+    ) -> Result<(File, usize)> {
+        #[cfg(target_os = "linux")]
+        {
+            let (file, request, _) = self.open_request(number.into())?;
+            let len = request.copy_name(name_buf)?;
+            if let Some(mtu) = self.mtu {
+                let name = std::str::from_utf8(&name_buf[..len])
+                    .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+                trace::traced("SIOCSIFMTU", name, interface::set_mtu(name, mtu))?;
+            }
+            if !self.offload.is_empty() {
+                let name = String::from_utf8_lossy(&name_buf[..len]);
+                trace::traced(
+                    "TUNSETOFFLOAD",
+                    &name,
+                    tun::negotiate_offload(&file, self.offload),
+                )?;
+            }
+            if let Some(pid) = self.signal_owner {
+                device::set_signal_driven(&file, Some(pid))?;
+            }
+            Ok((file, len))
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let (file, name) = self.open(number)?;
+            let len = name.len();
+            name_buf
+                .get_mut(..len)
+                .ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "the name buffer is too short",
+                    )
+                })?
+                .copy_from_slice(name.as_bytes());
+            Ok((file, len))
+        }
+    }
+
+    /**
+    Opens a multi-queue device with `queues` files attached to it, returning
+    each file along with the index of its queue, and the name of the device.
+
+    The kernel spreads the packets it sends to the device over the queues,
+    keeping the packets of a flow on one queue, so that the files can be
+    read by as many threads. The index of a queue is its position among the
+    queues of the device, from 0, as in `/sys/class/net/<name>/queues`, and
+    lets the caller pin the thread serving it, e.g. next to the CPU the
+    receive packet steering of the queue points to. Indices shift down when
+    a queue before them is closed, as the kernel renumbers the queues left.
+
+    Every queue is opened with the options specified by `self`. With
+    [`exclusive`], only the first queue requires the device to be new.
+
+    # Errors
+
+    Besides the errors of [`open`], this function returns [`InvalidInput`]
+    when `queues` is 0, and the OS error `EINVAL` when the device exists
+    without multiple queues. Opening more queues than the device supports,
+    256 by default, fails with `E2BIG`. The queues opened so far are closed
+    again then.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::{Mode, OpenOptions};
+
+    let (queues, name) = OpenOptions::new(Mode::Tun).open_multiqueue(0, 4).unwrap();
+    for (file, index) in queues {
+        std::thread::spawn(move || {
+            // pin the thread for queue `index`, then read `file`
+            let _ = (file, index);
+        });
+    }
+    println!("opened {}", name);
+    ```
+
+    [`exclusive`]: struct.OpenOptions.html#method.exclusive
+    [`open`]: struct.OpenOptions.html#method.open
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_multiqueue(
+        &mut self,
+        number: impl Into<Option<u32>>,
+        queues: usize,
+    ) -> Result<(Vec<(File, usize)>, String)> {
+        use interface::linux::{IFF_MULTI_QUEUE, IFF_TUN_EXCL};
+
+        if queues == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "a multi-queue device needs at least one queue",
+            ));
+        }
+        let flags = self.tuntap_flags()? | IFF_MULTI_QUEUE;
+        let (file, request, _) = self.attach_number(number.into(), flags)?;
+        let name = request.name()?;
+        if let Some(mtu) = self.mtu {
+            trace::traced("SIOCSIFMTU", &name, interface::set_mtu(&name, mtu))?;
+        }
+        if !self.offload.is_empty() {
+            trace::traced(
+                "TUNSETOFFLOAD",
+                &name,
+                tun::negotiate_offload(&file, self.offload),
+            )?;
+        }
+
+        // The kernel numbers the queues in the order they are attached.
+        let mut files = vec![(file, 0)];
+        for index in 1..queues {
+            let (file, ..) = self.attach(&name, flags & !IFF_TUN_EXCL)?;
+            files.push((file, index));
+        }
+        trace::opened(&name);
+        Ok((files, name))
+    }
+
+    /**
+    Opens a device twice, returning a read-only file, a write-only file and
+    the name of the device.
+
+    Unlike a file duplicated with `try_clone`, both files are separate open
+    file descriptions of their own, so reads and writes do not contend for
+    one queue of the driver. Two files can only be attached to a multi-queue
+    device, so the device is opened as with [`open_multiqueue`]; the queue
+    of the writer is then detached, so that the kernel sends every packet
+    to the reader while packets written to the writer still go through.
+    This is most useful for multi-queue devices, where more readers can be
+    attached later by name.
+
+    The [`read`] and [`write`] options of `self` are ignored; the others
+    apply to both files. If the writer cannot be opened, the reader is
+    closed again.
+
+    # Errors
+
+    Besides the errors of [`open`], this function returns the OS error
+    `EINVAL` when the device exists without multiple queues.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use std::io::{Read, Write};
+    use utuntap::{Mode, OpenOptions};
+
+    let (mut reader, mut writer, name) = OpenOptions::new(Mode::Tun).open_rw_split(0).unwrap();
+    let mut buffer = [0; 1500];
+    let number = reader.read(&mut buffer).unwrap();
+    writer.write(&buffer[..number]).unwrap();
+    ```
+
+    [`open_multiqueue`]: struct.OpenOptions.html#method.open_multiqueue
+    [`read`]: struct.OpenOptions.html#method.read
+    [`write`]: struct.OpenOptions.html#method.write
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_rw_split(
+        &mut self,
+        number: impl Into<Option<u32>>,
+    ) -> Result<(File, File, String)> {
+        use interface::linux::{Request, IFF_MULTI_QUEUE, IFF_TUN_EXCL};
+        use std::os::unix::io::AsRawFd;
+
+        let flags = self.tuntap_flags()? | IFF_MULTI_QUEUE;
+        let access = (self.read, self.write);
+        (self.read, self.write) = (true, false);
+        let result = self
+            .attach_number(number.into(), flags)
+            .map(|(reader, request, _)| (reader, request))
+            .and_then(|(reader, request)| {
+                let name = request.name()?;
+                (self.read, self.write) = (false, true);
+                let (writer, ..) = self.attach(&name, flags & !IFF_TUN_EXCL)?;
+                Ok((reader, writer, name))
+            });
+        (self.read, self.write) = access;
+        let (reader, writer, name) = result?;
+
+        trace::traced(
+            "TUNSETQUEUE",
+            &name,
+            Request::detach_queue(writer.as_raw_fd()),
+        )?;
+        if let Some(mtu) = self.mtu {
+            trace::traced("SIOCSIFMTU", &name, interface::set_mtu(&name, mtu))?;
+        }
+        if !self.offload.is_empty() {
+            trace::traced(
+                "TUNSETOFFLOAD",
+                &name,
+                tun::negotiate_offload(&reader, self.offload),
+            )?;
+        }
+        trace::opened(&name);
+        Ok((reader, writer, name))
+    }
+
+    /**
+    Opens the device named `name`, creating its interface unless it exists,
+    and tells whether it was created: `true` for a new interface, `false`
+    when attaching to an existing, persistent one.
+
+    The interface is first asked for with `IFF_TUN_EXCL`, which the kernel
+    refuses with `EBUSY` when it already exists; the device is then opened
+    again without it. Whether the interface was created is thus decided by
+    the kernel rather than by looking it up beforehand. Still, an interface
+    deleted between both attempts is reported as attached to although the
+    second attempt created it. The [`name`] and [`exclusive`] options of
+    `self` are left as they were.
+
+    # Errors
+
+    Besides the errors of [`open`], this function fails with `EBUSY` when
+    the existing interface is in use by another process, and with an error
+    carrying a [`ModeMismatch`] when it is of the other mode.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::{Mode, OpenOptions};
+
+    let (device, created) = OpenOptions::new(Mode::Tun).open_or_attach("wg0").unwrap();
+    if created {
+        // configure the new interface
+    }
+    ```
+
+    [`name`]: struct.OpenOptions.html#method.name
+    [`exclusive`]: struct.OpenOptions.html#method.exclusive
+    [`open`]: struct.OpenOptions.html#method.open
+    [`ModeMismatch`]: struct.ModeMismatch.html
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_or_attach(&mut self, name: &str) -> Result<(Device, bool)> {
+        let saved = (
+            self.name.replace(name.to_string()),
+            std::mem::take(&mut self.preferred_name),
+            std::mem::replace(&mut self.exclusive, true),
+        );
+        let result = match self.open_device(None) {
+            Err(error) if is_busy(&error) => {
+                self.exclusive = false;
+                self.open_device(None).map(|device| (device, false))
+            }
+            result => result.map(|device| (device, true)),
         };
+        (self.name, self.preferred_name, self.exclusive) = saved;
+        result
+    }
 
-        let file = {
+    /**
+    Opens a device file with the options specified by `self` inside the
+    network namespace `netns`, e.g. `/var/run/netns/blue` or
+    `/proc/<pid>/ns/net`, so that its interface lives there.
+
+    The calling thread enters `netns` with `setns` for the duration of the
+    call and returns to its original namespace afterwards, whether opening
+    succeeded or not. The file keeps working from any namespace. Entering a
+    namespace needs `CAP_SYS_ADMIN`.
+
+    # Errors
+
+    Besides the errors of [`open`], this function fails when `netns` cannot
+    be opened or entered. If the thread cannot return to its original
+    namespace, the device is closed and that error is returned.
+
+    This method is only available on Linux.
+
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_in_netns(
+        &mut self,
+        netns: &std::path::Path,
+        number: impl Into<Option<u32>>,
+    ) -> Result<(File, String)> {
+        use std::os::unix::io::AsRawFd;
+
+        let netns_name = netns.to_string_lossy();
+        let enter = |namespace: &File| {
+            let result = if unsafe { libc::setns(namespace.as_raw_fd(), libc::CLONE_NEWNET) } < 0 {
+                Err(std::io::Error::last_os_error())
+            } else {
+                Ok(())
+            };
+            trace::traced("setns", &netns_name, result)
+        };
+
+        let original = File::open("/proc/thread-self/ns/net")?;
+        let target = File::open(netns)?;
+        enter(&target)?;
+        let result = self.open(number);
+        enter(&original)?;
+        result
+    }
+
+    /**
+    Like [`open`], but retries `attempts` times in total, sleeping `delay`
+    in between, while the device is transiently unavailable, e.g. while
+    `/dev/net/tun` is yet to be created during early boot or in a container.
+
+    Only `NotFound` errors, i.e. `ENOENT`, and `ENODEV` are retried; any
+    other error, e.g. `EPERM`, is returned at once. The error of the last
+    attempt is returned when all of them fail. The device is opened at
+    least once, even when `attempts` is 0.
+
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    pub fn open_with_retry(
+        &mut self,
+        number: impl Into<Option<u32>>,
+        attempts: u32,
+        delay: std::time::Duration,
+    ) -> Result<(File, String)> {
+        let number = number.into();
+        let mut remaining = attempts.max(1);
+        loop {
+            remaining -= 1;
+            match self.open(number) {
+                Err(error) if remaining > 0 && is_transient(&error) => std::thread::sleep(delay),
+                result => return result,
+            }
+        }
+    }
+
+    /**
+    Checks that the device `number` can be opened with the options specified
+    by `self`, without opening it or making any system call.
+
+    This returns the error [`open`] fails with before reaching the OS: an
+    invalid name, number or combination of options is reported with
+    [`InvalidInput`], and a Tap device on macOS without the `macos-feth`
+    feature with [`Unsupported`]. Errors only the OS can tell, e.g. a
+    missing permission or a device in use, are left to [`open`].
+
+    # Examples
+
+    ```no_run
+    use utuntap::{Mode, OpenOptions};
+
+    let mut options = OpenOptions::new(Mode::Tun);
+    options.read(false).write(false);
+    assert!(options.validate(0).is_err());
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    [`Unsupported`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.Unsupported
+    */
+    pub fn validate(&self, number: impl Into<Option<u32>>) -> Result<()> {
+        let number = number.into();
+        #[cfg(all(target_os = "macos", not(feature = "macos-feth")))]
+        if self.mode == Mode::Tap {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                TAP_UNSUPPORTED,
+            ));
+        }
+        // Device files are opened for reading, writing or both, as
+        // `std::fs::OpenOptions` requires; utun sockets are not.
+        let has_file = !cfg!(target_os = "macos") || self.mode == Mode::Tap;
+        if has_file && !self.read && !self.write {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "a device must be opened for reading, writing or both",
+            ));
+        }
+        if self.read_timeout == Some(std::time::Duration::ZERO) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "cannot set a 0 duration timeout",
+            ));
+        }
+        if self.buffer_capacity == Some(0) {
+            return Err(empty_buffer());
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            self.tuntap_flags()?;
+            let mut buffer = [0u8; libc::IFNAMSIZ];
+            interface::linux::Request::new(self.interface_name(number, &mut buffer)?)?;
+        }
+        #[cfg(target_os = "macos")]
+        if self.mode == Mode::Tun {
+            self.utun_unit(number)?;
+        }
+        #[cfg(any(target_os = "illumos", target_os = "solaris"))]
+        ppa_for_number(number)?;
+        #[cfg(target_os = "openbsd")]
+        let _ = number;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn open_file(&mut self, number: Option<u32>) -> Result<(File, String, DeviceSource)> {
+        let (file, request, node) = self.open_request(number)?;
+        let source = DeviceSource::Node(node.to_path_buf());
+        Ok((file, request.name()?, source))
+    }
+
+    /// Opens a device file, returning it along with the request which
+    /// attached it, whose name the kernel resolved, and the clone device
+    /// opened.
+    #[cfg(target_os = "linux")]
+    fn open_request(
+        &mut self,
+        number: Option<u32>,
+    ) -> Result<(File, interface::linux::Request, &std::path::Path)> {
+        let flags = self.tuntap_flags()?;
+        self.attach_number(number, flags)
+    }
+
+    /// Attaches to the interface of the device `number` with the `IFF_*`
+    /// flags `flags`, falling back to a name the kernel picks when the
+    /// preferred name cannot be had.
+    #[cfg(target_os = "linux")]
+    fn attach_number(
+        &self,
+        number: Option<u32>,
+        flags: libc::c_short,
+    ) -> Result<(File, interface::linux::Request, &std::path::Path)> {
+        let mut buffer = [0u8; libc::IFNAMSIZ];
+        let name = self.interface_name(number, &mut buffer)?;
+        match self.attach(name, flags) {
+            Err(error) if self.preferred_name && !name.is_empty() && name_is_taken(&error) => {
+                self.attach("", flags)
+            }
+            result => result,
+        }
+    }
+
+    /// Opens the clone device and attaches it to the interface `name` with
+    /// the `IFF_*` flags `flags`, returning the clone device opened as well.
+    #[cfg(target_os = "linux")]
+    fn attach(
+        &self,
+        name: &str,
+        flags: libc::c_short,
+    ) -> Result<(File, interface::linux::Request, &std::path::Path)> {
+        use interface::linux::Request;
+        use std::os::unix::{fs::OpenOptionsExt, io::AsRawFd};
+
+        let (file, node) = {
             let mut options = std::fs::OpenOptions::new();
 
             options.read(self.read).write(self.write);
@@ -90,94 +1221,249 @@ impl OpenOptions {
                 options.custom_flags(O_NONBLOCK);
             }
 
-            options.open("/dev/net/tun")?
+            // Later nodes are tried while the earlier ones are missing; the
+            // error of the first is returned if none can be opened.
+            let mut nodes = self.tun_nodes();
+            let first = nodes.next().expect("there is a node to try");
+            let (mut node, mut result) = (first, options.open(first));
+            for next in nodes {
+                if !matches!(&result, Err(error) if error.kind() == std::io::ErrorKind::NotFound) {
+                    break;
+                }
+                if let Ok(file) = options.open(next) {
+                    (node, result) = (next, Ok(file));
+                }
+            }
+            (
+                trace::traced("open", &first.to_string_lossy(), result)?,
+                node,
+            )
+        };
+
+        let request = Request::with_flags(name, flags)?;
+        let request = match request.set_tuntap(file.as_raw_fd()) {
+            // An existing interface of the other mode is refused with EINVAL.
+            Err(error) if error.raw_os_error() == Some(libc::EINVAL) && !name.is_empty() => {
+                match interface::linux::get_mode(name) {
+                    Ok(Some(existing)) if existing != self.mode => {
+                        return Err(ModeMismatch {
+                            name: name.to_string(),
+                            requested: self.mode,
+                            existing,
+                        }
+                        .into())
+                    }
+                    _ => trace::traced("TUNSETIFF", name, Err(error))?,
+                }
+            }
+            result => trace::traced("TUNSETIFF", name, result)?,
+        };
+
+        Ok((file, request, node))
+    }
+
+    /// Returns the clone devices to try in turn: the [`device_path`] option,
+    /// or else [`TUN_NODES`].
+    ///
+    /// [`device_path`]: Self::device_path
+    #[cfg(target_os = "linux")]
+    fn tun_nodes(&self) -> impl Iterator<Item = &std::path::Path> {
+        let defaults = match self.device_path {
+            Some(_) => &TUN_NODES[..0],
+            None => &TUN_NODES[..],
         };
+        let defaults = defaults.iter().map(std::path::Path::new);
+        self.device_path.as_deref().into_iter().chain(defaults)
+    }
 
-        use libc::{__c_anonymous_ifr_ifru, c_int, c_short, ifreq, ioctl, strcpy};
-        use std::{ffi::CString, mem};
+    /// Returns the name `TUNSETIFF` is issued with for the device `number`,
+    /// formatting it into `buffer`: an empty name lets the kernel pick one.
+    #[cfg(target_os = "linux")]
+    fn interface_name<'a>(
+        &'a self,
+        number: Option<u32>,
+        buffer: &'a mut [u8; libc::IFNAMSIZ],
+    ) -> Result<&'a str> {
+        use std::io::Write;
 
-        const IFF_TUN: c_short = 0x0001;
-        const IFF_TAP: c_short = 0x0002;
-        const IFF_NO_PI: c_short = 0x1000;
-        #[cfg(target_env = "musl")]
-        type RequestId = c_int;
-        #[cfg(not(target_env = "musl"))]
-        type RequestId = libc::c_ulong;
-        const TUNSETIFF: RequestId = request_code_write!(b'T', 202, mem::size_of::<c_int>());
+        // The name is formatted on the stack; too long ones fail to fit.
+        let name = match (number, &self.name) {
+            (Some(_), Some(_)) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "a device cannot be opened by both number and name",
+                ))
+            }
+            (Some(number), None) => {
+                let mut cursor = &mut buffer[..];
+                write!(cursor, "{}{}", self.mode, number).map_err(|_| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid interface name")
+                })?;
+                let len = libc::IFNAMSIZ - cursor.len();
+                std::str::from_utf8(&buffer[..len]).expect("formatted names are ASCII")
+            }
+            (None, Some(name)) => name.as_str(),
+            (None, None) => "",
+        };
+        Ok(name)
+    }
 
-        let mut request = ifreq {
-            ifr_name: Default::default(),
-            ifr_ifru: __c_anonymous_ifr_ifru {
-                ifru_flags: {
-                    let mut flags = match self.mode {
-                        Mode::Tun => IFF_TUN,
-                        Mode::Tap => IFF_TAP,
-                    };
-                    if !self.packet_info {
-                        flags |= IFF_NO_PI;
-                    }
-                    flags
-                },
-            },
+    /// Computes the `IFF_*` flags `TUNSETIFF` is issued with: the mode,
+    /// `IFF_NO_PI` unless packet info is wanted, then the optional flags.
+    #[cfg(target_os = "linux")]
+    fn tuntap_flags(&self) -> Result<libc::c_short> {
+        use interface::linux::{IFF_NAPI, IFF_NAPI_FRAGS, IFF_NO_PI, IFF_TUN_EXCL, IFF_VNET_HDR};
+
+        let mut flags = self.mode.iff_flag();
+        if !self.packet_info {
+            flags |= IFF_NO_PI;
+        }
+        if self.exclusive {
+            flags |= IFF_TUN_EXCL;
+        }
+        if self.napi {
+            flags |= IFF_NAPI;
+        }
+        if self.napi_frags {
+            flags |= IFF_NAPI_FRAGS;
+        }
+        match (self.vnet_hdr, self.offload.is_empty()) {
+            (Some(false), false) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "offloads require the virtio net header",
+                ))
+            }
+            (Some(true), _) | (None, false) => flags |= IFF_VNET_HDR,
+            _ => {}
+        }
+        Ok(flags)
+    }
+
+    #[cfg(target_os = "openbsd")]
+    fn open_file(&mut self, number: Option<u32>) -> Result<(File, String, DeviceSource)> {
+        use std::{
+            io::Error,
+            os::unix::{fs::OpenOptionsExt, io::AsRawFd},
+        };
+
+        // Without a number, the clone node /dev/tun or /dev/tap hands out
+        // the next free unit.
+        let filename = match number {
+            Some(number) => format!("{}{}", self.mode, number),
+            None => self.mode.to_string(),
         };
 
-        let device_name = CString::new(format!("{}{}", self.mode, number))?;
+        let path = std::path::Path::new("/dev").join(&filename);
+        let file = {
+            let mut options = std::fs::OpenOptions::new();
+
+            options.read(self.read).write(self.write);
+            if self.nonblock {
+                options.custom_flags(O_NONBLOCK);
+            }
 
-        unsafe {
-            strcpy(request.ifr_name.as_mut_ptr(), device_name.as_ptr());
+            trace::traced("open", &filename, options.open(&path))?
+        };
+        if number.is_some() {
+            return Ok((file, filename, DeviceSource::Node(path)));
         }
 
-        let err = unsafe { ioctl(file.as_raw_fd(), TUNSETIFF, &mut request) };
-        if err != 0 {
+        // The unit assigned is the minor number of the device opened.
+        let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+        if unsafe { libc::fstat(file.as_raw_fd(), &mut stat) } < 0 {
             return Err(Error::last_os_error());
         }
+        let unit = libc::minor(stat.st_rdev);
 
-        Ok(file)
+        Ok((
+            file,
+            format!("{}{}", self.mode, unit),
+            DeviceSource::Node(path),
+        ))
     }
 
-    #[cfg(target_os = "openbsd")]
-    fn open(&mut self, number: u32) -> Result<File> {
-        use std::os::unix::fs::OpenOptionsExt;
+    #[cfg(any(target_os = "illumos", target_os = "solaris"))]
+    fn open_file(&mut self, number: Option<u32>) -> Result<(File, String, DeviceSource)> {
+        use std::os::unix::{fs::OpenOptionsExt, io::AsRawFd};
 
+        /// `I_STR` of `<sys/stropts.h>`, sending an ioctl down the stream.
+        const I_STR: libc::c_int = ((b'S' as libc::c_int) << 8) | 0o10;
+        /// Allocates a PPA, i.e. a device number, in the tun driver.
+        const TUNNEWPPA: libc::c_int = ((b'T' as libc::c_int) << 16) | 0x0001;
+
+        #[repr(C)]
+        struct strioctl {
+            ic_cmd: libc::c_int,
+            ic_timout: libc::c_int,
+            ic_len: libc::c_int,
+            ic_dp: *mut libc::c_char,
+        }
+
+        let mut ppa = ppa_for_number(number)?;
+
+        let path = std::path::Path::new("/dev").join(self.mode.to_string());
         let file = {
             let mut options = std::fs::OpenOptions::new();
 
-            let filename = format!("{}{}", self.mode, number);
-
             options.read(self.read).write(self.write);
             if self.nonblock {
                 options.custom_flags(O_NONBLOCK);
             }
 
-            let path = std::path::Path::new("/dev").join(&filename);
-            options.open(path)?
+            trace::traced("open", &self.mode.to_string(), options.open(&path))?
         };
 
-        Ok(file)
+        let mut request = strioctl {
+            ic_cmd: TUNNEWPPA,
+            ic_timout: 0,
+            ic_len: std::mem::size_of::<libc::c_int>() as libc::c_int,
+            ic_dp: &mut ppa as *mut libc::c_int as *mut libc::c_char,
+        };
+        let ppa = retry_eintr(|| {
+            let ppa = unsafe { libc::ioctl(file.as_raw_fd(), I_STR, &mut request) };
+            trace::check("TUNNEWPPA", &self.mode.to_string(), ppa)
+        })?;
+
+        Ok((
+            file,
+            format!("{}{}", self.mode, ppa),
+            DeviceSource::Node(path),
+        ))
     }
 
     #[cfg(target_os = "macos")]
-    fn open(&mut self, number: u32) -> Result<File> {
+    fn open_file(&mut self, number: Option<u32>) -> Result<(File, String, DeviceSource)> {
         use libc::{
-            c_ulong, connect, fcntl, ioctl, sockaddr, sockaddr_ctl, socket, socklen_t, FD_CLOEXEC,
-            F_SETFD, F_SETFL, PF_SYSTEM, SOCK_DGRAM, SYSPROTO_CONTROL,
+            c_ulong, connect, fcntl, getpeername, ioctl, sockaddr, sockaddr_ctl, socket, socklen_t,
+            FD_CLOEXEC, F_SETFD, F_SETFL, PF_SYSTEM, SOCK_DGRAM, SYSPROTO_CONTROL,
         };
         use std::{
             ffi::{c_uchar, c_ushort},
             io::Error,
             mem,
-            os::fd::FromRawFd,
+            os::fd::{AsRawFd, FromRawFd, OwnedFd},
         };
         const AF_SYSTEM: c_uchar = 32;
         const AF_SYS_CONTROL: c_ushort = 2;
         const CTLIOCGINFO: c_ulong = 0xc0644e03;
-        const UTUN_CONTROL_NAME: &'static str = "com.apple.net.utun_control";
 
-        let file = {
+        if self.mode == Mode::Tap {
+            #[cfg(feature = "macos-feth")]
+            return self.open_feth(number);
+            #[cfg(not(feature = "macos-feth"))]
+            return Err(Error::new(std::io::ErrorKind::Unsupported, TAP_UNSUPPORTED));
+        }
+
+        let unit = self.utun_unit(number)?;
+        let control_name = self.control_name.as_deref().unwrap_or(UTUN_CONTROL_NAME);
+
+        let (file, unit) = {
             let fd = unsafe { socket(PF_SYSTEM, SOCK_DGRAM, SYSPROTO_CONTROL) };
-            if fd < 0 {
-                return Err(Error::last_os_error());
-            }
+            let fd = trace::check("socket", control_name, fd)?;
+            // Owned right away, so that the socket is closed on every error.
+            let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+            let fd = socket.as_raw_fd();
 
             #[repr(C)]
             pub struct ctl_info {
@@ -189,63 +1475,511 @@ impl OpenOptions {
                 ctl_id: 0,
                 ctl_name: {
                     let mut buffer = [0u8; 96];
-                    buffer[..UTUN_CONTROL_NAME.len()]
-                        .clone_from_slice(UTUN_CONTROL_NAME.as_bytes());
+                    buffer[..control_name.len()].clone_from_slice(control_name.as_bytes());
                     buffer
                 },
             };
 
-            let err = unsafe { ioctl(fd, CTLIOCGINFO, &mut info) };
-            if err != 0 {
-                return Err(Error::last_os_error());
-            }
+            retry_eintr(|| {
+                let err = unsafe { ioctl(fd, CTLIOCGINFO, &mut info) };
+                trace::check("CTLIOCGINFO", control_name, err)
+            })?;
 
             let addr = sockaddr_ctl {
                 sc_len: mem::size_of::<sockaddr_ctl>() as u8,
                 sc_family: AF_SYSTEM,
                 ss_sysaddr: AF_SYS_CONTROL,
                 sc_id: info.ctl_id,
-                sc_unit: number + 1, // Real device number = sc_unit - 1
+                sc_unit: unit,
                 sc_reserved: [0; 5],
             };
 
-            let err = unsafe {
-                let addr_ptr = &addr as *const sockaddr_ctl;
-                connect(
-                    fd,
-                    addr_ptr as *const sockaddr,
-                    mem::size_of_val(&addr) as socklen_t,
-                )
+            let mut attempts = 0;
+            let connected = retry_eintr(|| {
+                attempts += 1;
+                let err = unsafe {
+                    let addr_ptr = &addr as *const sockaddr_ctl;
+                    connect(
+                        fd,
+                        addr_ptr as *const sockaddr,
+                        mem::size_of_val(&addr) as socklen_t,
+                    )
+                };
+                trace::check("connect", control_name, err)
+            });
+            // An interrupted connect may still complete, failing the retry.
+            let connected = match connected {
+                Err(error) if attempts > 1 && error.raw_os_error() == Some(libc::EISCONN) => Ok(0),
+                connected => connected,
             };
-            if err != 0 {
-                return Err(Error::last_os_error());
+            if let Err(error) = connected {
+                // The unit is taken, or no unit is left when the kernel picks.
+                if matches!(error.raw_os_error(), Some(libc::EBUSY | libc::EADDRINUSE)) {
+                    let message = match number {
+                        Some(number) => format!("utun{} is in use", number),
+                        None => "no utun unit is free".to_string(),
+                    };
+                    return Err(Error::new(std::io::ErrorKind::AddrInUse, message));
+                }
+                return Err(error);
             }
 
-            let err = unsafe { fcntl(fd, F_SETFD, FD_CLOEXEC) };
-            if err != 0 {
-                return Err(Error::last_os_error());
-            }
+            let mut peer: sockaddr_ctl = unsafe { mem::zeroed() };
+            let mut length = mem::size_of_val(&peer) as socklen_t;
+            retry_eintr(|| {
+                let err = unsafe {
+                    let peer_ptr = &mut peer as *mut sockaddr_ctl;
+                    getpeername(fd, peer_ptr as *mut sockaddr, &mut length)
+                };
+                trace::check("getpeername", control_name, err)
+            })?;
+
+            retry_eintr(|| {
+                let err = unsafe { fcntl(fd, F_SETFD, FD_CLOEXEC) };
+                trace::check("fcntl", control_name, err)
+            })?;
 
             if self.nonblock {
-                let err = unsafe { fcntl(fd, F_SETFL, O_NONBLOCK) };
-                if err != 0 {
-                    return Err(Error::last_os_error());
-                }
+                retry_eintr(|| {
+                    let err = unsafe { fcntl(fd, F_SETFL, O_NONBLOCK) };
+                    trace::check("fcntl", control_name, err)
+                })?;
             }
 
-            unsafe { File::from_raw_fd(fd) }
+            (File::from(socket), peer.sc_unit)
+        };
+
+        let source = DeviceSource::ControlSocket {
+            control: control_name.to_string(),
+            unit,
         };
+        Ok((file, format!("utun{}", unit - 1), source))
+    }
+
+    /// Returns the `sc_unit` to connect the utun control socket with for the
+    /// device `number`, checking the control name along the way.
+    #[cfg(target_os = "macos")]
+    fn utun_unit(&self, number: Option<u32>) -> Result<u32> {
+        use std::io::{Error, ErrorKind};
+
+        let control_name = self.control_name.as_deref().unwrap_or(UTUN_CONTROL_NAME);
+        // The name is copied into a 96-byte buffer, NUL included.
+        if control_name.len() >= 96 || control_name.bytes().any(|byte| byte == 0) {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid control name"));
+        }
 
-        Ok(file)
+        // 0 lets the kernel pick a unit
+        match (number, self.any_unit) {
+            (Some(_), true) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "a device cannot be opened by both number and any unit",
+            )),
+            (Some(number), false) => unit_for_number(number),
+            (None, _) => Ok(0),
+        }
+    }
+}
+
+#[cfg(all(target_os = "macos", feature = "macos-feth"))]
+impl OpenOptions {
+    /// Emulates a Tap device with a fake ethernet interface, whose frames
+    /// are read and written through a BPF device bound to it.
+    fn open_feth(&mut self, number: Option<u32>) -> Result<(File, String, DeviceSource)> {
+        use interface::macos;
+
+        let name = match number {
+            Some(number) => format!("feth{}", number),
+            None => "feth".to_string(),
+        };
+        let filename = macos::create_feth(&name)?;
+
+        match feth::bind(&filename, self.read, self.write, self.nonblock) {
+            Ok((file, node)) => Ok((file, filename, DeviceSource::Node(node))),
+            Err(error) => {
+                let _ = macos::destroy(&filename);
+                Err(error)
+            }
+        }
     }
 }
 
 impl Default for OpenOptions {
     fn default() -> Self {
-        Self::new()
+        Self::new(Mode::Tun)
+    }
+}
+
+/// The clone devices of Linux, tried in turn: the usual one, then where
+/// some hardened kernels put it.
+#[cfg(target_os = "linux")]
+const TUN_NODES: [&str; 2] = ["/dev/net/tun", "/dev/tun"];
+
+/// The kernel control behind utun devices.
+#[cfg(target_os = "macos")]
+const UTUN_CONTROL_NAME: &str = "com.apple.net.utun_control";
+
+/// Why opening a Tap device fails on macOS without the `macos-feth` feature.
+#[cfg(all(target_os = "macos", not(feature = "macos-feth")))]
+const TAP_UNSUPPORTED: &str =
+    "tap devices are not supported on macOS; use a feth interface or utun";
+
+/// Returns the `sc_unit` of the utun control socket for the device number
+/// `number`: unit 0 asks the kernel to pick one, so `utunN` is unit `N + 1`
+/// and the number of a connected socket is its unit minus 1.
+#[cfg(any(target_os = "macos", test))]
+fn unit_for_number(number: u32) -> Result<u32> {
+    number.checked_add(1).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "the device number is out of range",
+        )
+    })
+}
+
+/// Returns the PPA to ask the tun driver of illumos for: the device number,
+/// or -1 to let the driver pick a free one.
+#[cfg(any(target_os = "illumos", target_os = "solaris"))]
+fn ppa_for_number(number: Option<u32>) -> Result<libc::c_int> {
+    match number {
+        Some(number) => number.try_into().map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "device number is out of range",
+            )
+        }),
+        None => Ok(-1),
+    }
+}
+
+/**
+Returns whether opening a device failed because the device is in use.
+
+This is the case when another process holds the device on Linux, when the
+device exists and [`exclusive`] was requested, or when the utun unit is taken
+on macOS. Unlike permission errors, these are worth retrying later or with
+another device number.
+
+On Linux the error carries the OS error `EBUSY`. On macOS, where `connect`
+reports a taken unit with `EBUSY`, it is turned into an error of kind
+[`AddrInUse`] naming the unit.
+
+[`exclusive`]: tun/struct.OpenOptions.html#method.exclusive
+[`AddrInUse`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.AddrInUse
+
+# Examples
+
+```no_run
+use utuntap::{is_busy, tun::OpenOptions};
+
+let (file, filename) = (0..16)
+    .find_map(|number| match OpenOptions::new().open(number) {
+        Err(error) if is_busy(&error) => None,
+        result => Some(result),
+    })
+    .unwrap()
+    .unwrap();
+```
+*/
+pub fn is_busy(error: &std::io::Error) -> bool {
+    error.raw_os_error() == Some(libc::EBUSY) || error.kind() == std::io::ErrorKind::AddrInUse
+}
+
+/**
+Recovers the mode and number of a device from the name of its interface,
+the reverse of the name [`OpenOptions::open`] returns for a number.
+
+`tun` and the `utun` of macOS map to [`Mode::Tun`], `tap` to [`Mode::Tap`].
+Names which are not one of these prefixes followed by a number in decimal,
+without leading zeros, give `None`, e.g. names picked with
+[`tun::OpenOptions::name`] or the `utun` interfaces of macOS opened
+without a number.
+
+[`OpenOptions::open`]: struct.OpenOptions.html#method.open
+[`Mode::Tun`]: enum.Mode.html#variant.Tun
+[`Mode::Tap`]: enum.Mode.html#variant.Tap
+[`tun::OpenOptions::name`]: tun/struct.OpenOptions.html#method.name
+
+# Examples
+
+```
+use utuntap::{parse_device_name, Mode};
+
+assert_eq!(parse_device_name("tap3"), Some((Mode::Tap, 3)));
+assert_eq!(parse_device_name("utun0"), Some((Mode::Tun, 0)));
+assert_eq!(parse_device_name("uplink"), None);
+```
+*/
+pub fn parse_device_name(name: &str) -> Option<(Mode, u32)> {
+    let digits = name.find(|c: char| c.is_ascii_digit())?;
+    let (prefix, number) = name.split_at(digits);
+    let mode = match prefix {
+        "tun" | "utun" => Mode::Tun,
+        "tap" => Mode::Tap,
+        _ => return None,
+    };
+    if !number.bytes().all(|byte| byte.is_ascii_digit())
+        || (number.starts_with('0') && number.len() > 1)
+    {
+        return None;
+    }
+    Some((mode, number.parse().ok()?))
+}
+
+/**
+Why opening a device by number or name failed on Linux when the interface
+already exists as the other kind of device, e.g. `tun5` asked for as a Tun
+device while it is a persistent Tap interface.
+
+The kernel refuses to attach to it with a bare `EINVAL`; it is turned into
+an error of kind [`AlreadyExists`] carrying a `ModeMismatch`, which
+[`ModeMismatch::from_error`] gets back. The other settings of an existing
+interface, like packet info, are replaced when attaching to it rather than
+refused.
+
+This type is only available on Linux.
+
+[`AlreadyExists`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.AlreadyExists
+[`ModeMismatch::from_error`]: struct.ModeMismatch.html#method.from_error
+
+# Examples
+
+```no_run
+use utuntap::{tun::OpenOptions, ModeMismatch};
+
+match OpenOptions::new().open(5) {
+    Err(error) => match ModeMismatch::from_error(&error) {
+        Some(mismatch) => eprintln!("delete {} first", mismatch.name),
+        None => eprintln!("{}", error),
+    },
+    Ok((_, name)) => println!("{} opened", name),
+}
+```
+*/
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModeMismatch {
+    /// The name of the existing interface.
+    pub name: String,
+    /// The mode the device was opened with.
+    pub requested: Mode,
+    /// The mode of the existing interface.
+    pub existing: Mode,
+}
+
+#[cfg(target_os = "linux")]
+impl ModeMismatch {
+    /// Returns the `ModeMismatch` carried by `error`, if opening failed
+    /// because of one.
+    pub fn from_error(error: &std::io::Error) -> Option<&ModeMismatch> {
+        error.get_ref()?.downcast_ref()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl std::fmt::Display for ModeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} already exists as a {} device, not a {} device",
+            self.name, self.existing, self.requested
+        )
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl std::error::Error for ModeMismatch {}
+
+#[cfg(target_os = "linux")]
+impl From<ModeMismatch> for std::io::Error {
+    fn from(mismatch: ModeMismatch) -> Self {
+        std::io::Error::new(std::io::ErrorKind::AlreadyExists, mismatch)
+    }
+}
+
+/// Tells whether `TUNSETIFF` failed because of the interface already
+/// holding the name: in use, of another kind or not a Tun/Tap interface.
+#[cfg(target_os = "linux")]
+fn name_is_taken(error: &std::io::Error) -> bool {
+    is_busy(error)
+        || error.kind() == std::io::ErrorKind::AlreadyExists
+        || matches!(error.raw_os_error(), Some(libc::EEXIST | libc::EINVAL))
+}
+
+/// The error of a zero [`OpenOptions::buffer_capacity`].
+fn empty_buffer() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "cannot read packets into an empty buffer",
+    )
+}
+
+/// Tells whether opening failed because the device node or driver is not
+/// there yet.
+fn is_transient(error: &std::io::Error) -> bool {
+    error.kind() == std::io::ErrorKind::NotFound || error.raw_os_error() == Some(libc::ENODEV)
+}
+
+/// Runs the system call wrapped by `call` until it fails with anything but
+/// `EINTR`, so that a signal delivered to the thread does not abort it.
+pub(crate) fn retry_eintr<T>(mut call: impl FnMut() -> Result<T>) -> Result<T> {
+    loop {
+        match call() {
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => {}
+            result => return result,
+        }
     }
 }
 
-#[cfg(not(target_os = "macos"))]
+#[cfg(feature = "async-io")]
+mod async_device;
+mod backend;
+mod borrowed;
+mod capabilities;
+mod checksum;
+pub mod config;
+mod device;
+#[cfg(all(target_os = "macos", feature = "macos-feth"))]
+mod feth;
+mod guard;
+pub mod interface;
+#[cfg(target_os = "linux")]
+pub mod ioctl;
+#[cfg(feature = "mock")]
+mod mock;
+pub mod packet_info;
+#[cfg(feature = "smoltcp")]
+pub mod phy;
+mod pool;
 pub mod tap;
+mod trace;
 pub mod tun;
+pub mod vnet;
+
+#[cfg(feature = "async-io")]
+pub use async_device::{AsyncDevice, AsyncReadHalf, AsyncWriteHalf};
+pub use backend::TunDevice;
+pub use borrowed::BorrowedDevice;
+pub use capabilities::{capabilities, Capabilities};
+#[cfg(feature = "checksum")]
+pub use checksum::fix_checksums;
+pub use device::{AddressFamily, Device, DeviceSource};
+pub use guard::DeviceGuard;
+#[cfg(feature = "mock")]
+pub use mock::MockDevice;
+pub use pool::{PooledFrame, PooledReader};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_names_round_trip() {
+        for mode in [Mode::Tun, Mode::Tap] {
+            for number in [0, 7, 42, u32::MAX] {
+                let name = format!("{}{}", mode, number);
+                assert_eq!(parse_device_name(&name), Some((mode, number)));
+            }
+        }
+        assert_eq!(parse_device_name("utun12"), Some((Mode::Tun, 12)));
+        for name in [
+            "tun",
+            "tap-test",
+            "tun01",
+            "tun4294967296",
+            "eth0",
+            "tap1a",
+            "",
+        ] {
+            assert_eq!(parse_device_name(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn utun_unit_is_one_past_the_number() {
+        for number in [0, 1, 9, u32::MAX - 1] {
+            assert_eq!(unit_for_number(number).unwrap(), number + 1);
+        }
+        let error = unit_for_number(u32::MAX).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn mode_maps_to_iff_flags() {
+        for mode in [Mode::Tun, Mode::Tap] {
+            assert_eq!(Mode::from_iff(mode.iff_flag()), Some(mode));
+            // IFF_NO_PI and IFF_VNET_HDR do not change the mode.
+            assert_eq!(Mode::from_iff(mode.iff_flag() | 0x5000), Some(mode));
+        }
+        assert_eq!(Mode::Tun.iff_flag(), 0x0001);
+        assert_eq!(Mode::Tap.iff_flag(), 0x0002);
+        assert_eq!(Mode::from_iff(0), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn validation_matches_opening() {
+        use std::io::ErrorKind;
+
+        let mut options = OpenOptions::new(Mode::Tap);
+        options.validate(0).unwrap();
+        options.validate(u32::MAX).unwrap();
+
+        options.name("a-very-long-interface-name");
+        let error = options.validate(None).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        options.name("tap-test");
+        options.validate(None).unwrap();
+        let error = options.validate(0).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+        options.vnet_hdr(false).offload(tun::Offload::CSUM);
+        let error = options.validate(None).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        options.vnet_hdr(true);
+        options.validate(None).unwrap();
+
+        options.read(false).write(false);
+        let error = options.validate(None).unwrap_err();
+        let opened = options.open(None).unwrap_err();
+        assert_eq!(error.kind(), opened.kind());
+    }
+
+    #[test]
+    fn missing_devices_are_transient() {
+        assert!(is_transient(&std::io::Error::from_raw_os_error(
+            libc::ENOENT
+        )));
+        assert!(is_transient(&std::io::Error::from_raw_os_error(
+            libc::ENODEV
+        )));
+        assert!(!is_transient(&std::io::Error::from_raw_os_error(
+            libc::EPERM
+        )));
+        assert!(!is_transient(&std::io::Error::from_raw_os_error(
+            libc::EBUSY
+        )));
+    }
+
+    #[test]
+    fn interrupted_calls_are_retried() {
+        let mut calls = 0;
+        let result = retry_eintr(|| {
+            calls += 1;
+            match calls {
+                1 | 2 => Err(std::io::Error::from_raw_os_error(libc::EINTR)),
+                _ => Ok(calls),
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let error = retry_eintr(|| -> Result<()> {
+            calls += 1;
+            Err(std::io::Error::from_raw_os_error(libc::EBUSY))
+        })
+        .unwrap_err();
+        assert_eq!(error.raw_os_error(), Some(libc::EBUSY));
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,148 @@
+//! An in-memory stand-in for a Tun/Tap device
+
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+/**
+An in-memory device for testing code which reads and writes packets,
+without root or a kernel interface.
+
+It keeps two queues of packets: the packets reads return, queued with
+[`push_read`], and the packets written, taken with [`pop_written`]. A device
+created with [`loopback`] has one queue instead, so that reads return the
+packets written. Like [`Device`], it reads and writes one packet per call,
+and a packet longer than the buffer of a read is truncated, its rest lost.
+When no packet is queued, reads fail with [`WouldBlock`], as on a device in
+non-blocking mode.
+
+The interface helpers only change the state of the mock: the MTU starts at
+1500 and the interface down.
+
+This type is only available with the `mock` feature.
+
+[`push_read`]: struct.MockDevice.html#method.push_read
+[`pop_written`]: struct.MockDevice.html#method.pop_written
+[`loopback`]: struct.MockDevice.html#method.loopback
+[`Device`]: struct.Device.html
+[`WouldBlock`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.WouldBlock
+
+# Examples
+
+```
+use std::io::{Read, Write};
+use utuntap::MockDevice;
+
+let mut device = MockDevice::new("tun0");
+device.push_read(vec![0x45; 20]);
+let mut buffer = [0; 1500];
+assert_eq!(device.read(&mut buffer).unwrap(), 20);
+
+assert_eq!(device.write(&buffer[..20]).unwrap(), 20);
+assert_eq!(device.pop_written().unwrap().len(), 20);
+```
+*/
+#[derive(Debug, Clone, Default)]
+pub struct MockDevice {
+    name: String,
+    read_queue: VecDeque<Vec<u8>>,
+    written: VecDeque<Vec<u8>>,
+    loopback: bool,
+    mtu: Option<u32>,
+    up: bool,
+}
+
+impl MockDevice {
+    /// Creates a mock of the interface `name` with empty queues.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a mock of the interface `name` whose reads return the packets
+    /// written to it, in order.
+    pub fn loopback(name: &str) -> Self {
+        Self {
+            loopback: true,
+            ..Self::new(name)
+        }
+    }
+
+    /// Returns the name of the interface, e.g. `tun0`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Queues `packet` to be returned by a read, as if the kernel sent it.
+    pub fn push_read(&mut self, packet: Vec<u8>) {
+        self.read_queue.push_back(packet);
+    }
+
+    /// Takes the packet written first of those not taken yet. On a
+    /// [`loopback`] device, it is the packet the next read would return.
+    ///
+    /// [`loopback`]: struct.MockDevice.html#method.loopback
+    pub fn pop_written(&mut self) -> Option<Vec<u8>> {
+        if self.loopback {
+            self.read_queue.pop_front()
+        } else {
+            self.written.pop_front()
+        }
+    }
+
+    /// Returns the MTU of the mock interface, 1500 unless set.
+    pub fn mtu(&self) -> Result<u32> {
+        Ok(self.mtu.unwrap_or(1500))
+    }
+
+    /// Sets the MTU of the mock interface.
+    pub fn set_mtu(&mut self, mtu: u32) -> Result<()> {
+        self.mtu = Some(mtu);
+        Ok(())
+    }
+
+    /// Returns whether the mock interface is up.
+    pub fn is_up(&self) -> bool {
+        self.up
+    }
+
+    /// Brings the mock interface up.
+    pub fn set_up(&mut self) -> Result<()> {
+        self.up = true;
+        Ok(())
+    }
+
+    /// Brings the mock interface down.
+    pub fn set_down(&mut self) -> Result<()> {
+        self.up = false;
+        Ok(())
+    }
+}
+
+impl Read for MockDevice {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let packet = self
+            .read_queue
+            .pop_front()
+            .ok_or_else(|| Error::new(ErrorKind::WouldBlock, "no packet is queued"))?;
+        let number = packet.len().min(buf.len());
+        buf[..number].copy_from_slice(&packet[..number]);
+        Ok(number)
+    }
+}
+
+impl Write for MockDevice {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.loopback {
+            self.read_queue.push_back(buf.to_vec());
+        } else {
+            self.written.push_back(buf.to_vec());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
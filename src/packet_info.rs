@@ -0,0 +1,109 @@
+//! Protocol numbers found in the 4-byte header in front of packets
+//!
+//! On Linux, a device opened with [`packet_info`] prefixes packets with a
+//! `struct tun_pi`: two bytes of flags followed by the EtherType of the
+//! packet in network byte order. On macOS and OpenBSD, Tun packets are
+//! always prefixed with the address family of the packet, a host integer
+//! stored in network byte order.
+//!
+//! [`packet_info`]: ../tun/struct.OpenOptions.html#method.packet_info
+//!
+//! # Examples
+//!
+//! ```
+//! use utuntap::packet_info::{Protocol, ETH_P_IPV6};
+//!
+//! let header = Protocol::Ipv6.to_packet_info();
+//! assert_eq!(header, [0, 0, 0x86, 0xdd]);
+//! assert_eq!(Protocol::from_ethertype(ETH_P_IPV6), Some(Protocol::Ipv6));
+//! assert_eq!(Protocol::from_packet_info(header), Some(Protocol::Ipv6));
+//! ```
+
+use libc::c_int;
+
+/// The EtherType of IPv4.
+pub const ETH_P_IP: u16 = 0x0800;
+/// The EtherType of ARP.
+pub const ETH_P_ARP: u16 = 0x0806;
+/// The EtherType of IPv6.
+pub const ETH_P_IPV6: u16 = 0x86dd;
+
+/// The address family of IPv4 on the current platform.
+pub const AF_INET: c_int = libc::AF_INET;
+/// The address family of IPv6 on the current platform, e.g. 10 on Linux
+/// but 30 on macOS.
+pub const AF_INET6: c_int = libc::AF_INET6;
+
+/// A protocol carried by a Tun/Tap device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    /// IPv4
+    Ipv4,
+    /// IPv6
+    Ipv6,
+    /// ARP, only seen on Tap devices
+    Arp,
+}
+
+impl Protocol {
+    /// Returns the protocol of an EtherType.
+    pub fn from_ethertype(ethertype: u16) -> Option<Self> {
+        match ethertype {
+            ETH_P_IP => Some(Protocol::Ipv4),
+            ETH_P_IPV6 => Some(Protocol::Ipv6),
+            ETH_P_ARP => Some(Protocol::Arp),
+            _ => None,
+        }
+    }
+
+    /// Returns the EtherType of the protocol.
+    pub fn ethertype(self) -> u16 {
+        match self {
+            Protocol::Ipv4 => ETH_P_IP,
+            Protocol::Ipv6 => ETH_P_IPV6,
+            Protocol::Arp => ETH_P_ARP,
+        }
+    }
+
+    /// Returns the protocol of an address family of the current platform.
+    pub fn from_address_family(family: c_int) -> Option<Self> {
+        match family {
+            AF_INET => Some(Protocol::Ipv4),
+            AF_INET6 => Some(Protocol::Ipv6),
+            _ => None,
+        }
+    }
+
+    /// Returns the address family of the protocol on the current platform,
+    /// or `None` for ARP which has none.
+    pub fn address_family(self) -> Option<c_int> {
+        match self {
+            Protocol::Ipv4 => Some(AF_INET),
+            Protocol::Ipv6 => Some(AF_INET6),
+            Protocol::Arp => None,
+        }
+    }
+
+    /// Parses a Linux `struct tun_pi`, ignoring its flags.
+    pub fn from_packet_info(header: [u8; 4]) -> Option<Self> {
+        Self::from_ethertype(u16::from_be_bytes([header[2], header[3]]))
+    }
+
+    /// Builds a Linux `struct tun_pi` with no flags set.
+    pub fn to_packet_info(self) -> [u8; 4] {
+        let [high, low] = self.ethertype().to_be_bytes();
+        [0, 0, high, low]
+    }
+
+    /// Parses the address family prefix of macOS and OpenBSD.
+    pub fn from_address_family_prefix(prefix: [u8; 4]) -> Option<Self> {
+        Self::from_address_family(u32::from_be_bytes(prefix) as c_int)
+    }
+
+    /// Builds the address family prefix of macOS and OpenBSD, or `None` for
+    /// ARP.
+    pub fn to_address_family_prefix(self) -> Option<[u8; 4]> {
+        self.address_family()
+            .map(|family| (family as u32).to_be_bytes())
+    }
+}
@@ -0,0 +1,168 @@
+//! Integration with the [smoltcp] userspace TCP/IP stack
+//!
+//! [`Phy`] implements [`smoltcp::phy::Device`][device] over an opened
+//! [`Device`], so it can be handed straight to a smoltcp `Interface`. Tun
+//! devices carry IP packets and Tap devices Ethernet frames; the 4-byte
+//! packet information or address family header is stripped and added as
+//! the platform and options require.
+//!
+//! This module is only available with the `smoltcp` feature.
+//!
+//! [smoltcp]: https://docs.rs/smoltcp
+//! [device]: https://docs.rs/smoltcp/latest/smoltcp/phy/trait.Device.html
+//! [`Phy`]: struct.Phy.html
+//! [`Device`]: ../struct.Device.html
+
+use crate::{interface, AddressFamily, Device, Mode};
+use smoltcp::phy::{self, DeviceCapabilities, Medium};
+use smoltcp::time::Instant;
+use std::io::Result;
+
+/// Length of an Ethernet header, which the MTU of an interface excludes.
+const ETHERNET_HEADER_LEN: usize = 14;
+
+/**
+A smoltcp physical device backed by a Tun/Tap [`Device`].
+
+The device should be opened in non-blocking mode: [`receive`] reads a
+packet on every call and reports nothing when none is waiting, leaving the
+caller to wait for readiness on the descriptor, e.g. with
+`smoltcp::phy::wait`. Packets which cannot be read or written are dropped,
+as the smoltcp interface has no way to report errors.
+
+[`Device`]: ../struct.Device.html
+[`receive`]: https://docs.rs/smoltcp/latest/smoltcp/phy/trait.Device.html#tymethod.receive
+
+# Examples
+
+```no_run
+use smoltcp::iface::{Config, Interface};
+use smoltcp::time::Instant;
+use smoltcp::wire::HardwareAddress;
+use utuntap::{phy::Phy, tun::OpenOptions, Mode};
+
+let device = OpenOptions::new().nonblock(true).open_device(0).unwrap();
+let mut phy = Phy::new(device, Mode::Tun).unwrap();
+let config = Config::new(HardwareAddress::Ip);
+let iface = Interface::new(config, &mut phy, Instant::now());
+```
+*/
+#[derive(Debug)]
+pub struct Phy {
+    device: Device,
+    mode: Mode,
+    mtu: usize,
+}
+
+impl Phy {
+    /**
+    Wraps `device`, a device of the given `mode`, reading the MTU of its
+    interface to report in the capabilities.
+    */
+    pub fn new(device: Device, mode: Mode) -> Result<Self> {
+        let mtu = interface::get_mtu(device.name())? as usize;
+        Ok(Self { device, mode, mtu })
+    }
+
+    /// Returns the underlying device.
+    pub fn get_ref(&self) -> &Device {
+        &self.device
+    }
+
+    /// Returns the underlying device.
+    pub fn into_inner(self) -> Device {
+        self.device
+    }
+
+    /// Returns the largest packet the stack may hand over, including the
+    /// Ethernet header of Tap devices.
+    fn max_packet_len(&self) -> usize {
+        match self.mode {
+            Mode::Tun => self.mtu,
+            Mode::Tap => self.mtu + ETHERNET_HEADER_LEN,
+        }
+    }
+
+    fn read(&mut self) -> Result<Vec<u8>> {
+        let cap = self.max_packet_len();
+        match self.mode {
+            Mode::Tun => Ok(self.device.read_packet(cap)?.1),
+            Mode::Tap => self.device.read_ethernet(cap),
+        }
+    }
+}
+
+/// Writes `packet` to `device` with the header the device expects.
+fn write(device: &mut Device, mode: Mode, packet: &[u8]) -> Result<usize> {
+    match mode {
+        Mode::Tun => device.write_packet(AddressFamily::of_packet(packet)?, packet),
+        Mode::Tap => device.write_ethernet(packet),
+    }
+}
+
+impl phy::Device for Phy {
+    type RxToken<'a> = RxToken;
+    type TxToken<'a> = TxToken<'a>;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let buffer = self.read().ok()?;
+        Some((
+            RxToken { buffer },
+            TxToken {
+                device: &mut self.device,
+                mode: self.mode,
+            },
+        ))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        Some(TxToken {
+            device: &mut self.device,
+            mode: self.mode,
+        })
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut capabilities = DeviceCapabilities::default();
+        capabilities.medium = match self.mode {
+            Mode::Tun => Medium::Ip,
+            Mode::Tap => Medium::Ethernet,
+        };
+        capabilities.max_transmission_unit = self.max_packet_len();
+        capabilities
+    }
+}
+
+/// A packet read from a [`Phy`](struct.Phy.html), without its header.
+#[derive(Debug)]
+pub struct RxToken {
+    buffer: Vec<u8>,
+}
+
+impl phy::RxToken for RxToken {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        f(&self.buffer)
+    }
+}
+
+/// A permit to write one packet to a [`Phy`](struct.Phy.html).
+#[derive(Debug)]
+pub struct TxToken<'a> {
+    device: &'a mut Device,
+    mode: Mode,
+}
+
+impl<'a> phy::TxToken for TxToken<'a> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut buffer = vec![0; len];
+        let result = f(&mut buffer);
+        let _ = write(self.device, self.mode, &buffer);
+        result
+    }
+}
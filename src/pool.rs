@@ -0,0 +1,188 @@
+//! A reader recycling its packet buffers
+
+use crate::Device;
+use std::io::{Read, Result};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+/// Number of free buffers a reader keeps by default.
+const DEFAULT_CAPACITY: usize = 16;
+
+/// The free buffers of a reader, shared with the frames it handed out.
+#[derive(Debug)]
+struct Pool {
+    free: Mutex<Vec<Vec<u8>>>,
+    capacity: usize,
+}
+
+/**
+A blocking reader handing out each packet in a buffer taken from a pool,
+which the buffer returns to once the packet is dropped.
+
+A read loop which keeps owned packets, e.g. to send them to another thread,
+otherwise allocates a buffer per packet. The reader keeps up to a fixed
+number of free buffers, 16 by default, each big enough for any packet of
+the device, and only allocates when all of them are in use. Packets are
+read one per call as with [`Device`], so their boundaries are preserved.
+
+[`Device`]: struct.Device.html
+
+# Examples
+
+```no_run
+use utuntap::{tun::OpenOptions, PooledReader};
+
+let device = OpenOptions::new().open_device(0).unwrap();
+let mut reader = PooledReader::new(device).unwrap();
+loop {
+    let frame = reader.read().unwrap();
+    println!("read {} bytes", frame.len());
+    // The buffer goes back to the pool here.
+}
+```
+*/
+#[derive(Debug)]
+pub struct PooledReader {
+    device: Device,
+    buffer_size: usize,
+    pool: Arc<Pool>,
+}
+
+impl PooledReader {
+    /**
+    Wraps `device`, sizing the buffers with
+    [`Device::recommended_buffer_size`] from the MTU of its interface.
+
+    A larger MTU set later is not taken into account: packets beyond the
+    buffer size are truncated.
+
+    This function is only available on Linux and macOS.
+
+    [`Device::recommended_buffer_size`]: struct.Device.html#method.recommended_buffer_size
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn new(device: Device) -> Result<Self> {
+        let buffer_size = device.recommended_buffer_size()?;
+        Ok(Self::with_buffer_size(
+            device,
+            buffer_size,
+            DEFAULT_CAPACITY,
+        ))
+    }
+
+    /// Wraps `device`, reading into buffers of `buffer_size` bytes and
+    /// keeping up to `capacity` of them once freed.
+    pub fn with_buffer_size(device: Device, buffer_size: usize, capacity: usize) -> Self {
+        Self {
+            device,
+            buffer_size,
+            pool: Arc::new(Pool {
+                free: Mutex::new(Vec::with_capacity(capacity)),
+                capacity,
+            }),
+        }
+    }
+
+    /**
+    Reads one packet into a buffer from the pool, or a new one when the pool
+    is empty.
+
+    The buffer goes back to the pool when the returned frame is dropped,
+    also when the read fails.
+    */
+    pub fn read(&mut self) -> Result<PooledFrame> {
+        let buffer = self.pool.free.lock().ok().and_then(|mut free| free.pop());
+        let mut frame = PooledFrame {
+            buffer: buffer.unwrap_or_else(|| vec![0; self.buffer_size]),
+            len: 0,
+            pool: Some(self.pool.clone()),
+        };
+        frame.len = self.device.read(&mut frame.buffer)?;
+        Ok(frame)
+    }
+
+    /// Returns the size of the buffers packets are read into.
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    /// Returns the number of free buffers in the pool.
+    pub fn free_buffers(&self) -> usize {
+        self.pool.free.lock().map_or(0, |free| free.len())
+    }
+
+    /// Returns the underlying device.
+    pub fn get_ref(&self) -> &Device {
+        &self.device
+    }
+
+    /// Returns the underlying device, e.g. to write packets to it.
+    pub fn get_mut(&mut self) -> &mut Device {
+        &mut self.device
+    }
+
+    /// Returns the underlying device, dropping the free buffers.
+    pub fn into_inner(self) -> Device {
+        self.device
+    }
+}
+
+/**
+A packet read by a [`PooledReader`], dereferencing to its bytes.
+
+Its buffer returns to the pool of the reader when it is dropped, unless the
+pool is full. [`into_vec`] keeps the buffer instead.
+
+[`PooledReader`]: struct.PooledReader.html
+[`into_vec`]: struct.PooledFrame.html#method.into_vec
+*/
+#[derive(Debug)]
+pub struct PooledFrame {
+    buffer: Vec<u8>,
+    len: usize,
+    pool: Option<Arc<Pool>>,
+}
+
+impl PooledFrame {
+    /// Takes the buffer out of the pool, returning it truncated to the
+    /// packet.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.pool = None;
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.truncate(self.len);
+        buffer
+    }
+}
+
+impl Deref for PooledFrame {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+}
+
+impl DerefMut for PooledFrame {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer[..self.len]
+    }
+}
+
+impl AsRef<[u8]> for PooledFrame {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Drop for PooledFrame {
+    fn drop(&mut self) {
+        let Some(pool) = &self.pool else {
+            return;
+        };
+        if let Ok(mut free) = pool.free.lock() {
+            if free.len() < pool.capacity {
+                free.push(std::mem::take(&mut self.buffer));
+            }
+        }
+    }
+}
@@ -1,6 +1,37 @@
 //! APIs for level 2 Tap devices
-
-use super::Mode;
+//!
+//! macOS has no Tap devices: the module is available there so that
+//! cross-platform code compiles, but opening a device returns an
+//! [`Unsupported`] error.
+//!
+//! With the experimental `macos-feth` feature, opening a Tap device on macOS
+//! creates a fake ethernet interface `fethN` instead, and a BPF device bound
+//! to it, the first free one of `/dev/bpf0`, `/dev/bpf1` and so on. Frames
+//! written to the device are sent out of `fethN`, and reads return the
+//! frames it receives, so traffic only flows once the caller creates a peer
+//! interface and pairs the two, e.g. `ifconfig feth1 create` and `ifconfig
+//! feth1 peer feth0`. The peer then plays the part of the host side of the
+//! Tap device: addresses go on it, not on `fethN`.
+//!
+//! A [`Device`] reads one bare Ethernet frame per call, like a Tap device
+//! elsewhere, and destroys `fethN` when dropped. The file returned by `open`
+//! or [`Device::into_parts`] is the BPF device itself: each read of it
+//! returns BPF records, i.e. frames prefixed with a `struct bpf_hdr`, into a
+//! buffer of exactly the size `BIOCGBLEN` reports, and the interface is left
+//! for the caller to destroy with `ifconfig fethN destroy`.
+//!
+//! A Tap device always carries Ethernet frames, even with its link type set
+//! to `ARPHRD_NONE` by [`tun::set_link_type`]; devices carrying bare IP
+//! packets are Tun devices.
+//!
+//! [`Device`]: ../struct.Device.html
+//! [`Device::into_parts`]: ../struct.Device.html#method.into_parts
+//! [`tun::set_link_type`]: ../tun/fn.set_link_type.html
+//! [`Unsupported`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.Unsupported
+
+#[cfg(feature = "async-io")]
+pub use super::{AsyncDevice, AsyncReadHalf, AsyncWriteHalf};
+use super::{Device, Mode};
 use std::fs::File;
 use std::io::Result;
 
@@ -31,7 +62,7 @@ Opening device `tap0`:
 ```no_run
 use utuntap::tap::OpenOptions;
 
-let file = OpenOptions::new().open(0).unwrap();
+let (file, filename) = OpenOptions::new().open(0).unwrap();
 ```
 
 Opening device `tap0` with non-blocking I/O set:
@@ -39,7 +70,7 @@ Opening device `tap0` with non-blocking I/O set:
 ```no_run
 use utuntap::tap::OpenOptions;
 
-let file = OpenOptions::new()
+let (file, filename) = OpenOptions::new()
             .nonblock(true)
             .open(0)
             .unwrap();
@@ -61,12 +92,11 @@ impl OpenOptions {
     use utuntap::tap::OpenOptions;
 
     let mut options = OpenOptions::new();
-    let file = options.open(0).unwrap();
+    let (file, filename) = options.open(0).unwrap();
     ```
     */
     pub fn new() -> Self {
-        let mut options = super::OpenOptions::new();
-        options.mode(Mode::Tap);
+        let options = super::OpenOptions::new(Mode::Tap);
         Self { options }
     }
 
@@ -84,7 +114,7 @@ impl OpenOptions {
     use utuntap::tap::OpenOptions;
 
     let mut options = OpenOptions::new();
-    let file = options.read(true).write(true).open(0).unwrap();
+    let (file, filename) = options.read(true).write(true).open(0).unwrap();
     ```
     */
     pub fn read(&mut self, value: bool) -> &mut Self {
@@ -98,7 +128,10 @@ impl OpenOptions {
     This option, when true, will indicate that the file should be
     `write`-able if opened.
 
-    This opiton defaults to `true`.
+    This opiton defaults to `true`. Except for utun sockets on macOS, which
+    are always readable and writable, a device opened with `false` is opened
+    `O_RDONLY` and writes to it fail with `EBADF`. A read-only Tap device
+    suits passive monitoring, as nothing can be injected through it.
 
     # Examples
 
@@ -106,7 +139,7 @@ impl OpenOptions {
     use utuntap::tap::OpenOptions;
 
     let mut options = OpenOptions::new();
-    let file = options.read(true).write(true).open(0).unwrap();
+    let (file, filename) = options.read(true).write(true).open(0).unwrap();
     ```
     */
     pub fn write(&mut self, value: bool) -> &mut Self {
@@ -126,7 +159,7 @@ impl OpenOptions {
     use utuntap::tap::OpenOptions;
 
     let mut options = OpenOptions::new();
-    let file = options.nonblock(true).open(0).unwrap();
+    let (file, filename) = options.nonblock(true).open(0).unwrap();
     ```
     */
     #[cfg(target_family = "unix")]
@@ -135,6 +168,102 @@ impl OpenOptions {
         self
     }
 
+    /**
+    Sets how reads of the device wait for packets: forever, not at all, or
+    for at most a given duration.
+
+    This option defaults to [`BlockingMode::Blocking`]; [`nonblock`] is a
+    shorthand for the first two. See [`BlockingMode`] for how a timeout
+    applies.
+
+    # Examples
+
+    ```no_run
+    use std::time::Duration;
+    use utuntap::{tap::OpenOptions, BlockingMode};
+
+    let device = OpenOptions::new()
+        .blocking_mode(BlockingMode::Timeout(Duration::from_secs(1)))
+        .open_device(0)
+        .unwrap();
+    ```
+
+    [`BlockingMode::Blocking`]: ../enum.BlockingMode.html#variant.Blocking
+    [`BlockingMode`]: ../enum.BlockingMode.html
+    [`nonblock`]: struct.OpenOptions.html#method.nonblock
+    */
+    #[cfg(target_family = "unix")]
+    pub fn blocking_mode(&mut self, mode: crate::BlockingMode) -> &mut Self {
+        self.options.blocking_mode(mode);
+        self
+    }
+
+    /**
+    Sets the size of the buffer [`Device::read_frame_borrowed`] reads into,
+    allocating it when the device is opened rather than on the first read.
+
+    By default, the buffer is sized from the MTU by the first read. A
+    capacity of 0 makes opening fail with [`InvalidInput`].
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let mut device = OpenOptions::new()
+        .buffer_capacity(9000)
+        .open_device(0)
+        .unwrap();
+    let packet = device.read_frame_borrowed().unwrap();
+    ```
+
+    [`Device::read_frame_borrowed`]: ../struct.Device.html#method.read_frame_borrowed
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    pub fn buffer_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.options.buffer_capacity(capacity);
+        self
+    }
+
+    /**
+    Sets the option for signal-driven I/O: once the device is opened, `pid`,
+    or the current process if `None`, receives `SIGIO` whenever a packet is
+    waiting. `O_NONBLOCK` is left as set by [`nonblock`], and
+    [`clear_signal_driven`] turns the option off again.
+
+    The default action of `SIGIO` terminates the process, so a handler has
+    to be installed before opening. See [`Device::set_signal_driven`].
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    // A `SIGIO` handler must be installed before.
+    let device = OpenOptions::new().signal_driven(None).open_device(0).unwrap();
+    ```
+
+    [`nonblock`]: struct.OpenOptions.html#method.nonblock
+    [`clear_signal_driven`]: struct.OpenOptions.html#method.clear_signal_driven
+    [`Device::set_signal_driven`]: ../struct.Device.html#method.set_signal_driven
+    */
+    #[cfg(target_family = "unix")]
+    pub fn signal_driven(&mut self, pid: Option<libc::pid_t>) -> &mut Self {
+        self.options.signal_driven(pid);
+        self
+    }
+
+    /**
+    Turns the option for signal-driven I/O set by [`signal_driven`] off.
+
+    [`signal_driven`]: struct.OpenOptions.html#method.signal_driven
+    */
+    #[cfg(target_family = "unix")]
+    pub fn clear_signal_driven(&mut self) -> &mut Self {
+        self.options.clear_signal_driven();
+        self
+    }
+
     /**
     Sets the option for packet info.
 
@@ -149,7 +278,7 @@ impl OpenOptions {
     use utuntap::tap::OpenOptions;
 
     let mut options = OpenOptions::new();
-    let file = options.packet_info(true).open(0).unwrap();
+    let (file, filename) = options.packet_info(true).open(0).unwrap();
     ```
     */
     #[cfg(target_os = "linux")]
@@ -159,11 +288,533 @@ impl OpenOptions {
     }
 
     /**
-    Opens a tap device file with the options specified by `self`.
+    Sets the option for no packet info, the inverse of [`packet_info`].
+
+    This option, when true, will make the device be opened with
+    `IFF_NO_PI`, so that each packet read or written is one of the raw
+    Ethernet frames. This option defaults to `true`.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    let (file, filename) = options.no_packet_info(true).open(0).unwrap();
+    ```
+
+    [`packet_info`]: struct.OpenOptions.html#method.packet_info
+    */
+    #[cfg(target_os = "linux")]
+    pub fn no_packet_info(&mut self, value: bool) -> &mut Self {
+        self.options.no_packet_info(value);
+        self
+    }
+
+    /**
+    Sets the exact name of the interface to open, instead of deriving it from
+    the device number passed to [`open`], which must then be `None`.
+
+    When a persistent interface of that name exists, the device reattaches
+    to it and keeps its name, rather than creating a new interface.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    let (file, filename) = options.name("vpn0").open(None).unwrap();
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    #[cfg(target_os = "linux")]
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.options.name(name);
+        self
+    }
+
+    /**
+    Sets the name to open the interface with if it can be had, letting the
+    kernel pick one otherwise, e.g. `tap3`, which [`open`] returns.
+
+    Unlike with [`name`], opening does not fail when the interface `name`
+    is in use or exists as another kind of interface. [`open`] must be
+    called without a number.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let (file, filename) = OpenOptions::new().preferred_name("vpn0").open(None).unwrap();
+    println!("opened {}", filename);
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`name`]: struct.OpenOptions.html#method.name
+    */
+    #[cfg(target_os = "linux")]
+    pub fn preferred_name(&mut self, name: &str) -> &mut Self {
+        self.options.preferred_name(name);
+        self
+    }
+
+    /**
+    Sets the option for the virtio net header.
+
+    This option, when true, will indicate that each packet read or
+    written is prefixed with a `struct virtio_net_hdr`, which carries the
+    checksum and segmentation offload metadata.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    let (file, filename) = options.vnet_hdr(true).open(0).unwrap();
+    ```
+    */
+    #[cfg(target_os = "linux")]
+    pub fn vnet_hdr(&mut self, value: bool) -> &mut Self {
+        self.options.vnet_hdr(value);
+        self
+    }
+
+    /**
+    Sets the offloads to negotiate with the kernel once the device is open.
+
+    The requested offloads are masked against those the kernel accepts, and
+    the intersection is applied with `TUNSETOFFLOAD`. [`open_device`] returns
+    a [`Device`] telling which offloads were actually enabled.
+
+    Offloads are described in the virtio net header, so this option implies
+    [`vnet_hdr`]; opening fails with [`InvalidInput`] if it was explicitly
+    disabled.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+    use utuntap::tun::Offload;
+
+    let mut options = OpenOptions::new();
+    let device = options
+        .offload(Offload::CSUM | Offload::TSO4 | Offload::TSO6)
+        .open_device(0)
+        .unwrap();
+    println!("enabled offloads: {:?}", device.offload());
+    ```
+
+    [`open_device`]: struct.OpenOptions.html#method.open_device
+    [`Device`]: ../struct.Device.html
+    [`vnet_hdr`]: struct.OpenOptions.html#method.vnet_hdr
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    #[cfg(target_os = "linux")]
+    pub fn offload(&mut self, offload: crate::tun::Offload) -> &mut Self {
+        self.options.offload(offload);
+        self
+    }
+
+    /**
+    Sets the option to fail when the named device already exists.
+
+    This option, when true, will make opening fail with the OS error `EBUSY`
+    instead of attaching to an existing interface, e.g. a persistent one left
+    over by a previous run. This only makes sense together with a device
+    number or a [`name`].
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    match options.exclusive(true).open(0) {
+        Ok((file, filename)) => println!("created {}", filename),
+        Err(error) if error.raw_os_error() == Some(libc::EBUSY) => {
+            println!("tap0 already exists")
+        }
+        Err(error) => panic!("{}", error),
+    }
+    ```
+
+    [`name`]: struct.OpenOptions.html#method.name
+    */
+    #[cfg(target_os = "linux")]
+    pub fn exclusive(&mut self, value: bool) -> &mut Self {
+        self.options.exclusive(value);
+        self
+    }
+
+    /**
+    Sets the clone device to open, e.g. when the kernel puts it somewhere
+    else than `/dev/net/tun`.
+
+    Without this option, `/dev/net/tun` is opened, or `/dev/tun` where the
+    former does not exist, as on some hardened kernels; the error of
+    `/dev/net/tun` is returned if neither can be opened.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let device = OpenOptions::new()
+        .device_path("/run/tun")
+        .open_device(0)
+        .unwrap();
+    ```
+    */
+    #[cfg(target_os = "linux")]
+    pub fn device_path(&mut self, path: impl AsRef<std::path::Path>) -> &mut Self {
+        self.options.device_path(path);
+        self
+    }
+
+    /**
+    Lets the kernel assign a free name to the device, e.g. `tap3`, which
+    [`open`] returns.
+
+    This is handy for short-lived devices, e.g. in tests, as no counter has
+    to be managed. It replaces any [`name`] set before, and [`open`] must be
+    called without a number.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let (file, filename) = OpenOptions::new().ephemeral().open(None).unwrap();
+    println!("opened {}", filename);
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`name`]: struct.OpenOptions.html#method.name
+    */
+    #[cfg(target_os = "linux")]
+    pub fn ephemeral(&mut self) -> &mut Self {
+        self.options.ephemeral();
+        self
+    }
+
+    /**
+    Sets the option for NAPI processing of written packets.
+
+    This option, when true, will make the kernel hand packets written to the
+    device to the network stack through NAPI, batching them like a real
+    driver does. It is meant for performance experiments and tests of the
+    receive path; it needs `CAP_NET_ADMIN` and Linux 4.15 or later, and
+    older kernels fail the open with `EINVAL`.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    let (file, filename) = options.napi(true).open(0).unwrap();
+    ```
+    */
+    #[cfg(target_os = "linux")]
+    pub fn napi(&mut self, value: bool) -> &mut Self {
+        self.options.napi(value);
+        self
+    }
+
+    /**
+    Sets the option for NAPI processing of written fragments.
+
+    This option, when true, will let each write carry a packet split into
+    fragments with `writev`, which the kernel assembles into a single
+    buffer. It only works on Tap devices together with [`napi`], and is
+    typically combined with [`vnet_hdr`]; kernels without support fail the
+    open with `EINVAL`.
+
+    This option is only available on Linux.
+
+    [`napi`]: struct.OpenOptions.html#method.napi
+    [`vnet_hdr`]: struct.OpenOptions.html#method.vnet_hdr
+    */
+    #[cfg(target_os = "linux")]
+    pub fn napi_frags(&mut self, value: bool) -> &mut Self {
+        self.options.napi_frags(value);
+        self
+    }
+
+    /**
+    Sets the option to strip the protocol information of the platform.
+
+    This option, when true, will make the `Read` implementation of a
+    [`Device`] opened with [`open_device`] drop the 4-byte prefix of every
+    packet, i.e. the address family on macOS or the packet info on Linux,
+    and its `Write` implementation add the appropriate one back, so only raw
+    packets go through. The family of read packets is lost then; use
+    [`Device::read_packet`] to learn it. Files returned by [`open`] are
+    unaffected.
+
+    This option defaults to `false`.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    let device = options.strip_protocol_info(true).open_device(0).unwrap();
+    ```
+
+    [`Device`]: ../struct.Device.html
+    [`Device::read_packet`]: ../struct.Device.html#method.read_packet
+    [`open_device`]: struct.OpenOptions.html#method.open_device
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    pub fn strip_protocol_info(&mut self, value: bool) -> &mut Self {
+        self.options.strip_protocol_info(value);
+        self
+    }
+
+    /**
+    Like [`open`], but writes the name of the device into `name_buf`
+    instead of allocating a `String`, returning the length of the name.
+
+    See [`utuntap::OpenOptions::open_into`] for details.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let mut name = [0; 16];
+    let (file, len) = OpenOptions::new().open_into(0, &mut name).unwrap();
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`utuntap::OpenOptions::open_into`]: ../struct.OpenOptions.html#method.open_into
+    */
+    pub fn open_into(
+        &mut self,
+        number: impl Into<Option<u32>>,
+        name_buf: &mut [u8],
+    ) -> Result<(File, usize)> {
+        self.options.open_into(number, name_buf)
+    }
+
+    /**
+    Opens a multi-queue tap device with `queues` files attached to it,
+    returning each file along with the index of its queue, and the name of
+    the device.
+
+    See [`utuntap::OpenOptions::open_multiqueue`] for details.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let (queues, name) = OpenOptions::new().open_multiqueue(0, 4).unwrap();
+    ```
+
+    [`utuntap::OpenOptions::open_multiqueue`]: ../struct.OpenOptions.html#method.open_multiqueue
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_multiqueue(
+        &mut self,
+        number: impl Into<Option<u32>>,
+        queues: usize,
+    ) -> Result<(Vec<(File, usize)>, String)> {
+        self.options.open_multiqueue(number, queues)
+    }
+
+    /**
+    Opens a tap device twice, returning a read-only file, a write-only file
+    and the name of the device.
+
+    See [`utuntap::OpenOptions::open_rw_split`] for details.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let (reader, writer, name) = OpenOptions::new().open_rw_split(0).unwrap();
+    ```
+
+    [`utuntap::OpenOptions::open_rw_split`]: ../struct.OpenOptions.html#method.open_rw_split
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_rw_split(
+        &mut self,
+        number: impl Into<Option<u32>>,
+    ) -> Result<(File, File, String)> {
+        self.options.open_rw_split(number)
+    }
+
+    /**
+    Opens the tap device named `name`, creating its interface unless it
+    exists, and tells whether it was created.
+
+    See [`utuntap::OpenOptions::open_or_attach`] for details.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let (device, created) = OpenOptions::new().open_or_attach("vm0").unwrap();
+    ```
+
+    [`utuntap::OpenOptions::open_or_attach`]: ../struct.OpenOptions.html#method.open_or_attach
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_or_attach(&mut self, name: &str) -> Result<(Device, bool)> {
+        self.options.open_or_attach(name)
+    }
+
+    /**
+    Like [`open`], but creates the interface inside the network namespace
+    `netns`, returning the calling thread to its own namespace afterwards.
+
+    See [`utuntap::OpenOptions::open_in_netns`] for details.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use std::path::Path;
+    use utuntap::tap::OpenOptions;
+
+    let netns = Path::new("/var/run/netns/blue");
+    let (file, filename) = OpenOptions::new().open_in_netns(netns, 0).unwrap();
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`utuntap::OpenOptions::open_in_netns`]: ../struct.OpenOptions.html#method.open_in_netns
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_in_netns(
+        &mut self,
+        netns: &std::path::Path,
+        number: impl Into<Option<u32>>,
+    ) -> Result<(File, String)> {
+        self.options.open_in_netns(netns, number)
+    }
+
+    /**
+    Like [`open`], but retries `attempts` times in total, sleeping `delay`
+    in between, while the device node or driver is missing.
+
+    See [`utuntap::OpenOptions::open_with_retry`] for details.
+
+    # Examples
+
+    ```no_run
+    use std::time::Duration;
+    use utuntap::tap::OpenOptions;
+
+    let (file, filename) = OpenOptions::new()
+        .open_with_retry(0, 10, Duration::from_millis(100))
+        .unwrap();
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`utuntap::OpenOptions::open_with_retry`]: ../struct.OpenOptions.html#method.open_with_retry
+    */
+    pub fn open_with_retry(
+        &mut self,
+        number: impl Into<Option<u32>>,
+        attempts: u32,
+        delay: std::time::Duration,
+    ) -> Result<(File, String)> {
+        self.options.open_with_retry(number, attempts, delay)
+    }
+
+    /**
+    Checks that the device `number` can be opened with the options specified
+    by `self`, without opening it, e.g. for a `--check` mode of a tool.
+
+    See [`utuntap::OpenOptions::validate`] for details.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    OpenOptions::new().validate(0).expect("invalid configuration");
+    ```
+
+    [`utuntap::OpenOptions::validate`]: ../struct.OpenOptions.html#method.validate
+    */
+    pub fn validate(&self, number: impl Into<Option<u32>>) -> Result<()> {
+        self.options.validate(number)
+    }
+
+    /**
+    Sets the MTU of the interface as part of opening it.
+
+    This option, when set, will make opening issue `SIOCSIFMTU` once the
+    device is created, the same as [`interface::set_mtu`]. If the MTU cannot
+    be set, e.g. because it is out of range for the interface, opening fails
+    and the device file is closed, so a device is never returned with
+    another MTU. Setting the MTU needs `CAP_NET_ADMIN` on Linux and root on
+    macOS, even for a persistent device owned by the user.
+
+    This option is only available on Linux and macOS.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    let (file, filename) = options.mtu(1280).open(0).unwrap();
+    ```
+
+    [`interface::set_mtu`]: ../interface/fn.set_mtu.html
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn mtu(&mut self, value: u32) -> &mut Self {
+        self.options.mtu(value);
+        self
+    }
+
+    /**
+    Opens a tap device file with the options specified by `self`, returning
+    the file and the name of the device.
 
     # Arguments
 
-    * `number` - the number of the device, e.g. the "0" of "tun0".
+    * `number` - the number of the device, e.g. the "0" of "tap0". On Linux,
+      OpenBSD and illumos, when it is not set, the OS will assign a name for
+      you.
 
     # Errors
 
@@ -175,6 +826,12 @@ impl OpenOptions {
     * [`NotFound`]: The device file does not exist.
     * [`PermissionDenied`]: The user lacks permission to get the specified
       access rights for the file.
+    * [`InvalidInput`]: The number is out of range for the platform: the
+      device name must be shorter than `IFNAMSIZ` (16 bytes), and utun
+      numbers on macOS go up to `u32::MAX - 1`.
+    * [`AlreadyExists`]: The interface exists as the other kind of device on
+      Linux, with a [`ModeMismatch`] telling which.
+    * [`Unsupported`]: Tap devices are not supported on macOS.
 
     # Examples
 
@@ -182,14 +839,136 @@ impl OpenOptions {
     use utuntap::tap::OpenOptions;
 
     let mut options = OpenOptions::new();
-    let file = options.open(0).unwrap();
+    let (file, filename) = options.open(0).unwrap();
+    ```
+
+    Letting the OS assign a name on Linux:
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let (file, filename) = OpenOptions::new().open(None).unwrap();
     ```
 
     [`ErrorKind`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html
     [`NotFound`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.NotFound
     [`PermissionDenied`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.PermissionDenied
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    [`AlreadyExists`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.AlreadyExists
+    [`ModeMismatch`]: ../struct.ModeMismatch.html
+    [`Unsupported`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.Unsupported
+    */
+    pub fn open(&mut self, number: impl Into<Option<u32>>) -> Result<(File, String)> {
+        self.options.open(number)
+    }
+
+    /**
+    Opens a tap device with the options specified by `self`, returning a
+    [`Device`] which keeps the file and the name of the device together.
+
+    See [`open`] for the arguments and errors.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let device = OpenOptions::new().open_device(0).unwrap();
+    println!("opened {}", device.name());
+    ```
+
+    [`Device`]: ../struct.Device.html
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    pub fn open_device(&mut self, number: impl Into<Option<u32>>) -> Result<Device> {
+        self.options.open_device(number)
+    }
+
+    /**
+    Opens a tap device with the options specified by `self`, consuming the
+    builder and returning a [`Device`].
+
+    See [`open`] for the arguments and errors.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    options.nonblock(true);
+    let device = options.open_owned(0).unwrap();
+    println!("opened {}", device.name());
+    ```
+
+    [`Device`]: ../struct.Device.html
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    pub fn open_owned(self, number: impl Into<Option<u32>>) -> Result<Device> {
+        self.options.open_owned(number)
+    }
+
+    /**
+    Opens a tap device with the options specified by `self`, returning an
+    [`AsyncDevice`] which implements `AsyncRead` and `AsyncWrite` from
+    `futures-io`.
+
+    The device is always opened in non-blocking mode, whatever [`nonblock`]
+    was set to. See [`open`] for the arguments and errors.
+
+    This method is only available with the `async-io` feature.
+
+    # Examples
+
+    ```no_run
+    use futures_lite::AsyncWriteExt;
+    use utuntap::tap::OpenOptions;
+
+    async_io::block_on(async {
+        let mut device = OpenOptions::new().open_async(0).unwrap();
+        device.write(&[0; 20]).await.unwrap();
+    });
+    ```
+
+    [`AsyncDevice`]: struct.AsyncDevice.html
+    [`nonblock`]: struct.OpenOptions.html#method.nonblock
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    #[cfg(feature = "async-io")]
+    pub fn open_async(&mut self, number: impl Into<Option<u32>>) -> Result<AsyncDevice> {
+        self.options.open_async(number)
+    }
+
+    /**
+    Opens a device like [`open_async`], returning the halves which read and
+    write it, ready to move into two tasks, along with its name.
+
+    See [`AsyncDevice::split`] for how the halves relate.
+
+    This method is only available with the `async-io` feature.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tap::OpenOptions;
+
+    let (reader, writer, name) = OpenOptions::new().open_async_split(0).unwrap();
+    ```
+
+    [`open_async`]: struct.OpenOptions.html#method.open_async
+    [`AsyncDevice::split`]: struct.AsyncDevice.html#method.split
     */
-    pub fn open(&mut self, number: u32) -> Result<File> {
-        Ok(self.options.open(number)?)
+    #[cfg(feature = "async-io")]
+    pub fn open_async_split(
+        &mut self,
+        number: impl Into<Option<u32>>,
+    ) -> Result<(AsyncReadHalf, AsyncWriteHalf, String)> {
+        self.options.open_async_split(number)
+    }
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self::new()
     }
 }
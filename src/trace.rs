@@ -0,0 +1,47 @@
+//! Debug events for the system calls made while opening devices, emitted
+//! through `tracing` when the `tracing` feature is enabled. Without the
+//! feature, every function here compiles down to passing its result on.
+
+use std::io::Result;
+
+/// Passes on the `result` of the system call `call` made for `name`, a
+/// device name or path, emitting a debug event with the outcome.
+#[inline(always)]
+pub(crate) fn traced<T>(call: &'static str, name: &str, result: Result<T>) -> Result<T> {
+    #[cfg(feature = "tracing")]
+    match &result {
+        Ok(_) => tracing::debug!(call, name, "succeeded"),
+        Err(error) => tracing::debug!(
+            call,
+            name,
+            errno = error.raw_os_error().unwrap_or(0),
+            %error,
+            "failed"
+        ),
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = (call, name);
+    result
+}
+
+/// Turns the return value `ret` of a libc call into a result, a negative
+/// one failing with `errno`, and passes it on like [`traced`].
+#[cfg(any(target_os = "macos", target_os = "illumos", target_os = "solaris"))]
+#[inline(always)]
+pub(crate) fn check(call: &'static str, name: &str, ret: libc::c_int) -> Result<libc::c_int> {
+    let result = if ret < 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    };
+    traced(call, name, result)
+}
+
+/// Emits a debug event for the device `name` once it is open.
+#[inline(always)]
+pub(crate) fn opened(name: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(name, "opened device");
+    #[cfg(not(feature = "tracing"))]
+    let _ = name;
+}
@@ -1,6 +1,8 @@
 //! APIs for level 3 Tun devices
 
-use super::Mode;
+#[cfg(feature = "async-io")]
+pub use super::{AsyncDevice, AsyncReadHalf, AsyncWriteHalf};
+use super::{Device, Mode};
 use std::fs::File;
 use std::io::Result;
 
@@ -31,7 +33,7 @@ Opening device `tun0`:
 ```no_run
 use utuntap::tun::OpenOptions;
 
-let file = OpenOptions::new().open(0).unwrap();
+let (file, filename) = OpenOptions::new().open(0).unwrap();
 ```
 
 Opening device `tun0` with non-blocking I/O set:
@@ -39,7 +41,7 @@ Opening device `tun0` with non-blocking I/O set:
 ```no_run
 use utuntap::tun::OpenOptions;
 
-let file = OpenOptions::new()
+let (file, filename) = OpenOptions::new()
             .nonblock(true)
             .open(0)
             .unwrap();
@@ -61,12 +63,11 @@ impl OpenOptions {
     use utuntap::tun::OpenOptions;
 
     let mut options = OpenOptions::new();
-    let file = options.open(0).unwrap();
+    let (file, filename) = options.open(0).unwrap();
     ```
     */
     pub fn new() -> Self {
-        let mut options = super::OpenOptions::new();
-        options.mode(Mode::Tun);
+        let options = super::OpenOptions::new(Mode::Tun);
         Self { options }
     }
 
@@ -84,7 +85,7 @@ impl OpenOptions {
     use utuntap::tun::OpenOptions;
 
     let mut options = OpenOptions::new();
-    let file = options.read(true).write(true).open(0).unwrap();
+    let (file, filename) = options.read(true).write(true).open(0).unwrap();
     ```
     */
     pub fn read(&mut self, value: bool) -> &mut Self {
@@ -98,7 +99,10 @@ impl OpenOptions {
     This option, when true, will indicate that the file should be
     `write`-able if opened.
 
-    This opiton defaults to `true`.
+    This opiton defaults to `true`. Except for utun sockets on macOS, which
+    are always readable and writable, a device opened with `false` is opened
+    `O_RDONLY` and writes to it fail with `EBADF`. A read-only Tun device
+    suits passive monitoring, as nothing can be injected through it.
 
     # Examples
 
@@ -106,7 +110,7 @@ impl OpenOptions {
     use utuntap::tun::OpenOptions;
 
     let mut options = OpenOptions::new();
-    let file = options.read(true).write(true).open(0).unwrap();
+    let (file, filename) = options.read(true).write(true).open(0).unwrap();
     ```
     */
     pub fn write(&mut self, value: bool) -> &mut Self {
@@ -126,7 +130,7 @@ impl OpenOptions {
     use utuntap::tun::OpenOptions;
 
     let mut options = OpenOptions::new();
-    let file = options.nonblock(true).open(0).unwrap();
+    let (file, filename) = options.nonblock(true).open(0).unwrap();
     ```
     */
     #[cfg(target_family = "unix")]
@@ -135,6 +139,102 @@ impl OpenOptions {
         self
     }
 
+    /**
+    Sets how reads of the device wait for packets: forever, not at all, or
+    for at most a given duration.
+
+    This option defaults to [`BlockingMode::Blocking`]; [`nonblock`] is a
+    shorthand for the first two. See [`BlockingMode`] for how a timeout
+    applies.
+
+    # Examples
+
+    ```no_run
+    use std::time::Duration;
+    use utuntap::{tun::OpenOptions, BlockingMode};
+
+    let device = OpenOptions::new()
+        .blocking_mode(BlockingMode::Timeout(Duration::from_secs(1)))
+        .open_device(0)
+        .unwrap();
+    ```
+
+    [`BlockingMode::Blocking`]: ../enum.BlockingMode.html#variant.Blocking
+    [`BlockingMode`]: ../enum.BlockingMode.html
+    [`nonblock`]: struct.OpenOptions.html#method.nonblock
+    */
+    #[cfg(target_family = "unix")]
+    pub fn blocking_mode(&mut self, mode: crate::BlockingMode) -> &mut Self {
+        self.options.blocking_mode(mode);
+        self
+    }
+
+    /**
+    Sets the size of the buffer [`Device::read_frame_borrowed`] reads into,
+    allocating it when the device is opened rather than on the first read.
+
+    By default, the buffer is sized from the MTU by the first read. A
+    capacity of 0 makes opening fail with [`InvalidInput`].
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut device = OpenOptions::new()
+        .buffer_capacity(9000)
+        .open_device(0)
+        .unwrap();
+    let packet = device.read_frame_borrowed().unwrap();
+    ```
+
+    [`Device::read_frame_borrowed`]: ../struct.Device.html#method.read_frame_borrowed
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    pub fn buffer_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.options.buffer_capacity(capacity);
+        self
+    }
+
+    /**
+    Sets the option for signal-driven I/O: once the device is opened, `pid`,
+    or the current process if `None`, receives `SIGIO` whenever a packet is
+    waiting. `O_NONBLOCK` is left as set by [`nonblock`], and
+    [`clear_signal_driven`] turns the option off again.
+
+    The default action of `SIGIO` terminates the process, so a handler has
+    to be installed before opening. See [`Device::set_signal_driven`].
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    // A `SIGIO` handler must be installed before.
+    let device = OpenOptions::new().signal_driven(None).open_device(0).unwrap();
+    ```
+
+    [`nonblock`]: struct.OpenOptions.html#method.nonblock
+    [`clear_signal_driven`]: struct.OpenOptions.html#method.clear_signal_driven
+    [`Device::set_signal_driven`]: ../struct.Device.html#method.set_signal_driven
+    */
+    #[cfg(target_family = "unix")]
+    pub fn signal_driven(&mut self, pid: Option<libc::pid_t>) -> &mut Self {
+        self.options.signal_driven(pid);
+        self
+    }
+
+    /**
+    Turns the option for signal-driven I/O set by [`signal_driven`] off.
+
+    [`signal_driven`]: struct.OpenOptions.html#method.signal_driven
+    */
+    #[cfg(target_family = "unix")]
+    pub fn clear_signal_driven(&mut self) -> &mut Self {
+        self.options.clear_signal_driven();
+        self
+    }
+
     /**
     Sets the option for packet info.
 
@@ -149,7 +249,7 @@ impl OpenOptions {
     use utuntap::tun::OpenOptions;
 
     let mut options = OpenOptions::new();
-    let file = options.packet_info(true).open(0).unwrap();
+    let (file, filename) = options.packet_info(true).open(0).unwrap();
     ```
     */
     #[cfg(target_os = "linux")]
@@ -159,22 +259,39 @@ impl OpenOptions {
     }
 
     /**
-    Opens a tun device file with the options specified by `self`.
+    Sets the option for no packet info, the inverse of [`packet_info`].
 
-    # Arguments
+    This option, when true, will make the device be opened with
+    `IFF_NO_PI`, so that each packet read or written is one of the raw
+    IP packets. This option defaults to `true`.
 
-    * `number` - the number of the device, e.g. the "0" of "tun0".
+    This option is only available on Linux.
 
-    # Errors
+    # Examples
 
-    This function will return an error under a number of different
-    circumstances. Some of these error conditions are listed here, together
-    with their [`ErrorKind`]. The mapping to [`ErrorKind`]s is not part of
-    the compatibility contract of the function.
+    ```no_run
+    use utuntap::tun::OpenOptions;
 
-    * [`NotFound`]: The device file does not exist.
-    * [`PermissionDenied`]: The user lacks permission to get the specified
-      access rights for the file.
+    let mut options = OpenOptions::new();
+    let (file, filename) = options.no_packet_info(true).open(0).unwrap();
+    ```
+
+    [`packet_info`]: struct.OpenOptions.html#method.packet_info
+    */
+    #[cfg(target_os = "linux")]
+    pub fn no_packet_info(&mut self, value: bool) -> &mut Self {
+        self.options.no_packet_info(value);
+        self
+    }
+
+    /**
+    Sets the exact name of the interface to open, instead of deriving it from
+    the device number passed to [`open`], which must then be `None`.
+
+    When a persistent interface of that name exists, the device reattaches
+    to it and keeps its name, rather than creating a new interface.
+
+    This option is only available on Linux.
 
     # Examples
 
@@ -182,14 +299,1204 @@ impl OpenOptions {
     use utuntap::tun::OpenOptions;
 
     let mut options = OpenOptions::new();
-    let file = options.open(0).unwrap();
+    let (file, filename) = options.name("vpn0").open(None).unwrap();
     ```
 
-    [`ErrorKind`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html
-    [`NotFound`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.NotFound
-    [`PermissionDenied`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.PermissionDenied
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    #[cfg(target_os = "linux")]
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.options.name(name);
+        self
+    }
+
+    /**
+    Sets the name to open the interface with if it can be had, letting the
+    kernel pick one otherwise, e.g. `tun3`, which [`open`] returns.
+
+    Unlike with [`name`], opening does not fail when the interface `name`
+    is in use or exists as another kind of interface. [`open`] must be
+    called without a number.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let (file, filename) = OpenOptions::new().preferred_name("vpn0").open(None).unwrap();
+    println!("opened {}", filename);
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`name`]: struct.OpenOptions.html#method.name
+    */
+    #[cfg(target_os = "linux")]
+    pub fn preferred_name(&mut self, name: &str) -> &mut Self {
+        self.options.preferred_name(name);
+        self
+    }
+
+    /**
+    Sets the option for the virtio net header.
+
+    This option, when true, will indicate that each packet read or
+    written is prefixed with a `struct virtio_net_hdr`, which carries the
+    checksum and segmentation offload metadata.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    let (file, filename) = options.vnet_hdr(true).open(0).unwrap();
+    ```
+    */
+    #[cfg(target_os = "linux")]
+    pub fn vnet_hdr(&mut self, value: bool) -> &mut Self {
+        self.options.vnet_hdr(value);
+        self
+    }
+
+    /**
+    Sets the offloads to negotiate with the kernel once the device is open.
+
+    The requested offloads are masked against those the kernel accepts, and
+    the intersection is applied with `TUNSETOFFLOAD`. [`open_device`] returns
+    a [`Device`] telling which offloads were actually enabled.
+
+    Offloads are described in the virtio net header, so this option implies
+    [`vnet_hdr`]; opening fails with [`InvalidInput`] if it was explicitly
+    disabled.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+    use utuntap::tun::Offload;
+
+    let mut options = OpenOptions::new();
+    let device = options
+        .offload(Offload::CSUM | Offload::TSO4 | Offload::TSO6)
+        .open_device(0)
+        .unwrap();
+    println!("enabled offloads: {:?}", device.offload());
+    ```
+
+    [`open_device`]: struct.OpenOptions.html#method.open_device
+    [`Device`]: ../struct.Device.html
+    [`vnet_hdr`]: struct.OpenOptions.html#method.vnet_hdr
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    #[cfg(target_os = "linux")]
+    pub fn offload(&mut self, offload: crate::tun::Offload) -> &mut Self {
+        self.options.offload(offload);
+        self
+    }
+
+    /**
+    Sets the option to fail when the named device already exists.
+
+    This option, when true, will make opening fail with the OS error `EBUSY`
+    instead of attaching to an existing interface, e.g. a persistent one left
+    over by a previous run. This only makes sense together with a device
+    number or a [`name`].
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    match options.exclusive(true).open(0) {
+        Ok((file, filename)) => println!("created {}", filename),
+        Err(error) if error.raw_os_error() == Some(libc::EBUSY) => {
+            println!("tun0 already exists")
+        }
+        Err(error) => panic!("{}", error),
+    }
+    ```
+
+    [`name`]: struct.OpenOptions.html#method.name
+    */
+    #[cfg(target_os = "linux")]
+    pub fn exclusive(&mut self, value: bool) -> &mut Self {
+        self.options.exclusive(value);
+        self
+    }
+
+    /**
+    Sets the clone device to open, e.g. when the kernel puts it somewhere
+    else than `/dev/net/tun`.
+
+    Without this option, `/dev/net/tun` is opened, or `/dev/tun` where the
+    former does not exist, as on some hardened kernels; the error of
+    `/dev/net/tun` is returned if neither can be opened.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new()
+        .device_path("/run/tun")
+        .open_device(0)
+        .unwrap();
+    ```
+    */
+    #[cfg(target_os = "linux")]
+    pub fn device_path(&mut self, path: impl AsRef<std::path::Path>) -> &mut Self {
+        self.options.device_path(path);
+        self
+    }
+
+    /**
+    Lets the kernel pick the first free utun unit, e.g. `utun3`, which
+    [`open`] returns.
+
+    This option, when set, will make opening connect to the utun control
+    with `sc_unit` 0, the macOS counterpart of `ephemeral` on Linux.
+    Opening with no device number does the same; this option states it
+    explicitly and rejects a number passed to [`open`] with
+    [`InvalidInput`] instead.
+
+    This option is only available on macOS.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let (file, filename) = OpenOptions::new().any_unit().open(None).unwrap();
+    println!("opened {}", filename);
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    */
+    #[cfg(target_os = "macos")]
+    pub fn any_unit(&mut self) -> &mut Self {
+        self.options.any_unit();
+        self
+    }
+
+    /**
+    Lets the kernel assign a free name to the device, e.g. `tun3`, which
+    [`open`] returns.
+
+    This is handy for short-lived devices, e.g. in tests, as no counter has
+    to be managed. It replaces any [`name`] set before, and [`open`] must be
+    called without a number.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let (file, filename) = OpenOptions::new().ephemeral().open(None).unwrap();
+    println!("opened {}", filename);
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`name`]: struct.OpenOptions.html#method.name
+    */
+    #[cfg(target_os = "linux")]
+    pub fn ephemeral(&mut self) -> &mut Self {
+        self.options.ephemeral();
+        self
+    }
+
+    /**
+    Sets the option for NAPI processing of written packets.
+
+    This option, when true, will make the kernel hand packets written to the
+    device to the network stack through NAPI, batching them like a real
+    driver does. It is meant for performance experiments and tests of the
+    receive path; it needs `CAP_NET_ADMIN` and Linux 4.15 or later, and
+    older kernels fail the open with `EINVAL`.
+
+    This option is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    let (file, filename) = options.napi(true).open(0).unwrap();
+    ```
+    */
+    #[cfg(target_os = "linux")]
+    pub fn napi(&mut self, value: bool) -> &mut Self {
+        self.options.napi(value);
+        self
+    }
+
+    /**
+    Sets the option to strip the protocol information of the platform.
+
+    This option, when true, will make the `Read` implementation of a
+    [`Device`] opened with [`open_device`] drop the 4-byte prefix of every
+    packet, i.e. the address family on macOS or the packet info on Linux,
+    and its `Write` implementation add the appropriate one back, so only raw
+    packets go through. The family of read packets is lost then; use
+    [`Device::read_packet`] to learn it. Files returned by [`open`] are
+    unaffected.
+
+    This option defaults to `false`.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    let device = options.strip_protocol_info(true).open_device(0).unwrap();
+    ```
+
+    [`Device`]: ../struct.Device.html
+    [`Device::read_packet`]: ../struct.Device.html#method.read_packet
+    [`open_device`]: struct.OpenOptions.html#method.open_device
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    pub fn strip_protocol_info(&mut self, value: bool) -> &mut Self {
+        self.options.strip_protocol_info(value);
+        self
+    }
+
+    /**
+    Sets the name of the kernel control to connect to.
+
+    This option, when set, will make the device be opened through the given
+    kernel control, e.g. one exposed by a custom kernel extension, instead
+    of `com.apple.net.utun_control`. The control must behave like utun: the
+    name of the device is derived as `utun<N>` all the same.
+
+    This option is only available on macOS.
+
+    # Errors
+
+    Opening fails with [`InvalidInput`] when the name does not fit the
+    96-byte `ctl_name` buffer along with its terminating NUL.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    let (file, filename) = options.control_name("com.example.utun").open(0).unwrap();
+    ```
+
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
     */
-    pub fn open(&mut self, number: u32) -> Result<File> {
-        Ok(self.options.open(number)?)
+    #[cfg(target_os = "macos")]
+    pub fn control_name(&mut self, value: &str) -> &mut Self {
+        self.options.control_name(value);
+        self
+    }
+
+    /**
+    Like [`open`], but writes the name of the device into `name_buf`
+    instead of allocating a `String`, returning the length of the name.
+
+    See [`utuntap::OpenOptions::open_into`] for details.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut name = [0; 16];
+    let (file, len) = OpenOptions::new().open_into(0, &mut name).unwrap();
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`utuntap::OpenOptions::open_into`]: ../struct.OpenOptions.html#method.open_into
+    */
+    pub fn open_into(
+        &mut self,
+        number: impl Into<Option<u32>>,
+        name_buf: &mut [u8],
+    ) -> Result<(File, usize)> {
+        self.options.open_into(number, name_buf)
+    }
+
+    /**
+    Opens a multi-queue tun device with `queues` files attached to it,
+    returning each file along with the index of its queue, and the name of
+    the device.
+
+    See [`utuntap::OpenOptions::open_multiqueue`] for details.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let (queues, name) = OpenOptions::new().open_multiqueue(0, 4).unwrap();
+    ```
+
+    [`utuntap::OpenOptions::open_multiqueue`]: ../struct.OpenOptions.html#method.open_multiqueue
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_multiqueue(
+        &mut self,
+        number: impl Into<Option<u32>>,
+        queues: usize,
+    ) -> Result<(Vec<(File, usize)>, String)> {
+        self.options.open_multiqueue(number, queues)
+    }
+
+    /**
+    Opens a tun device twice, returning a read-only file, a write-only file
+    and the name of the device.
+
+    See [`utuntap::OpenOptions::open_rw_split`] for details.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let (reader, writer, name) = OpenOptions::new().open_rw_split(0).unwrap();
+    ```
+
+    [`utuntap::OpenOptions::open_rw_split`]: ../struct.OpenOptions.html#method.open_rw_split
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_rw_split(
+        &mut self,
+        number: impl Into<Option<u32>>,
+    ) -> Result<(File, File, String)> {
+        self.options.open_rw_split(number)
+    }
+
+    /**
+    Opens the tun device named `name`, creating its interface unless it
+    exists, and tells whether it was created.
+
+    See [`utuntap::OpenOptions::open_or_attach`] for details.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let (device, created) = OpenOptions::new().open_or_attach("wg0").unwrap();
+    ```
+
+    [`utuntap::OpenOptions::open_or_attach`]: ../struct.OpenOptions.html#method.open_or_attach
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_or_attach(&mut self, name: &str) -> Result<(Device, bool)> {
+        self.options.open_or_attach(name)
+    }
+
+    /**
+    Like [`open`], but creates the interface inside the network namespace
+    `netns`, returning the calling thread to its own namespace afterwards.
+
+    See [`utuntap::OpenOptions::open_in_netns`] for details.
+
+    This method is only available on Linux.
+
+    # Examples
+
+    ```no_run
+    use std::path::Path;
+    use utuntap::tun::OpenOptions;
+
+    let netns = Path::new("/var/run/netns/blue");
+    let (file, filename) = OpenOptions::new().open_in_netns(netns, 0).unwrap();
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`utuntap::OpenOptions::open_in_netns`]: ../struct.OpenOptions.html#method.open_in_netns
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_in_netns(
+        &mut self,
+        netns: &std::path::Path,
+        number: impl Into<Option<u32>>,
+    ) -> Result<(File, String)> {
+        self.options.open_in_netns(netns, number)
+    }
+
+    /**
+    Like [`open`], but retries `attempts` times in total, sleeping `delay`
+    in between, while the device node or driver is missing.
+
+    See [`utuntap::OpenOptions::open_with_retry`] for details.
+
+    # Examples
+
+    ```no_run
+    use std::time::Duration;
+    use utuntap::tun::OpenOptions;
+
+    let (file, filename) = OpenOptions::new()
+        .open_with_retry(0, 10, Duration::from_millis(100))
+        .unwrap();
+    ```
+
+    [`open`]: struct.OpenOptions.html#method.open
+    [`utuntap::OpenOptions::open_with_retry`]: ../struct.OpenOptions.html#method.open_with_retry
+    */
+    pub fn open_with_retry(
+        &mut self,
+        number: impl Into<Option<u32>>,
+        attempts: u32,
+        delay: std::time::Duration,
+    ) -> Result<(File, String)> {
+        self.options.open_with_retry(number, attempts, delay)
+    }
+
+    /**
+    Checks that the device `number` can be opened with the options specified
+    by `self`, without opening it, e.g. for a `--check` mode of a tool.
+
+    See [`utuntap::OpenOptions::validate`] for details.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    OpenOptions::new().validate(0).expect("invalid configuration");
+    ```
+
+    [`utuntap::OpenOptions::validate`]: ../struct.OpenOptions.html#method.validate
+    */
+    pub fn validate(&self, number: impl Into<Option<u32>>) -> Result<()> {
+        self.options.validate(number)
+    }
+
+    /**
+    Sets the MTU of the interface as part of opening it.
+
+    This option, when set, will make opening issue `SIOCSIFMTU` once the
+    device is created, the same as [`interface::set_mtu`]. If the MTU cannot
+    be set, e.g. because it is out of range for the interface, opening fails
+    and the device file is closed, so a device is never returned with
+    another MTU. Setting the MTU needs `CAP_NET_ADMIN` on Linux and root on
+    macOS, even for a persistent device owned by the user.
+
+    This option is only available on Linux and macOS.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    let (file, filename) = options.mtu(1280).open(0).unwrap();
+    ```
+
+    [`interface::set_mtu`]: ../interface/fn.set_mtu.html
+    */
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn mtu(&mut self, value: u32) -> &mut Self {
+        self.options.mtu(value);
+        self
+    }
+
+    /**
+    Opens a tun device file with the options specified by `self`, returning
+    the file and the name of the device.
+
+    # Arguments
+
+    * `number` - the number of the device, e.g. the "0" of "tun0". On Linux,
+      macOS, OpenBSD and illumos, when it is not set, the OS will assign one
+      for you; the returned name, e.g. `utun7`, tells which.
+
+    # Errors
+
+    This function will return an error under a number of different
+    circumstances. Some of these error conditions are listed here, together
+    with their [`ErrorKind`]. The mapping to [`ErrorKind`]s is not part of
+    the compatibility contract of the function.
+
+    * [`NotFound`]: The device file does not exist.
+    * [`PermissionDenied`]: The user lacks permission to get the specified
+      access rights for the file.
+    * [`InvalidInput`]: The number is out of range for the platform: the
+      device name must be shorter than `IFNAMSIZ` (16 bytes), and utun
+      numbers on macOS go up to `u32::MAX - 1`.
+    * [`AlreadyExists`]: The interface exists as the other kind of device on
+      Linux, with a [`ModeMismatch`] telling which.
+    * [`AddrInUse`]: The utun unit is taken on macOS, or no unit is free when
+      the kernel picks one. [`is_busy`] tells this apart on every platform.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    let (file, filename) = options.open(0).unwrap();
+    ```
+
+    Letting the OS assign a name on Linux or macOS:
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let (file, filename) = OpenOptions::new().open(None).unwrap();
+    ```
+
+    [`ErrorKind`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html
+    [`NotFound`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.NotFound
+    [`PermissionDenied`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.PermissionDenied
+    [`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+    [`AlreadyExists`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.AlreadyExists
+    [`ModeMismatch`]: ../struct.ModeMismatch.html
+    [`AddrInUse`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.AddrInUse
+    [`is_busy`]: ../fn.is_busy.html
+    */
+    pub fn open(&mut self, number: impl Into<Option<u32>>) -> Result<(File, String)> {
+        self.options.open(number)
+    }
+
+    /**
+    Opens a tun device with the options specified by `self`, returning a
+    [`Device`] which keeps the file and the name of the device together.
+
+    See [`open`] for the arguments and errors.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let device = OpenOptions::new().open_device(0).unwrap();
+    println!("opened {}", device.name());
+    ```
+
+    [`Device`]: ../struct.Device.html
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    pub fn open_device(&mut self, number: impl Into<Option<u32>>) -> Result<Device> {
+        self.options.open_device(number)
+    }
+
+    /**
+    Opens a tun device with the options specified by `self`, consuming the
+    builder and returning a [`Device`].
+
+    See [`open`] for the arguments and errors.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let mut options = OpenOptions::new();
+    options.nonblock(true);
+    let device = options.open_owned(0).unwrap();
+    println!("opened {}", device.name());
+    ```
+
+    [`Device`]: ../struct.Device.html
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    pub fn open_owned(self, number: impl Into<Option<u32>>) -> Result<Device> {
+        self.options.open_owned(number)
+    }
+
+    /**
+    Opens a tun device with the options specified by `self`, returning an
+    [`AsyncDevice`] which implements `AsyncRead` and `AsyncWrite` from
+    `futures-io`.
+
+    The device is always opened in non-blocking mode, whatever [`nonblock`]
+    was set to. See [`open`] for the arguments and errors.
+
+    This method is only available with the `async-io` feature.
+
+    # Examples
+
+    ```no_run
+    use futures_lite::AsyncWriteExt;
+    use utuntap::tun::OpenOptions;
+
+    async_io::block_on(async {
+        let mut device = OpenOptions::new().open_async(0).unwrap();
+        device.write(&[0; 20]).await.unwrap();
+    });
+    ```
+
+    [`AsyncDevice`]: struct.AsyncDevice.html
+    [`nonblock`]: struct.OpenOptions.html#method.nonblock
+    [`open`]: struct.OpenOptions.html#method.open
+    */
+    #[cfg(feature = "async-io")]
+    pub fn open_async(&mut self, number: impl Into<Option<u32>>) -> Result<AsyncDevice> {
+        self.options.open_async(number)
+    }
+
+    /**
+    Opens a device like [`open_async`], returning the halves which read and
+    write it, ready to move into two tasks, along with its name.
+
+    See [`AsyncDevice::split`] for how the halves relate.
+
+    This method is only available with the `async-io` feature.
+
+    # Examples
+
+    ```no_run
+    use utuntap::tun::OpenOptions;
+
+    let (reader, writer, name) = OpenOptions::new().open_async_split(0).unwrap();
+    ```
+
+    [`open_async`]: struct.OpenOptions.html#method.open_async
+    [`AsyncDevice::split`]: struct.AsyncDevice.html#method.split
+    */
+    #[cfg(feature = "async-io")]
+    pub fn open_async_split(
+        &mut self,
+        number: impl Into<Option<u32>>,
+    ) -> Result<(AsyncReadHalf, AsyncWriteHalf, String)> {
+        self.options.open_async_split(number)
+    }
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/**
+A set of offloads which the kernel may hand over to the reader of a device.
+
+With an offload enabled, packets read from the device may lack a checksum or
+exceed the MTU, as described by the virtio net header preceding them.
+
+TSO and UFO only take effect along with `CSUM`.
+*/
+#[cfg(target_os = "linux")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Offload(libc::c_uint);
+
+#[cfg(target_os = "linux")]
+impl Offload {
+    /// Packets may carry a partial checksum.
+    pub const CSUM: Self = Self(0x01);
+    /// Packets may be TCP over IPv4 segmentation offloaded.
+    pub const TSO4: Self = Self(0x02);
+    /// Packets may be TCP over IPv6 segmentation offloaded.
+    pub const TSO6: Self = Self(0x04);
+    /// TCP segmentation offloaded packets may carry ECN.
+    pub const TSO_ECN: Self = Self(0x08);
+    /// Packets may be UDP fragmentation offloaded.
+    pub const UFO: Self = Self(0x10);
+
+    const ALL: [Self; 5] = [Self::CSUM, Self::TSO4, Self::TSO6, Self::TSO_ECN, Self::UFO];
+
+    /// Returns an empty set.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns the raw `TUN_F_*` flags.
+    pub const fn bits(&self) -> libc::c_uint {
+        self.0
+    }
+
+    /// Converts raw `TUN_F_*` flags, dropping unknown ones.
+    pub const fn from_bits_truncate(bits: libc::c_uint) -> Self {
+        Self(bits & 0x1f)
+    }
+
+    /// Returns `true` if no offload is set.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if all offloads of `other` are set.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl std::ops::BitOr for Offload {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl std::ops::BitOrAssign for Offload {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl std::ops::BitAnd for Offload {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+/**
+Returns the `IFF_*` flags supported by the Tun/Tap driver, as reported by
+`TUNGETFEATURES`.
+
+This function works on any file of the driver, Tun or Tap, and is only
+available on Linux.
+*/
+#[cfg(target_os = "linux")]
+pub fn get_features(file: &File) -> Result<libc::c_uint> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut features: libc::c_uint = 0;
+    unsafe {
+        crate::ioctl::tun_ioctl(
+            file.as_raw_fd(),
+            crate::ioctl::TUNGETFEATURES,
+            &mut features,
+        )?
+    };
+    Ok(features)
+}
+
+/**
+Sets the offloads the reader of the device accepts with `TUNSETOFFLOAD`.
+
+The kernel rejects unknown offloads with [`InvalidInput`]. Prefer
+[`OpenOptions::offload`], which only applies the offloads the kernel
+supports.
+
+This function is only available on Linux.
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+[`OpenOptions::offload`]: struct.OpenOptions.html#method.offload
+*/
+#[cfg(target_os = "linux")]
+pub fn set_offload(file: &File, offload: Offload) -> Result<()> {
+    use crate::ioctl::{tun_ioctl_with_value, TUNSETOFFLOAD};
+    use std::os::unix::io::AsRawFd;
+
+    unsafe { tun_ioctl_with_value(file.as_raw_fd(), TUNSETOFFLOAD, offload.bits().into()) }
+}
+
+/**
+Returns the offloads in effect on the interface of the device.
+
+The kernel has no ioctl to read back what `TUNSETOFFLOAD` set, so the
+offloads are read from the features of the interface, as `ethtool -k`
+shows them. [`Offload::UFO`] is never reported, since recent kernels map it
+to no interface feature. [`Device::offload`] returns what was negotiated at
+open time without asking the kernel.
+
+This function is only available on Linux.
+
+# Examples
+
+```no_run
+use utuntap::tun::{self, Offload};
+
+let device = tun::OpenOptions::new()
+    .offload(Offload::CSUM | Offload::TSO4)
+    .open_device(0)
+    .unwrap();
+let offload = tun::get_offload(device.file()).unwrap();
+println!("TSO4 enabled: {}", offload.contains(Offload::TSO4));
+```
+
+[`Offload::UFO`]: struct.Offload.html#associatedconstant.UFO
+[`Device::offload`]: ../struct.Device.html#method.offload
+*/
+#[cfg(target_os = "linux")]
+pub fn get_offload(file: &File) -> Result<Offload> {
+    use crate::interface::linux::{get_active_features, Request};
+    use std::os::unix::io::AsRawFd;
+
+    let name = Request::get_tuntap(file.as_raw_fd())?.name()?;
+    let mut offload = Offload::empty();
+    for feature in get_active_features(&name)? {
+        offload |= match feature.as_str() {
+            "tx-checksum-ip-generic" => Offload::CSUM,
+            "tx-tcp-segmentation" => Offload::TSO4,
+            "tx-tcp6-segmentation" => Offload::TSO6,
+            "tx-tcp-ecn-segmentation" => Offload::TSO_ECN,
+            _ => continue,
+        };
+    }
+    Ok(offload)
+}
+
+/**
+Sets whether the interface of the device outlives it with `TUNSETPERSIST`.
+
+A persistent interface stays around after every file attached to it is
+closed, so it can be configured once and reopened by name later. Clearing
+the flag lets the kernel remove the interface once it is closed.
+
+This function works on Tun and Tap devices, and is only available on Linux.
+
+# Examples
+
+```no_run
+use utuntap::tun;
+
+let device = tun::OpenOptions::new().open_device(0).unwrap();
+tun::set_persist(device.file(), true).unwrap();
+```
+*/
+#[cfg(target_os = "linux")]
+pub fn set_persist(file: &File, persist: bool) -> Result<()> {
+    use crate::ioctl::{tun_ioctl_with_value, TUNSETPERSIST};
+    use std::os::unix::io::AsRawFd;
+
+    unsafe { tun_ioctl_with_value(file.as_raw_fd(), TUNSETPERSIST, persist.into()) }
+}
+
+/**
+Turns the debug messages of the Tun/Tap driver about the interface of `file`
+on or off with `TUNSETDEBUG`, as a debugging aid for packets going missing.
+
+Whether the messages reach the kernel log, as read by `dmesg`, depends on
+how the kernel was built: older kernels only log them with `TUN_DEBUG`
+defined in the driver, newer ones take the flag as the message level of
+the interface and log through dynamic debug, which has to be enabled for
+the `tun` module.
+
+This function works on Tun and Tap devices, and is only available on Linux.
+
+# Examples
+
+```no_run
+use utuntap::tun;
+
+let device = tun::OpenOptions::new().open_device(0).unwrap();
+tun::set_debug(device.file(), true).unwrap();
+```
+*/
+#[cfg(target_os = "linux")]
+pub fn set_debug(file: &File, on: bool) -> Result<()> {
+    use crate::ioctl::{tun_ioctl_with_value, TUNSETDEBUG};
+    use std::os::unix::io::AsRawFd;
+
+    unsafe { tun_ioctl_with_value(file.as_raw_fd(), TUNSETDEBUG, on.into()) }
+}
+
+/// The ARP hardware type of Ethernet, that of Tap interfaces by default.
+#[cfg(target_os = "linux")]
+pub const ARPHRD_ETHER: libc::c_int = 1;
+/// The ARP hardware type of PPP links.
+#[cfg(target_os = "linux")]
+pub const ARPHRD_PPP: libc::c_int = 512;
+/// The ARP hardware type of links without a hardware header, that of Tun
+/// interfaces.
+#[cfg(target_os = "linux")]
+pub const ARPHRD_NONE: libc::c_int = 0xfffe;
+
+/**
+Sets the ARP hardware type of the interface of `file` to `arphrd` with
+`TUNSETLINK`, e.g. [`ARPHRD_NONE`] or [`ARPHRD_PPP`].
+
+The type is what `ip link` shows as the link type, and what software looking
+at the interface goes by, e.g. the link-local address IPv6 generates or how
+packet sockets report it. It does not change what the device carries: a Tap
+device whose type is `ARPHRD_NONE` still reads and writes Ethernet frames
+and resolves neighbours with ARP, as the driver keeps its Ethernet header
+handling. For bare IP packets, open a Tun device instead, whose type is
+`ARPHRD_NONE` already. The type can only be changed while the interface is
+down.
+
+This function works on Tun and Tap devices, and is only available on Linux.
+
+# Errors
+
+Errors are reported by the OS, e.g. `EBUSY` when the interface is up.
+
+# Examples
+
+```no_run
+use utuntap::tun;
+
+let device = utuntap::tap::OpenOptions::new().open_device(0).unwrap();
+tun::set_link_type(device.file(), tun::ARPHRD_NONE).unwrap();
+```
+
+[`ARPHRD_NONE`]: constant.ARPHRD_NONE.html
+[`ARPHRD_PPP`]: constant.ARPHRD_PPP.html
+*/
+#[cfg(target_os = "linux")]
+pub fn set_link_type(file: &File, arphrd: libc::c_int) -> Result<()> {
+    use crate::ioctl::{tun_ioctl_with_value, TUNSETLINK};
+    use std::os::unix::io::AsRawFd;
+
+    unsafe { tun_ioctl_with_value(file.as_raw_fd(), TUNSETLINK, arphrd as _) }
+}
+
+/**
+Sets the byte order of the virtio net header with `TUNSETVNETLE` and
+`TUNSETVNETBE`: little-endian when `le` is true, big-endian otherwise.
+
+Parse and build headers with the matching [`vnet::Endianness`].
+
+# Errors
+
+Big-endian headers on a little-endian host need a kernel built with
+`CONFIG_TUN_VNET_CROSS_LE`; other kernels fail with [`InvalidInput`].
+
+This function is only available on Linux.
+
+# Examples
+
+```no_run
+use utuntap::{tun, vnet::Endianness};
+
+let device = tun::OpenOptions::new().vnet_hdr(true).open_device(0).unwrap();
+tun::set_vnet_little_endian(device.file(), true).unwrap();
+let endianness = Endianness::Little;
+```
+
+[`vnet::Endianness`]: ../vnet/enum.Endianness.html
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+#[cfg(target_os = "linux")]
+pub fn set_vnet_little_endian(file: &File, le: bool) -> Result<()> {
+    use crate::ioctl::{tun_ioctl, TUNSETVNETBE, TUNSETVNETLE};
+    use std::os::unix::io::AsRawFd;
+
+    let fd = file.as_raw_fd();
+    let mut enabled = libc::c_int::from(le);
+    unsafe { tun_ioctl(fd, TUNSETVNETLE, &mut enabled)? };
+    if !le && cfg!(target_endian = "little") {
+        let mut enabled: libc::c_int = 1;
+        unsafe { tun_ioctl(fd, TUNSETVNETBE, &mut enabled)? };
+    }
+    Ok(())
+}
+
+/// Returns the byte order of the virtio net header, as read with
+/// `TUNGETVNETLE` and `TUNGETVNETBE`. Kernels without cross-endian support
+/// reject the latter, leaving the host byte order.
+#[cfg(target_os = "linux")]
+pub(crate) fn get_vnet_endianness(file: &File) -> Result<crate::vnet::Endianness> {
+    use crate::ioctl::{tun_ioctl, TUNGETVNETBE, TUNGETVNETLE};
+    use crate::vnet::Endianness;
+    use std::os::unix::io::AsRawFd;
+
+    let fd = file.as_raw_fd();
+    let mut enabled: libc::c_int = 0;
+    unsafe { tun_ioctl(fd, TUNGETVNETLE, &mut enabled)? };
+    if enabled != 0 {
+        return Ok(Endianness::Little);
+    }
+    if unsafe { tun_ioctl(fd, TUNGETVNETBE, &mut enabled) }.is_ok() && enabled != 0 {
+        return Ok(Endianness::Big);
+    }
+    Ok(Endianness::Native)
+}
+
+/**
+Returns the size of the virtio net header in front of each packet, as read
+with `TUNGETVNETHDRSZ`.
+
+The header is 10 bytes long unless the size was changed, e.g. to 12 bytes
+for the `num_buffers` field of `struct virtio_net_hdr_mrg_rxbuf`. This tells
+a process which adopted a device from elsewhere where the packet starts
+after the [`VnetHdr`], which [`VnetHdr::parse`] reads the first 10 bytes of.
+
+This function is only available on Linux.
+
+# Examples
+
+```no_run
+use std::io::Read;
+use utuntap::{tun, vnet::{Endianness, VnetHdr}};
+
+let device = unsafe { utuntap::Device::from_fd(3, "tun0".to_string()) };
+let size = tun::get_vnet_hdr_size(device.file()).unwrap() as usize;
+let mut buffer = [0; 65536];
+let number = device.file().read(&mut buffer).unwrap();
+let hdr = VnetHdr::parse(&buffer[..number], Endianness::Native).unwrap();
+let packet = &buffer[size..number];
+```
+
+[`VnetHdr`]: ../vnet/struct.VnetHdr.html
+[`VnetHdr::parse`]: ../vnet/struct.VnetHdr.html#method.parse
+*/
+#[cfg(target_os = "linux")]
+pub fn get_vnet_hdr_size(file: &File) -> Result<u32> {
+    use crate::ioctl::{tun_ioctl, TUNGETVNETHDRSZ};
+    use std::os::unix::io::AsRawFd;
+
+    let mut size: libc::c_int = 0;
+    unsafe { tun_ioctl(file.as_raw_fd(), TUNGETVNETHDRSZ, &mut size)? };
+    Ok(size as u32)
+}
+
+/**
+Attaches the eBPF program `prog_fd` which picks the queue of each packet of
+an interface with several queues, with `TUNSETSTEERINGEBPF`. A `prog_fd` of
+-1 detaches the program, bringing back the kernel's own flow hashing.
+
+The program must already be loaded as a `BPF_PROG_TYPE_SOCKET_FILTER`, e.g.
+with a BPF library; its return value modulo the number of queues selects the
+queue. The kernel holds its own reference, so `prog_fd` may be closed
+afterwards.
+
+# Errors
+
+This function returns [`InvalidInput`] when `prog_fd` is not a loaded program
+of the right type.
+
+This function is only available on Linux.
+
+# Examples
+
+```no_run
+use std::os::unix::io::RawFd;
+use utuntap::tun;
+
+let device = tun::OpenOptions::new().open_device(0).unwrap();
+let prog_fd: RawFd = 42; // loaded elsewhere
+tun::set_steering_ebpf(device.file(), prog_fd).unwrap();
+```
+
+[`InvalidInput`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidInput
+*/
+#[cfg(target_os = "linux")]
+pub fn set_steering_ebpf(file: &File, prog_fd: std::os::unix::io::RawFd) -> Result<()> {
+    use crate::ioctl::{tun_ioctl, TUNSETSTEERINGEBPF};
+    use std::os::unix::io::AsRawFd;
+
+    let mut prog_fd: libc::c_int = prog_fd;
+    unsafe { tun_ioctl(file.as_raw_fd(), TUNSETSTEERINGEBPF, &mut prog_fd) }
+}
+
+/**
+Sets the size of the send buffer of the utun socket `file` with
+`SO_SNDBUF`, bounding how many bytes of packets written to the device may
+wait for the kernel to take them.
+
+The kernel may adjust the size, and fails with `ENOBUFS` beyond the
+`kern.ipc.maxsockbuf` limit. Only macOS devices are sockets; this function
+is only available there.
+
+# Examples
+
+```no_run
+use utuntap::tun;
+
+let device = tun::OpenOptions::new().open_device(0).unwrap();
+tun::set_send_buffer(device.file(), 256 * 1024).unwrap();
+```
+*/
+#[cfg(target_os = "macos")]
+pub fn set_send_buffer(file: &File, bytes: libc::c_int) -> Result<()> {
+    set_socket_option(file, libc::SO_SNDBUF, bytes)
+}
+
+/**
+Sets the size of the receive buffer of the utun socket `file` with
+`SO_RCVBUF`, bounding how many bytes of packets the kernel queues for the
+device before dropping them.
+
+The kernel may adjust the size, and fails with `ENOBUFS` beyond the
+`kern.ipc.maxsockbuf` limit. Only macOS devices are sockets; this function
+is only available there.
+
+# Examples
+
+```no_run
+use utuntap::tun;
+
+let device = tun::OpenOptions::new().open_device(0).unwrap();
+tun::set_recv_buffer(device.file(), 1024 * 1024).unwrap();
+```
+*/
+#[cfg(target_os = "macos")]
+pub fn set_recv_buffer(file: &File, bytes: libc::c_int) -> Result<()> {
+    set_socket_option(file, libc::SO_RCVBUF, bytes)
+}
+
+/// Sets the `SOL_SOCKET` option `name` of the socket `file` to `value`.
+#[cfg(target_os = "macos")]
+fn set_socket_option(file: &File, name: libc::c_int, value: libc::c_int) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let err = unsafe {
+        libc::setsockopt(
+            file.as_raw_fd(),
+            libc::SOL_SOCKET,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of_val(&value) as libc::socklen_t,
+        )
+    };
+    if err < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Applies the subset of `requested` the kernel accepts, returning the
+/// offloads which took effect.
+#[cfg(target_os = "linux")]
+pub(crate) fn negotiate_offload(file: &File, requested: Offload) -> Result<Offload> {
+    use crate::interface::linux::IFF_VNET_HDR;
+    use std::io::{Error, ErrorKind};
+
+    if get_features(file)? & IFF_VNET_HDR as libc::c_uint == 0 {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "the kernel does not support the virtio net header",
+        ));
+    }
+
+    let accepted = match set_offload(file, requested) {
+        Ok(()) => requested,
+        Err(error) if error.kind() == ErrorKind::InvalidInput => {
+            let mut accepted = Offload::empty();
+            for offload in Offload::ALL {
+                if requested.contains(offload) && set_offload(file, accepted | offload).is_ok() {
+                    accepted |= offload;
+                }
+            }
+            set_offload(file, accepted)?;
+            accepted
+        }
+        Err(error) => return Err(error),
+    };
+
+    // The kernel ignores segmentation offloads without checksum offload.
+    if accepted.contains(Offload::CSUM) {
+        Ok(accepted)
+    } else {
+        Ok(Offload::empty())
     }
 }
@@ -0,0 +1,332 @@
+/*!
+The virtio net header preceding packets of devices opened with `vnet_hdr`
+
+When offloads are negotiated, the kernel hands over super-packets larger than
+the MTU along with a [`VnetHdr`] describing how to segment them. [`segment`]
+splits such a packet into packets a non-GSO-aware consumer can handle.
+
+[`VnetHdr`]: struct.VnetHdr.html
+[`segment`]: fn.segment.html
+*/
+
+use crate::checksum;
+use std::io::{Error, ErrorKind, Result};
+
+/// The packet carries a partial checksum to complete at `csum_start + csum_offset`.
+pub const VIRTIO_NET_HDR_F_NEEDS_CSUM: u8 = 1;
+/// The packet is not a GSO super-packet.
+pub const VIRTIO_NET_HDR_GSO_NONE: u8 = 0;
+/// The packet is a TCP over IPv4 super-packet.
+pub const VIRTIO_NET_HDR_GSO_TCPV4: u8 = 1;
+/// The packet is a UDP over IPv4 super-packet to fragment.
+pub const VIRTIO_NET_HDR_GSO_UDP: u8 = 3;
+/// The packet is a TCP over IPv6 super-packet.
+pub const VIRTIO_NET_HDR_GSO_TCPV6: u8 = 4;
+/// The packet is a UDP super-packet to split into datagrams.
+pub const VIRTIO_NET_HDR_GSO_UDP_L4: u8 = 5;
+/// The TCP super-packet carries ECN.
+pub const VIRTIO_NET_HDR_GSO_ECN: u8 = 0x80;
+
+/**
+The byte order of the multi-byte fields of a [`VnetHdr`].
+
+Devices use the host byte order unless told otherwise with
+[`tun::set_vnet_little_endian`], which virtio 1.0 guests expect.
+
+[`VnetHdr`]: struct.VnetHdr.html
+[`tun::set_vnet_little_endian`]: ../tun/fn.set_vnet_little_endian.html
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    /// The byte order of the host, the default of the kernel.
+    Native,
+    /// Little-endian, as set by `TUNSETVNETLE`.
+    Little,
+    /// Big-endian, as set by `TUNSETVNETBE`.
+    Big,
+}
+
+impl Endianness {
+    fn read(self, bytes: [u8; 2]) -> u16 {
+        match self {
+            Endianness::Native => u16::from_ne_bytes(bytes),
+            Endianness::Little => u16::from_le_bytes(bytes),
+            Endianness::Big => u16::from_be_bytes(bytes),
+        }
+    }
+
+    fn write(self, value: u16) -> [u8; 2] {
+        match self {
+            Endianness::Native => value.to_ne_bytes(),
+            Endianness::Little => value.to_le_bytes(),
+            Endianness::Big => value.to_be_bytes(),
+        }
+    }
+}
+
+/**
+A `struct virtio_net_hdr`.
+
+The header is 10 bytes long, or 12 bytes when it carries `num_buffers`
+(`struct virtio_net_hdr_mrg_rxbuf`). The byte order of its multi-byte fields
+depends on the device, so it is passed explicitly as an [`Endianness`]:
+parsing with the wrong one silently corrupts `gso_size` and `csum_offset`.
+
+[`Endianness`]: enum.Endianness.html
+*/
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VnetHdr {
+    /// `VIRTIO_NET_HDR_F_*` flags.
+    pub flags: u8,
+    /// One of the `VIRTIO_NET_HDR_GSO_*` types, possibly with `VIRTIO_NET_HDR_GSO_ECN`.
+    pub gso_type: u8,
+    /// The length of the headers to replicate in each segment.
+    pub hdr_len: u16,
+    /// The maximum payload size of each segment.
+    pub gso_size: u16,
+    /// The offset at which to start checksumming.
+    pub csum_start: u16,
+    /// The offset after `csum_start` at which to store the checksum.
+    pub csum_offset: u16,
+}
+
+impl VnetHdr {
+    /// The size of the header without `num_buffers`.
+    pub const SIZE: usize = 10;
+
+    /**
+    Parses a header from the beginning of `bytes`, whose fields are in the
+    given byte order.
+
+    Returns `None` if `bytes` is shorter than [`SIZE`].
+
+    [`SIZE`]: struct.VnetHdr.html#associatedconstant.SIZE
+    */
+    pub fn parse(bytes: &[u8], endianness: Endianness) -> Option<Self> {
+        if bytes.len() < Self::SIZE {
+            return None;
+        }
+        let field = |offset: usize| endianness.read([bytes[offset], bytes[offset + 1]]);
+        Some(Self {
+            flags: bytes[0],
+            gso_type: bytes[1],
+            hdr_len: field(2),
+            gso_size: field(4),
+            csum_start: field(6),
+            csum_offset: field(8),
+        })
+    }
+
+    /// Serializes the header into its 10-byte representation, with fields in
+    /// the given byte order.
+    pub fn to_bytes(&self, endianness: Endianness) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[0] = self.flags;
+        bytes[1] = self.gso_type;
+        bytes[2..4].copy_from_slice(&endianness.write(self.hdr_len));
+        bytes[4..6].copy_from_slice(&endianness.write(self.gso_size));
+        bytes[6..8].copy_from_slice(&endianness.write(self.csum_start));
+        bytes[8..10].copy_from_slice(&endianness.write(self.csum_offset));
+        bytes
+    }
+}
+
+fn malformed(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/**
+Splits the GSO super-packet `payload` described by `hdr` into packets whose
+transport payloads are at most `gso_size` bytes.
+
+`payload` is the packet following the virtio net header, starting with the
+IP header on Tun devices or with the Ethernet header on Tap devices. Each
+segment repeats the headers with its IP length, IPv4 identification and
+transport fields fixed up, and carries complete IP and transport checksums.
+TCP segments get consecutive sequence numbers; FIN and PSH are only kept on
+the last segment and CWR only on the first.
+
+Packets which are not super-packets are returned as they are.
+
+# Errors
+
+* [`InvalidData`]: The packet is malformed.
+* [`Unsupported`]: The packet needs UDP fragmentation offload
+  (`VIRTIO_NET_HDR_GSO_UDP`), which is not implemented.
+
+# Examples
+
+```no_run
+use std::io::Read;
+use utuntap::{tun::OpenOptions, tun::Offload, vnet};
+
+let mut device = OpenOptions::new()
+    .offload(Offload::CSUM | Offload::TSO4)
+    .open_device(0)
+    .unwrap();
+let mut buffer = vec![0; 65536];
+let number = device.read(&mut buffer).unwrap();
+let hdr = vnet::VnetHdr::parse(&buffer[..number], vnet::Endianness::Native).unwrap();
+for packet in vnet::segment(&hdr, &buffer[vnet::VnetHdr::SIZE..number]).unwrap() {
+    // Handle each MTU-sized packet.
+}
+```
+
+[`InvalidData`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.InvalidData
+[`Unsupported`]: https://doc.rust-lang.org/nightly/std/io/enum.ErrorKind.html#variant.Unsupported
+*/
+pub fn segment(hdr: &VnetHdr, payload: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let gso_type = hdr.gso_type & !VIRTIO_NET_HDR_GSO_ECN;
+    let tcp = match gso_type {
+        VIRTIO_NET_HDR_GSO_NONE => return Ok(vec![payload.to_vec()]),
+        VIRTIO_NET_HDR_GSO_TCPV4 | VIRTIO_NET_HDR_GSO_TCPV6 => true,
+        VIRTIO_NET_HDR_GSO_UDP_L4 => false,
+        VIRTIO_NET_HDR_GSO_UDP => {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "UDP fragmentation offload is not supported",
+            ))
+        }
+        _ => return Err(malformed("unknown GSO type")),
+    };
+    if hdr.gso_size == 0 {
+        return Err(malformed("GSO size is zero"));
+    }
+
+    let network = network_offset(hdr, payload)?;
+    let (version, transport, protocol) = parse_ip(payload, network)?;
+    let transport_length = match (tcp, protocol) {
+        (true, 6) if payload.len() >= transport + 20 => {
+            usize::from(payload[transport + 12] >> 4) * 4
+        }
+        (false, 17) => 8,
+        _ => return Err(malformed("transport header does not match GSO type")),
+    };
+    let data = transport + transport_length;
+    if payload.len() < data {
+        return Err(malformed("truncated transport header"));
+    }
+
+    let headers = &payload[..data];
+    let chunks: Vec<&[u8]> = payload[data..].chunks(usize::from(hdr.gso_size)).collect();
+    let count = chunks.len();
+    let mut segments = Vec::with_capacity(count);
+    let mut sequence_offset: u32 = 0;
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        let mut segment = Vec::with_capacity(headers.len() + chunk.len());
+        segment.extend_from_slice(headers);
+        segment.extend_from_slice(chunk);
+
+        let transport_total = (transport_length + chunk.len()) as u32;
+        match version {
+            4 => {
+                let total = (transport - network) as u32 + transport_total;
+                segment[network + 2..network + 4].copy_from_slice(&(total as u16).to_be_bytes());
+                let id = u16::from_be_bytes([segment[network + 4], segment[network + 5]])
+                    .wrapping_add(index as u16);
+                segment[network + 4..network + 6].copy_from_slice(&id.to_be_bytes());
+                segment[network + 10..network + 12].copy_from_slice(&[0, 0]);
+                let sum = checksum::finish(checksum::add(0, &segment[network..transport]));
+                segment[network + 10..network + 12].copy_from_slice(&sum.to_be_bytes());
+            }
+            _ => {
+                segment[network + 4..network + 6]
+                    .copy_from_slice(&(transport_total as u16).to_be_bytes());
+            }
+        }
+
+        let checksum_offset = if tcp {
+            let sequence = u32::from_be_bytes([
+                segment[transport + 4],
+                segment[transport + 5],
+                segment[transport + 6],
+                segment[transport + 7],
+            ])
+            .wrapping_add(sequence_offset);
+            segment[transport + 4..transport + 8].copy_from_slice(&sequence.to_be_bytes());
+            const FIN: u8 = 0x01;
+            const PSH: u8 = 0x08;
+            const CWR: u8 = 0x80;
+            if index + 1 < count {
+                segment[transport + 13] &= !(FIN | PSH);
+            }
+            if index > 0 {
+                segment[transport + 13] &= !CWR;
+            }
+            transport + 16
+        } else {
+            segment[transport + 4..transport + 6]
+                .copy_from_slice(&(transport_total as u16).to_be_bytes());
+            transport + 6
+        };
+
+        let (source, destination) = match version {
+            4 => (network + 12..network + 16, network + 16..network + 20),
+            _ => (network + 8..network + 24, network + 24..network + 40),
+        };
+        segment[checksum_offset..checksum_offset + 2].copy_from_slice(&[0, 0]);
+        let sum = checksum::pseudo_header(
+            &segment[source],
+            &segment[destination],
+            protocol,
+            transport_total,
+        );
+        let mut sum = checksum::finish(checksum::add(sum, &segment[transport..]));
+        if !tcp && sum == 0 {
+            sum = 0xffff;
+        }
+        segment[checksum_offset..checksum_offset + 2].copy_from_slice(&sum.to_be_bytes());
+
+        sequence_offset = sequence_offset.wrapping_add(chunk.len() as u32);
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+/// Parses the IP header at `network`, returning the IP version, the offset
+/// of the transport header and the transport protocol.
+fn parse_ip(payload: &[u8], network: usize) -> Result<(u8, usize, u8)> {
+    match payload.get(network).map(|byte| byte >> 4) {
+        Some(4) => {
+            let length = usize::from(payload[network] & 0x0f) * 4;
+            if length < 20 || payload.len() < network + length {
+                return Err(malformed("truncated IPv4 header"));
+            }
+            Ok((4, network + length, payload[network + 9]))
+        }
+        Some(6) => {
+            if payload.len() < network + 40 {
+                return Err(malformed("truncated IPv6 header"));
+            }
+            Ok((6, network + 40, payload[network + 6]))
+        }
+        Some(_) => Err(malformed("unknown IP version")),
+        None => Err(malformed("truncated packet")),
+    }
+}
+
+/// Returns the offset of the IP header, skipping the Ethernet header of
+/// frames from Tap devices.
+///
+/// A partial checksum tells where the transport header starts, which settles
+/// whether the packet has an Ethernet header. Otherwise the first byte is
+/// expected to carry the IP version.
+fn network_offset(hdr: &VnetHdr, payload: &[u8]) -> Result<usize> {
+    if hdr.flags & VIRTIO_NET_HDR_F_NEEDS_CSUM != 0 {
+        for network in [0, 14] {
+            if let Ok((_, transport, _)) = parse_ip(payload, network) {
+                if transport == usize::from(hdr.csum_start) {
+                    return Ok(network);
+                }
+            }
+        }
+    }
+    match payload.first().map(|byte| byte >> 4) {
+        Some(4) | Some(6) => Ok(0),
+        Some(_) if payload.len() > 14 => match [payload[12], payload[13]] {
+            [0x08, 0x00] | [0x86, 0xdd] => Ok(14),
+            _ => Err(malformed("unknown EtherType")),
+        },
+        _ => Err(malformed("truncated packet")),
+    }
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "checksum")]
+
+use std::io::ErrorKind;
+use utuntap::fix_checksums;
+
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|chunk| u32::from(chunk[0]) << 8 | u32::from(*chunk.get(1).unwrap_or(&0)))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn udp_over_ipv4() -> Vec<u8> {
+    vec![
+        0x45, 0, 0, 31, 0x12, 0x34, 0x40, 0, 64, 17, 0xde, 0xad, 10, 10, 10, 1, 10, 10, 10, 2,
+        0x09, 0x78, 0x10, 0x92, 0, 11, 0xbe, 0xef, 1, 2, 3,
+    ]
+}
+
+#[test]
+fn fixes_ipv4_and_udp_checksums() {
+    let mut packet = udp_over_ipv4();
+    fix_checksums(&mut packet).unwrap();
+    assert_eq!(checksum(&packet[..20]), 0);
+    let mut pseudo = Vec::new();
+    pseudo.extend_from_slice(&packet[12..20]);
+    pseudo.extend_from_slice(&[0, 17, 0, 11]);
+    pseudo.extend_from_slice(&packet[20..]);
+    assert_eq!(checksum(&pseudo), 0);
+}
+
+#[test]
+fn fixes_tcp_over_ipv6_checksum() {
+    let mut packet = vec![0x60, 0, 0, 0, 0, 24, 6, 64];
+    packet.extend_from_slice(&[0xfd; 16]);
+    packet.extend_from_slice(&[0xfe; 16]);
+    packet.extend_from_slice(&[
+        0x09, 0x78, 0x10, 0x92, 0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x18, 0xff, 0xff, 0xaa, 0xbb, 0, 0,
+        1, 2, 3, 4,
+    ]);
+    fix_checksums(&mut packet).unwrap();
+    let mut pseudo = Vec::new();
+    pseudo.extend_from_slice(&packet[8..40]);
+    pseudo.extend_from_slice(&[0, 0, 0, 24, 0, 0, 0, 6]);
+    pseudo.extend_from_slice(&packet[40..]);
+    assert_eq!(checksum(&pseudo), 0);
+}
+
+#[test]
+fn leaves_fragments_and_trailing_bytes_alone() {
+    let mut packet = udp_over_ipv4();
+    packet[6] = 0x20;
+    packet.extend_from_slice(&[0xff; 4]);
+    fix_checksums(&mut packet).unwrap();
+    assert_eq!(checksum(&packet[..20]), 0);
+    assert_eq!(packet[26..28], [0xbe, 0xef]);
+    assert_eq!(packet[31..], [0xff; 4]);
+}
+
+#[test]
+fn rejects_malformed_packets() {
+    for mut packet in [vec![], vec![0x45, 0, 0, 40], vec![0x10; 20], {
+        let mut packet = udp_over_ipv4();
+        packet.truncate(25);
+        packet[2..4].copy_from_slice(&[0, 25]);
+        packet
+    }] {
+        let error = fix_checksums(&mut packet).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,103 @@
+//! Support for tests which create Tun/Tap devices
+//!
+//! Tests run on their own threads, and network namespaces are per thread on
+//! Linux, so every test can move into a fresh namespace where `tun10` is
+//! free and vanishes with the namespace. Without the privileges to do so,
+//! tests fall back to the host namespace and the devices `tests/setup.sh`
+//! prepares.
+
+use std::fs::File;
+use std::net::Ipv4Addr;
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+use utuntap::interface;
+
+/// Keeps the calling thread in a network namespace of its own until dropped.
+pub struct Netns {
+    host: Option<File>,
+}
+
+impl Netns {
+    /// Moves the calling thread into a fresh network namespace, or leaves it
+    /// in the host namespace when it lacks `CAP_SYS_ADMIN`.
+    pub fn enter() -> Self {
+        let host = match File::open("/proc/thread-self/ns/net") {
+            Ok(host) => host,
+            Err(_) => return Self { host: None },
+        };
+        if unsafe { libc::unshare(libc::CLONE_NEWNET) } != 0 {
+            return Self { host: None };
+        }
+        Self { host: Some(host) }
+    }
+
+    /// Moves the calling thread into a fresh network namespace, failing the
+    /// test when it cannot: tests which change the interface or read what
+    /// only the namespace sees have no host fallback.
+    pub fn isolate() -> Self {
+        let netns = Self::enter();
+        assert!(
+            netns.is_isolated(),
+            "the test needs CAP_SYS_ADMIN to enter a network namespace"
+        );
+        netns
+    }
+
+    /// Returns whether the thread left the host namespace.
+    pub fn is_isolated(&self) -> bool {
+        self.host.is_some()
+    }
+
+    /// Brings the interface `name` up with the address `10.10.10.1/24`, as
+    /// `tests/setup.sh` does on the host.
+    ///
+    /// IPv6 is disabled on the interface, so the kernel does not send router
+    /// solicitations which tests would read instead of their own packets.
+    pub fn configure(&self, name: &str) {
+        if !self.is_isolated() {
+            return;
+        }
+        let _ = std::fs::write(
+            format!("/proc/sys/net/ipv6/conf/{}/disable_ipv6", name),
+            "1",
+        );
+        interface::set_address(
+            name,
+            Ipv4Addr::new(10, 10, 10, 1),
+            Ipv4Addr::new(255, 255, 255, 0),
+        )
+        .expect("failed to set address");
+        interface::set_up(name).expect("failed to bring interface up");
+    }
+}
+
+impl Drop for Netns {
+    fn drop(&mut self) {
+        if let Some(host) = &self.host {
+            unsafe { libc::setns(host.as_raw_fd(), libc::CLONE_NEWNET) };
+        }
+    }
+}
+
+/// Fails the test unless `tests/setup.sh`, which `make test` runs, prepared
+/// the interface `name` on the host.
+pub fn require_prepared(name: &str) {
+    assert!(
+        interface::interface_exists(name),
+        "{} is missing; run tests/setup.sh first",
+        name
+    );
+}
+
+/// Waits until the kernel notices the carrier of the interface `name`,
+/// failing the test after two seconds.
+pub fn wait_until_running(name: &str) {
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while !interface::get_flags(name)
+        .expect("failed to read flags")
+        .is_running()
+    {
+        assert!(Instant::now() < deadline, "{} never started running", name);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
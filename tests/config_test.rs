@@ -0,0 +1,45 @@
+use std::io::ErrorKind;
+use utuntap::config::DeviceConfig;
+use utuntap::Mode;
+
+// Every test uses a prefix of its own, as tests share the environment.
+
+#[test]
+fn config_defaults_without_variables() {
+    let config = DeviceConfig::from_env("UTUNTAP_UNSET_").unwrap();
+    assert_eq!(config, DeviceConfig::default());
+}
+
+#[test]
+fn config_is_read_from_variables() {
+    std::env::set_var("UTUNTAP_SET_MODE", "tap");
+    std::env::set_var("UTUNTAP_SET_NAME", "vpn0");
+    std::env::set_var("UTUNTAP_SET_NONBLOCK", "1");
+    std::env::set_var("UTUNTAP_SET_WRITE", "false");
+    let config = DeviceConfig::from_env("UTUNTAP_SET_").unwrap();
+    assert_eq!(
+        config,
+        DeviceConfig {
+            mode: Mode::Tap,
+            name: Some("vpn0".to_string()),
+            nonblock: true,
+            write: false,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn malformed_variables_are_named() {
+    for (prefix, name, value) in [
+        ("UTUNTAP_BAD_MODE_", "MODE", "tum"),
+        ("UTUNTAP_BAD_NUMBER_", "NUMBER", "-1"),
+        ("UTUNTAP_BAD_FLAG_", "PACKET_INFO", "maybe"),
+    ] {
+        let variable = format!("{}{}", prefix, name);
+        std::env::set_var(&variable, value);
+        let error = DeviceConfig::from_env(prefix).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert!(error.to_string().contains(&variable), "{}", error);
+    }
+}
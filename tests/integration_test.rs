@@ -1,21 +1,35 @@
+// The packet tests carried over from the first releases borrow slices twice,
+// assert on constants and leave read and write amounts to the socket checks.
+#![allow(
+    clippy::assertions_on_constants,
+    clippy::needless_borrow,
+    clippy::unused_io_amount
+)]
+
 use etherparse::{IpHeader, PacketBuilder, PacketHeaders, TransportHeader};
 use serial_test::serial;
 #[cfg(target_family = "unix")]
 use std::io::ErrorKind;
 use std::io::{IoSlice, Read, Write};
 use std::net::{IpAddr, Ipv4Addr, UdpSocket};
-#[cfg(not(target_os = "macos"))]
+#[cfg(target_os = "linux")]
+use utuntap::interface::InterfaceFlags;
 use utuntap::tap;
 use utuntap::tun;
 
+#[cfg(target_os = "linux")]
+mod common;
+
 #[cfg(target_os = "linux")]
 #[test]
 #[serial]
 fn tun_sents_packets() {
-    let mut file = tun::OpenOptions::new()
+    let netns = common::Netns::enter();
+    let (mut file, _) = tun::OpenOptions::new()
         .packet_info(false)
         .open(10)
         .expect("failed to open device");
+    netns.configure("tun10");
     let data = [1; 10];
     let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
     socket
@@ -46,10 +60,12 @@ fn tun_sents_packets() {
 #[test]
 #[serial]
 fn tun_sents_packets_with_packet_info() {
-    let mut file = tun::OpenOptions::new()
+    let netns = common::Netns::enter();
+    let (mut file, _) = tun::OpenOptions::new()
         .packet_info(true)
         .open(10)
         .expect("failed to open device");
+    netns.configure("tun10");
     let data = [1; 10];
     let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
     socket
@@ -81,10 +97,12 @@ fn tun_sents_packets_with_packet_info() {
 #[test]
 #[serial]
 fn tun_receives_packets() {
-    let mut file = tun::OpenOptions::new()
+    let netns = common::Netns::enter();
+    let (mut file, _) = tun::OpenOptions::new()
         .packet_info(false)
         .open(10)
         .expect("failed to open device");
+    netns.configure("tun10");
     let data = [1; 10];
     let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
     let builder = PacketBuilder::ipv4([10, 10, 10, 2], [10, 10, 10, 1], 20).udp(4242, 2424);
@@ -110,10 +128,12 @@ fn tun_receives_packets() {
 #[test]
 #[serial]
 fn tun_receives_packets_with_packet_info() {
-    let mut file = tun::OpenOptions::new()
+    let netns = common::Netns::enter();
+    let (mut file, _) = tun::OpenOptions::new()
         .packet_info(true)
         .open(10)
         .expect("failed to open device");
+    netns.configure("tun10");
     let data = [1; 10];
     let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
     let builder = PacketBuilder::ipv4([10, 10, 10, 2], [10, 10, 10, 1], 20).udp(4242, 2424);
@@ -141,7 +161,7 @@ fn tun_receives_packets_with_packet_info() {
 #[test]
 #[serial]
 fn tun_sents_packets() {
-    let mut file = tun::OpenOptions::new()
+    let (mut file, _) = tun::OpenOptions::new()
         .open(10)
         .expect("failed to open device");
     let data = [1; 10];
@@ -175,7 +195,7 @@ fn tun_sents_packets() {
 #[test]
 #[serial]
 fn tun_receives_packets() {
-    let mut file = tun::OpenOptions::new()
+    let (mut file, _) = tun::OpenOptions::new()
         .open(10)
         .expect("failed to open device");
     let data = [1; 10];
@@ -201,11 +221,67 @@ fn tun_receives_packets() {
     assert_eq!(data, &buffer[..number]);
 }
 
+#[cfg(target_os = "macos")]
+#[test]
+fn tun_rejects_out_of_range_number() {
+    let error = tun::OpenOptions::new()
+        .open(u32::MAX)
+        .expect_err("out of range number should not open");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn tun_rejects_too_long_control_name() {
+    let error = tun::OpenOptions::new()
+        .control_name(&"a".repeat(96))
+        .open(0)
+        .expect_err("too long control name should not open");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn tun_opens_ephemeral_device() {
+    let _netns = common::Netns::enter();
+    let (_file, filename) = tun::OpenOptions::new()
+        .ephemeral()
+        .open(None)
+        .expect("failed to open device");
+    let number = filename
+        .strip_prefix("tun")
+        .expect("unexpected device name");
+    assert!(!number.is_empty() && number.bytes().all(|byte| byte.is_ascii_digit()));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn tun_rejects_too_long_name() {
+    let error = tun::OpenOptions::new()
+        .name("a_very_long_tun_name")
+        .open(None)
+        .expect_err("too long name should not open");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+#[serial]
+fn tun_resolves_assigned_unit() {
+    let (_file, filename) = tun::OpenOptions::new()
+        .open(None)
+        .expect("failed to open device");
+    let unit = filename
+        .strip_prefix("utun")
+        .expect("unexpected device name");
+    assert!(unit.parse::<u32>().is_ok());
+}
+
 #[cfg(target_os = "macos")]
 #[test]
 #[serial]
 fn tun_sents_packets() {
-    let mut file = tun::OpenOptions::new()
+    let (mut file, _) = tun::OpenOptions::new()
         .open(10)
         .expect("failed to open device");
 
@@ -249,7 +325,7 @@ fn tun_sents_packets() {
 #[test]
 #[serial]
 fn tun_receives_packets() {
-    let mut file = tun::OpenOptions::new()
+    let (mut file, _) = tun::OpenOptions::new()
         .open(10)
         .expect("failed to open device");
 
@@ -289,7 +365,7 @@ fn tun_receives_packets() {
 #[test]
 #[serial]
 fn tun_non_blocking_io() {
-    let mut file = tun::OpenOptions::new()
+    let (mut file, _) = tun::OpenOptions::new()
         .nonblock(true)
         .open(11)
         .expect("failed to open device");
@@ -299,11 +375,202 @@ fn tun_non_blocking_io() {
     assert_eq!(error.kind(), ErrorKind::WouldBlock);
 }
 
+#[cfg(target_family = "unix")]
+#[test]
+#[serial]
+fn tun_times_out_reads() {
+    use std::time::{Duration, Instant};
+    use utuntap::BlockingMode;
+
+    let mut device = tun::OpenOptions::new()
+        .blocking_mode(BlockingMode::Timeout(Duration::from_millis(50)))
+        .open_device(11)
+        .expect("failed to open device");
+    assert_eq!(device.read_timeout(), Some(Duration::from_millis(50)));
+    assert!(!device.is_nonblocking().unwrap());
+    let mut buffer = [0; 1500];
+    let start = Instant::now();
+    while device.read(&mut buffer).is_ok() {}
+    let error = device.read(&mut buffer).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    drop(device);
+
+    let mut options = tun::OpenOptions::new();
+    options.blocking_mode(BlockingMode::Timeout(Duration::ZERO));
+    let error = options.open_device(11).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    let device = options
+        .blocking_mode(BlockingMode::NonBlocking)
+        .open_device(11)
+        .expect("failed to open device");
+    assert!(device.is_nonblocking().unwrap());
+    assert_eq!(device.read_timeout(), None);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_splits_into_reader_and_writer() {
+    use std::time::Duration;
+
+    let netns = common::Netns::isolate();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(false)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .expect("failed to set timeout");
+    socket
+        .send_to(b"ping", "10.10.10.2:4242")
+        .expect("failed to send data");
+
+    let (mut reader, mut writer) = device.split_mut();
+    let mut buffer = [0; 1500];
+    let number = reader.read(&mut buffer).expect("failed to read packet");
+    // Swapping the addresses and the ports keeps both checksums valid.
+    let (source, destination) = buffer[12..20].split_at_mut(4);
+    source.swap_with_slice(destination);
+    let (source, destination) = buffer[20..24].split_at_mut(2);
+    source.swap_with_slice(destination);
+    writer
+        .write_all(&buffer[..number])
+        .expect("failed to write packet");
+
+    let mut received = [0; 10];
+    let (number, address) = socket.recv_from(&mut received).expect("failed to receive");
+    assert_eq!(&received[..number], b"ping");
+    assert_eq!(address, "10.10.10.2:4242".parse().unwrap());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_opens_separate_reader_and_writer() {
+    use std::time::Duration;
+
+    let netns = common::Netns::isolate();
+    let (reader, mut writer, name) = tun::OpenOptions::new()
+        .packet_info(false)
+        .open_rw_split(12)
+        .expect("failed to open device");
+    assert_eq!(name, "tun12");
+    netns.configure(&name);
+    let mut reader = utuntap::Device::from_owned_fd(reader.into(), name);
+    reader
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .expect("failed to set timeout");
+
+    // Every flow reaches the reader, none the detached queue of the writer.
+    let sockets: Vec<_> = (2424..2432)
+        .map(|port| {
+            let socket = UdpSocket::bind(("10.10.10.1", port)).expect("failed to bind");
+            socket
+                .set_read_timeout(Some(Duration::from_secs(1)))
+                .expect("failed to set timeout");
+            socket
+                .send_to(b"ping", "10.10.10.2:4242")
+                .expect("failed to send data");
+            socket
+        })
+        .collect();
+    for _ in &sockets {
+        let mut buffer = [0; 1500];
+        let number = reader.read(&mut buffer).expect("failed to read packet");
+        let (source, destination) = buffer[12..20].split_at_mut(4);
+        source.swap_with_slice(destination);
+        let (source, destination) = buffer[20..24].split_at_mut(2);
+        source.swap_with_slice(destination);
+        writer
+            .write_all(&buffer[..number])
+            .expect("failed to write packet");
+    }
+    for socket in &sockets {
+        let mut received = [0; 10];
+        let (number, _) = socket.recv_from(&mut received).expect("failed to receive");
+        assert_eq!(&received[..number], b"ping");
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_guards_packet_info_on_write() {
+    let netns = common::Netns::isolate();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(true)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let packet = [
+        0x45, 0, 0, 20, 0, 0, 0, 0, 64, 17, 0, 0, 10, 10, 10, 2, 10, 10, 10, 1,
+    ];
+    let error = device.write_guarded(&packet).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    let error = device.write_guarded(&[0, 0, 8]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+    let mut buffer = vec![0, 0, 0x08, 0x00];
+    buffer.extend_from_slice(&packet);
+    let number = device
+        .write_guarded(&buffer)
+        .expect("failed to write packet");
+    assert_eq!(number, buffer.len());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_sets_signal_driven_io() {
+    use std::os::unix::io::AsRawFd;
+
+    let device = tun::OpenOptions::new()
+        .signal_driven(None)
+        .open_device(11)
+        .expect("failed to open device");
+    let fd = device.as_raw_fd();
+    assert_ne!(unsafe { libc::fcntl(fd, libc::F_GETFL) } & libc::O_ASYNC, 0);
+    assert_eq!(unsafe { libc::fcntl(fd, libc::F_GETOWN) }, unsafe {
+        libc::getpid()
+    });
+    assert!(!device.is_nonblocking().unwrap());
+
+    let group = unsafe { libc::getpgrp() };
+    device
+        .set_signal_driven(Some(-group))
+        .expect("failed to set owner");
+    assert_eq!(unsafe { libc::fcntl(fd, libc::F_GETOWN) }, -group);
+
+    device
+        .clear_signal_driven()
+        .expect("failed to turn signals off");
+    assert_eq!(unsafe { libc::fcntl(fd, libc::F_GETFL) } & libc::O_ASYNC, 0);
+    device
+        .set_signal_driven(None)
+        .expect("failed to turn signals on");
+    assert_ne!(unsafe { libc::fcntl(fd, libc::F_GETFL) } & libc::O_ASYNC, 0);
+    assert_eq!(unsafe { libc::fcntl(fd, libc::F_GETOWN) }, unsafe {
+        libc::getpid()
+    });
+    drop(device);
+
+    let device = tun::OpenOptions::new()
+        .signal_driven(None)
+        .clear_signal_driven()
+        .open_device(11)
+        .expect("failed to open device");
+    let flags = unsafe { libc::fcntl(device.as_raw_fd(), libc::F_GETFL) };
+    assert_eq!(flags & libc::O_ASYNC, 0);
+}
+
 #[cfg(all(target_family = "unix", not(target_os = "macos")))]
 #[test]
 #[serial]
 fn tap_non_blocking_io() {
-    let mut file = tap::OpenOptions::new()
+    let (mut file, _) = tap::OpenOptions::new()
         .nonblock(true)
         .open(11)
         .expect("failed to open device");
@@ -312,3 +579,2070 @@ fn tap_non_blocking_io() {
     let error = file.read(&mut buffer).err().unwrap();
     assert_eq!(error.kind(), ErrorKind::WouldBlock);
 }
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_writes_batch() {
+    let netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .nonblock(true)
+        .packet_info(false)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let packet = [
+        0x45, 0, 0, 20, 0, 0, 0, 0, 64, 17, 0, 0, 10, 10, 10, 2, 10, 10, 10, 1,
+    ];
+    assert_eq!(device.write_batch(&[]).expect("failed to write batch"), 0);
+    let written = device
+        .write_batch(&[&packet, &packet])
+        .expect("failed to write batch");
+    assert_eq!(written, 2);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_registers_with_epoll() {
+    let netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(false)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+    assert!(epfd >= 0);
+    device
+        .register_epoll(epfd, libc::EPOLLIN as u32, 7)
+        .expect("failed to register device");
+    assert!(device.is_nonblocking().expect("failed to read mode"));
+    device
+        .modify_epoll(epfd, (libc::EPOLLIN | libc::EPOLLOUT) as u32, 8)
+        .expect("failed to modify registration");
+
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(&[1; 10], "10.10.10.2:4242")
+        .expect("failed to send data");
+    let mut events = [libc::epoll_event { events: 0, u64: 0 }; 4];
+    let number = unsafe { libc::epoll_wait(epfd, events.as_mut_ptr(), 4, 5000) };
+    assert_eq!(number, 1);
+    let (token, ready) = (events[0].u64, events[0].events);
+    assert_eq!(token, 8);
+    assert_ne!(ready & libc::EPOLLIN as u32, 0);
+    let mut buffer = [0; 50];
+    assert_eq!(
+        device.read(&mut buffer).expect("failed to receive data"),
+        38
+    );
+
+    device
+        .deregister_epoll(epfd)
+        .expect("failed to deregister device");
+    let number = unsafe { libc::epoll_wait(epfd, events.as_mut_ptr(), 4, 0) };
+    assert_eq!(number, 0);
+    unsafe { libc::close(epfd) };
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_writes_ip_packets_with_packet_info() {
+    let netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(true)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(&[1; 10], "10.10.10.2:4242")
+        .expect("failed to send data");
+    let mut buffer = [0; 50];
+    let number = device.read(&mut buffer).expect("failed to receive data");
+    assert_eq!(number, 42);
+
+    // Send the datagram back to the socket.
+    let mut packet = buffer[4..number].to_vec();
+    packet[12..16].copy_from_slice(&[10, 10, 10, 2]);
+    packet[16..20].copy_from_slice(&[10, 10, 10, 1]);
+    packet[20..22].copy_from_slice(&4242u16.to_be_bytes());
+    packet[22..24].copy_from_slice(&2424u16.to_be_bytes());
+    packet[26..28].copy_from_slice(&[0, 0]);
+    let written = device.write_ip(&packet).expect("failed to send data");
+    assert_eq!(written, packet.len());
+    socket
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .expect("failed to set timeout");
+    let (number, source) = socket
+        .recv_from(&mut buffer)
+        .expect("the packet should not be dropped");
+    assert_eq!(number, 10);
+    assert_eq!(source, "10.10.10.2:4242".parse().unwrap());
+
+    let error = device
+        .write_ip(&[0x15, 0, 0, 0])
+        .expect_err("a non-IP packet should not be written");
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_writes_whole_packets() {
+    let netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(false)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let packet = [
+        0x45, 0, 0, 20, 0, 0, 0, 0, 64, 17, 0, 0, 10, 10, 10, 2, 10, 10, 10, 1,
+    ];
+    device
+        .write_all_packet(&packet)
+        .expect("failed to write packet");
+    let error = device
+        .write_all_packet(&[])
+        .expect_err("an empty packet should not be written");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_strips_protocol_info() {
+    let netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(true)
+        .strip_protocol_info(true)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(&[1; 10], "10.10.10.2:4242")
+        .expect("failed to send data");
+    let mut buffer = [0; 50];
+    let number = device.read(&mut buffer).expect("failed to receive data");
+    assert_eq!(number, 38);
+    assert_eq!(buffer[0] >> 4, 4);
+    let written = device
+        .write(&buffer[..number])
+        .expect("failed to send data");
+    assert_eq!(written, number);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_strips_protocol_info_vectored() {
+    use std::io::IoSliceMut;
+
+    let netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(true)
+        .strip_protocol_info(true)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(&[1; 10], "10.10.10.2:4242")
+        .expect("failed to send data");
+    let (mut ip_header, mut datagram) = ([0; 20], [0; 30]);
+    let number = device
+        .read_vectored(&mut [
+            IoSliceMut::new(&mut ip_header),
+            IoSliceMut::new(&mut datagram),
+        ])
+        .expect("failed to receive data");
+    assert_eq!(number, 38);
+    assert_eq!(ip_header[0], 0x45);
+    assert_eq!(datagram[..2], 2424u16.to_be_bytes());
+    assert_eq!(datagram[8..18], [1; 10]);
+
+    let builder = PacketBuilder::ipv4([10, 10, 10, 2], [10, 10, 10, 1], 20).udp(4242, 2424);
+    let mut packet = Vec::new();
+    builder
+        .write(&mut packet, &[2; 10])
+        .expect("failed to build packet");
+    let (ip_header, datagram) = packet.split_at(20);
+    let written = device
+        .write_vectored(&[IoSlice::new(ip_header), IoSlice::new(datagram)])
+        .expect("failed to send packet");
+    assert_eq!(written, packet.len());
+    let mut buffer = [0; 50];
+    let (number, _) = socket
+        .recv_from(&mut buffer)
+        .expect("failed to receive data");
+    assert_eq!(buffer[..number], [2; 10]);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn device_recommends_buffer_size() {
+    let _netns = common::Netns::enter();
+    let tun = tun::OpenOptions::new()
+        .packet_info(true)
+        .open_device(10)
+        .expect("failed to open device");
+    let size = tun
+        .recommended_buffer_size()
+        .expect("failed to compute size");
+    assert_eq!(size, 1500 + 4);
+    let tap = tap::OpenOptions::new()
+        .packet_info(false)
+        .vnet_hdr(true)
+        .ephemeral()
+        .open_device(None)
+        .expect("failed to open device");
+    let size = tap
+        .recommended_buffer_size()
+        .expect("failed to compute size");
+    assert_eq!(size, 1500 + 14 + 10);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reports_missing_timestamps() {
+    let netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(false)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(&[1; 10], "10.10.10.2:4242")
+        .expect("failed to send data");
+    let mut buffer = [0; 50];
+    let (number, timestamp) = device
+        .read_timestamped(&mut buffer)
+        .expect("failed to receive data");
+    assert_eq!(number, 38);
+    assert_eq!(timestamp, None);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reads_prefixed_packets() {
+    let netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(true)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(&[1; 10], "10.10.10.2:4242")
+        .expect("failed to send data");
+    let mut prefix = [0; 4];
+    let mut payload = [0; 50];
+    let lengths = device
+        .read_prefixed(&mut prefix, &mut payload)
+        .expect("failed to receive data");
+    assert_eq!(lengths, (4, 38));
+    assert_eq!(prefix, [0, 0, 0x08, 0x00]);
+    assert_eq!(payload[0] >> 4, 4);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_opens_into_name_buffer() {
+    use std::os::unix::io::AsRawFd;
+
+    let _netns = common::Netns::enter();
+    let mut name = [0; 16];
+    let (file, len) = tun::OpenOptions::new()
+        .signal_driven(None)
+        .open_into(10, &mut name)
+        .expect("failed to open device");
+    assert_eq!(&name[..len], b"tun10");
+    let flags = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETFL) };
+    assert_ne!(flags & libc::O_ASYNC, 0);
+    let error = tun::OpenOptions::new()
+        .open_into(11, &mut [0; 4])
+        .expect_err("short buffer should not open");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn device_opens_with_packet_info_flags() {
+    use std::io::Read;
+    use std::os::unix::io::AsRawFd;
+    use utuntap::{ioctl, Mode, OpenOptions};
+
+    const IFF_TUN: libc::c_short = 0x0001;
+    const IFF_TAP: libc::c_short = 0x0002;
+
+    let netns = common::Netns::isolate();
+    for (mode, mode_flag) in [(Mode::Tun, IFF_TUN), (Mode::Tap, IFF_TAP)] {
+        for no_packet_info in [false, true] {
+            let (mut file, name) = OpenOptions::new(mode)
+                .no_packet_info(no_packet_info)
+                .open(10)
+                .expect("failed to open device");
+            // TUNGETIFF reports IFF_NOFILTER in place of IFF_NO_PI, so only
+            // the mode is checked there and packet info on the wire.
+            let mut ifreq: libc::ifreq = unsafe { std::mem::zeroed() };
+            unsafe { ioctl::tun_ioctl(file.as_raw_fd(), ioctl::TUNGETIFF, &mut ifreq) }
+                .expect("failed to read flags");
+            let flags = unsafe { ifreq.ifr_ifru.ifru_flags };
+            assert_eq!(flags & (IFF_TUN | IFF_TAP), mode_flag);
+
+            netns.configure(&name);
+            while !utuntap::interface::get_flags(&name)
+                .expect("failed to read flags")
+                .is_running()
+            {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+            socket
+                .send_to(&[1; 10], "10.10.10.2:4242")
+                .expect("failed to send data");
+            let mut buffer = [0; 100];
+            file.read(&mut buffer).expect("failed to receive data");
+            // An IPv4 packet on Tun, an ARP request for 10.10.10.2 on Tap.
+            let expected: &[u8] = match (mode, no_packet_info) {
+                (Mode::Tun, false) => &[0, 0, 0x08, 0x00, 0x45],
+                (Mode::Tun, true) => &[0x45],
+                (Mode::Tap, false) => &[0, 0, 0x08, 0x06, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+                (Mode::Tap, true) => &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            };
+            assert_eq!(&buffer[..expected.len()], expected);
+            let device = utuntap::Device::from_owned_fd(file.into(), name);
+            assert_eq!(device.mode().expect("failed to read mode"), mode);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_sets_steering_ebpf() {
+    use std::os::unix::io::AsRawFd;
+
+    let _netns = common::Netns::enter();
+    let device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    tun::set_steering_ebpf(device.file(), -1).expect("failed to detach program");
+    let error = tun::set_steering_ebpf(device.file(), device.as_raw_fd())
+        .expect_err("a device file should not be accepted as a program");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_sets_debug() {
+    let _netns = common::Netns::enter();
+    let device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    tun::set_debug(device.file(), true).expect("failed to enable debugging");
+    tun::set_debug(device.file(), false).expect("failed to disable debugging");
+    // The file is not attached to an interface yet.
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/net/tun")
+        .expect("failed to open /dev/net/tun");
+    let error = tun::set_debug(&file, true).unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::EBADFD));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tap_sets_link_type() {
+    use std::os::unix::io::AsRawFd;
+
+    let _netns = common::Netns::isolate();
+    let device = tap::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    // sysfs shows the interfaces of the host namespace, so ask the kernel.
+    let link_type = || {
+        let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let mut request = utuntap::ioctl::IfReq::new("tap10").unwrap();
+        unsafe {
+            utuntap::ioctl::ifreq_ioctl(socket.as_raw_fd(), libc::SIOCGIFHWADDR as _, &mut request)
+                .expect("failed to read hardware address");
+            request.ifreq().ifr_ifru.ifru_hwaddr.sa_family
+        }
+    };
+    assert_eq!(link_type(), 1);
+    tun::set_link_type(device.file(), tun::ARPHRD_NONE).expect("failed to set link type");
+    assert_eq!(link_type(), 0xfffe);
+
+    device.set_up().expect("failed to bring interface up");
+    let error = tun::set_link_type(device.file(), tun::ARPHRD_ETHER).unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::EBUSY));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_opens_with_mtu() {
+    let _netns = common::Netns::enter();
+    let _device = tun::OpenOptions::new()
+        .mtu(1280)
+        .open_device(10)
+        .expect("failed to open device");
+    let mtu = utuntap::interface::get_mtu("tun10").expect("failed to read mtu");
+    assert_eq!(mtu, 1280);
+
+    let error = tun::OpenOptions::new()
+        .mtu(0)
+        .open_device(11)
+        .expect_err("an invalid mtu should not open");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    let error = utuntap::interface::get_mtu("tun11").expect_err("interface should be closed");
+    assert_eq!(error.raw_os_error(), Some(libc::ENODEV));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reports_mtu() {
+    let _netns = common::Netns::enter();
+    let _device = tun::OpenOptions::new()
+        .open(10)
+        .expect("failed to open device");
+    let mtu = utuntap::interface::get_mtu("tun10").expect("failed to read mtu");
+    assert_eq!(mtu, 1500);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_opens_in_network_namespace() {
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+
+    let _netns = common::Netns::isolate();
+    let target = std::fs::File::open("/proc/thread-self/ns/net").expect("failed to open netns");
+    assert_eq!(unsafe { libc::unshare(libc::CLONE_NEWNET) }, 0);
+    let caller = std::fs::metadata("/proc/thread-self/ns/net").expect("failed to stat netns");
+
+    let path = format!("/proc/self/fd/{}", target.as_raw_fd());
+    let (_file, filename) = tun::OpenOptions::new()
+        .open_in_netns(std::path::Path::new(&path), 10)
+        .expect("failed to open device");
+    assert_eq!(filename, "tun10");
+    let current = std::fs::metadata("/proc/thread-self/ns/net").expect("failed to stat netns");
+    assert_eq!(current.ino(), caller.ino());
+    let error = utuntap::interface::get_mtu("tun10")
+        .expect_err("interface should not be in the caller's namespace");
+    assert_eq!(error.raw_os_error(), Some(libc::ENODEV));
+
+    let error = tun::OpenOptions::new()
+        .open_in_netns(std::path::Path::new("/nonexistent"), 10)
+        .expect_err("missing namespace should not open");
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+
+    assert_eq!(
+        unsafe { libc::setns(target.as_raw_fd(), libc::CLONE_NEWNET) },
+        0
+    );
+    utuntap::interface::get_mtu("tun10").expect("interface should be in the target namespace");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_issues_custom_interface_ioctls() {
+    use std::os::unix::io::AsRawFd;
+    use utuntap::ioctl::{self, IfReq};
+
+    let _netns = common::Netns::enter();
+    let _device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    let socket = UdpSocket::bind("0.0.0.0:0").expect("failed to open socket");
+    let mut request = IfReq::new("tun10").expect("failed to build request");
+    unsafe { ioctl::ifreq_ioctl(socket.as_raw_fd(), libc::SIOCGIFMTU, &mut request) }
+        .expect("failed to read mtu");
+    assert_eq!(unsafe { request.ifreq().ifr_ifru.ifru_mtu }, 1500);
+    assert_eq!(request.name().expect("failed to read name"), "tun10");
+    let error = IfReq::new("a-name-far-too-long")
+        .map(drop)
+        .expect_err("long names should be rejected");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn device_switches_nonblocking_mode() {
+    let _netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    assert!(!device.is_nonblocking().expect("failed to read mode"));
+    device
+        .set_nonblocking(true)
+        .expect("failed to switch to non-blocking mode");
+    assert!(device.is_nonblocking().expect("failed to read mode"));
+    let error = device.read(&mut [0; 1500]).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+    device
+        .set_nonblocking(false)
+        .expect("failed to switch to blocking mode");
+    assert!(!device.is_nonblocking().expect("failed to read mode"));
+}
+
+#[cfg(any(target_os = "macos", target_os = "openbsd"))]
+#[test]
+#[serial]
+fn device_switches_nonblocking_mode() {
+    let mut device = tun::OpenOptions::new()
+        .nonblock(true)
+        .open_device(10)
+        .expect("failed to open device");
+    assert!(device.is_nonblocking().expect("failed to read mode"));
+    device
+        .set_nonblocking(false)
+        .expect("failed to switch to blocking mode");
+    assert!(!device.is_nonblocking().expect("failed to read mode"));
+    device
+        .set_nonblocking(true)
+        .expect("failed to switch to non-blocking mode");
+    let mut buffer = [0; 1500];
+    while device.read(&mut buffer).is_ok() {}
+    let error = device.read(&mut buffer).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+}
+
+#[cfg(target_os = "openbsd")]
+#[test]
+#[serial]
+fn tun_opens_clone_device_without_number() {
+    let (_file, name) = tun::OpenOptions::new()
+        .open(None)
+        .expect("failed to open device");
+    let number = name.strip_prefix("tun").expect("unexpected device name");
+    assert!(number.parse::<u32>().is_ok(), "{}", name);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn device_sets_mtu_and_flags() {
+    let _netns = common::Netns::enter();
+    let device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    device.set_mtu(1420).expect("failed to set mtu");
+    assert_eq!(device.mtu().expect("failed to read mtu"), 1420);
+    device.set_up().expect("failed to bring device up");
+    assert!(device.flags().expect("failed to read flags").is_up());
+    device.set_down().expect("failed to bring device down");
+    assert!(!device.flags().expect("failed to read flags").is_up());
+    let flags = device.flags().expect("failed to read flags");
+    device
+        .set_flags(flags | InterfaceFlags::PROMISC)
+        .expect("failed to set flags");
+    let promisc = device.flags().expect("failed to read flags");
+    assert!(promisc.is_promisc());
+    assert_eq!(promisc & !InterfaceFlags::PROMISC, flags);
+    device.set_flags(flags).expect("failed to restore flags");
+    // Leave the device as tests/setup.sh prepared it on the host.
+    device.set_mtu(1500).expect("failed to restore mtu");
+    device.set_up().expect("failed to bring device up");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn device_is_provisioned_in_one_step() {
+    use std::net::Ipv4Addr;
+    use utuntap::config::{provision, DeviceConfig, DeviceSpec};
+    use utuntap::interface::get_mtu;
+
+    let _netns = common::Netns::isolate();
+    let mut spec = DeviceSpec {
+        config: DeviceConfig {
+            name: Some("prov-test".to_string()),
+            ..Default::default()
+        },
+        owner: Some(0),
+        mtu: Some(1400),
+        address: Some((
+            Ipv4Addr::new(10, 10, 10, 1),
+            Ipv4Addr::new(255, 255, 255, 0),
+        )),
+        up: true,
+        ..Default::default()
+    };
+    let device = provision(&spec).expect("failed to provision device");
+    assert_eq!(get_mtu("prov-test").unwrap(), 1400);
+    assert!(device.flags().unwrap().is_up());
+    UdpSocket::bind("10.10.10.1:0").expect("address was not assigned");
+    drop(device);
+
+    // A failing step removes the interface even though it was persistent.
+    spec.persist = true;
+    spec.mtu = Some(10);
+    let error = provision(&spec).unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::EINVAL));
+    let error = get_mtu("prov-test").unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::ENODEV));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_adds_secondary_addresses() {
+    use std::net::Ipv4Addr;
+    use utuntap::interface::add_address;
+
+    let netns = common::Netns::isolate();
+    let device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure(device.name());
+    let netmask = Ipv4Addr::new(255, 255, 255, 0);
+    add_address(device.name(), Ipv4Addr::new(10, 10, 10, 2), netmask)
+        .expect("failed to add address");
+    add_address(device.name(), Ipv4Addr::new(10, 10, 20, 1), netmask)
+        .expect("failed to add address");
+    for address in ["10.10.10.1:0", "10.10.10.2:0", "10.10.20.1:0"] {
+        UdpSocket::bind(address).expect("address was not kept");
+    }
+
+    let error = add_address(device.name(), Ipv4Addr::new(10, 10, 20, 1), netmask).unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::EEXIST));
+    let netmask = Ipv4Addr::new(255, 0, 255, 0);
+    let error = add_address(device.name(), Ipv4Addr::new(10, 10, 30, 1), netmask).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_lists_addresses() {
+    use std::net::{IpAddr, Ipv4Addr};
+    use utuntap::interface::{add_address, get_addresses};
+
+    let netns = common::Netns::isolate();
+    let device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    assert_eq!(get_addresses(device.name()).unwrap(), Vec::new());
+    netns.configure(device.name());
+    add_address(
+        device.name(),
+        Ipv4Addr::new(10, 20, 0, 1),
+        Ipv4Addr::new(255, 255, 0, 0),
+    )
+    .expect("failed to add address");
+    let addresses = get_addresses(device.name()).expect("failed to list addresses");
+    assert!(addresses.contains(&(IpAddr::V4(Ipv4Addr::new(10, 10, 10, 1)), 24)));
+    assert!(addresses.contains(&(IpAddr::V4(Ipv4Addr::new(10, 20, 0, 1)), 16)));
+
+    let error = get_addresses("tun99").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn configurator_reuses_one_socket() {
+    use utuntap::interface::InterfaceConfigurator;
+
+    let _netns = common::Netns::enter();
+    let device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    let configurator = InterfaceConfigurator::new().expect("failed to open socket");
+    configurator
+        .set_mtu(device.name(), 1400)
+        .expect("failed to set mtu");
+    assert_eq!(configurator.get_mtu(device.name()).unwrap(), 1400);
+    configurator
+        .set_down(device.name())
+        .expect("failed to bring device down");
+    assert!(!configurator.get_flags(device.name()).unwrap().is_up());
+    configurator
+        .set_up(device.name())
+        .expect("failed to bring device up");
+    assert!(configurator.get_flags(device.name()).unwrap().is_up());
+    let error = configurator.get_mtu("missing0").unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::ENODEV));
+    // Leave the device as tests/setup.sh prepared it on the host.
+    configurator
+        .set_mtu(device.name(), 1500)
+        .expect("failed to restore mtu");
+}
+
+#[cfg(all(target_os = "linux", feature = "smoltcp"))]
+#[test]
+#[serial]
+fn tun_reports_smoltcp_capabilities() {
+    use smoltcp::phy::{Device, Medium};
+    use utuntap::phy::Phy;
+
+    let _netns = common::Netns::enter();
+    let device = tun::OpenOptions::new()
+        .nonblock(true)
+        .open_device(10)
+        .expect("failed to open device");
+    let phy = Phy::new(device, utuntap::Mode::Tun).expect("failed to wrap device");
+    let capabilities = phy.capabilities();
+    assert_eq!(capabilities.medium, Medium::Ip);
+    assert_eq!(capabilities.max_transmission_unit, 1500);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_lists_devices() {
+    // Interfaces come from sysfs, which only shows the host namespace.
+    let _device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    let names = utuntap::interface::list_devices().expect("failed to list devices");
+    assert!(names.iter().any(|name| name == "tun10"));
+    assert!(!names.iter().any(|name| name == "lo"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_finds_next_free_number() {
+    use utuntap::{interface::next_free_number, Mode};
+
+    // Interfaces come from sysfs, which only shows the host namespace.
+    let number = next_free_number(Mode::Tun).expect("failed to find a free number");
+    assert!(!std::path::Path::new(&format!("/sys/class/net/tun{}", number)).exists());
+    let _device = tun::OpenOptions::new()
+        .open_device(number)
+        .expect("failed to open device");
+    let next = next_free_number(Mode::Tun).expect("failed to find a free number");
+    assert_ne!(next, number);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_opens_read_only() {
+    use std::os::unix::io::AsRawFd;
+
+    let _netns = common::Netns::enter();
+    let (mut file, _) = tun::OpenOptions::new()
+        .write(false)
+        .open(10)
+        .expect("failed to open device");
+    let error = file.write(&[0, 0, 8, 0, 0x45]).unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::EBADF));
+    let mode = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETFL) };
+    assert_eq!(mode & libc::O_ACCMODE, libc::O_RDONLY);
+
+    let error = tun::OpenOptions::new()
+        .read(false)
+        .write(false)
+        .open(10)
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn device_flushes_without_syncing() {
+    use std::os::unix::io::AsRawFd;
+
+    let _netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    // The device has no fsync, so flushing must not reach the kernel.
+    let result = unsafe { libc::fsync(device.as_raw_fd()) };
+    assert_eq!(result, -1);
+    assert_eq!(
+        std::io::Error::last_os_error().raw_os_error(),
+        Some(libc::EINVAL)
+    );
+    device.flush().expect("failed to flush device");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reads_one_packet_per_call() {
+    let netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(false)
+        .nonblock(true)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    for size in [10, 20, 30] {
+        socket
+            .send_to(&vec![1; size], "10.10.10.2:4242")
+            .expect("failed to send data");
+    }
+    let mut buffer = [0; 1500];
+    assert_eq!(device.read(&mut buffer).expect("failed to read"), 38);
+    assert_eq!(device.read(&mut buffer).expect("failed to read"), 48);
+    // A short buffer gets the start of the packet, the rest is dropped.
+    assert_eq!(device.read(&mut buffer[..20]).expect("failed to read"), 20);
+    let error = device.read(&mut buffer).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn device_checks_writes_against_mtu() {
+    let _netns = common::Netns::isolate();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(false)
+        .open_device(10)
+        .expect("failed to open device");
+    device.set_mtu(1280).expect("failed to set MTU");
+    device.set_up().expect("failed to bring interface up");
+    let error = device.write_checked(&[0x45; 1281]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert_eq!(device.write_checked(&[0x45; 1280]).unwrap(), 1280);
+    drop(device);
+
+    // The packet info and Ethernet header come on top of the MTU.
+    let mut device = tap::OpenOptions::new()
+        .packet_info(true)
+        .open_device(10)
+        .expect("failed to open device");
+    assert_eq!(device.mtu().unwrap(), 1500);
+    device.set_up().expect("failed to bring interface up");
+    let error = device.write_checked(&[0; 1523]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert_eq!(device.write_checked(&[0; 1522]).unwrap(), 1522);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_cannot_peek() {
+    let netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(false)
+        .nonblock(true)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(&[1; 10], "10.10.10.2:4242")
+        .expect("failed to send data");
+    let mut buffer = [0; 1500];
+    let error = device.peek(&mut buffer).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+    // The packet is still queued.
+    assert_eq!(device.read(&mut buffer).expect("failed to read"), 38);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_pauses_and_resumes() {
+    let netns = common::Netns::isolate();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(false)
+        .nonblock(true)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(&[1; 10], "10.10.10.2:4242")
+        .expect("failed to send data");
+    device.pause().expect("failed to pause");
+    assert!(!device.flags().expect("failed to read flags").is_up());
+    // Packets queued before pausing are still read.
+    let mut buffer = [0; 50];
+    assert_eq!(device.read(&mut buffer).expect("failed to drain"), 38);
+    assert!(socket.send_to(&[1; 10], "10.10.10.2:4242").is_err());
+    let error = device.read(&mut buffer).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+    device.resume().expect("failed to resume");
+    assert!(device.flags().expect("failed to read flags").is_up());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tap_joins_bridge() {
+    use std::ffi::CString;
+    use std::os::unix::io::AsRawFd;
+    use utuntap::interface::{add_to_bridge, remove_from_bridge};
+
+    const SIOCBRADDBR: libc::c_ulong = 0x89a0;
+    const SIOCBRDELBR: libc::c_ulong = 0x89a1;
+
+    let _netns = common::Netns::isolate();
+    let socket = UdpSocket::bind("0.0.0.0:0").expect("failed to open socket");
+    let bridge = CString::new("test-br").unwrap();
+    let bridge_ioctl =
+        |request| unsafe { libc::ioctl(socket.as_raw_fd(), request as _, bridge.as_ptr()) == 0 };
+    if !bridge_ioctl(SIOCBRADDBR) {
+        // The kernel was built without bridging.
+        return;
+    }
+    let device = tap::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    add_to_bridge(device.name(), "test-br").expect("failed to join bridge");
+    let master = std::fs::read_link(format!("/sys/class/net/{}/master", device.name()));
+    if let Ok(master) = master {
+        assert!(master.ends_with("test-br"));
+    }
+    remove_from_bridge(device.name(), "test-br").expect("failed to leave bridge");
+    let error = remove_from_bridge(device.name(), "test-br").unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::EINVAL));
+    let error = add_to_bridge(device.name(), "missing-br").unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::ENODEV));
+    drop(device);
+    assert!(bridge_ioctl(SIOCBRDELBR));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_opens_with_retry() {
+    use std::time::{Duration, Instant};
+
+    let _netns = common::Netns::enter();
+    let (_file, name) = tun::OpenOptions::new()
+        .open_with_retry(10, 3, Duration::from_secs(5))
+        .expect("failed to open device");
+    assert_eq!(name, "tun10");
+
+    // Errors other than a missing device are not retried.
+    let start = Instant::now();
+    let error = tun::OpenOptions::new()
+        .name("a-name-far-too-long")
+        .open_with_retry(None, 3, Duration::from_secs(5))
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn device_reports_effective_tun_flags() {
+    use std::os::unix::io::IntoRawFd;
+    use utuntap::Device;
+
+    const IFF_TUN: libc::c_short = 0x0001;
+    const IFF_TAP: libc::c_short = 0x0002;
+    const IFF_NO_PI: libc::c_short = 0x1000;
+    const IFF_VNET_HDR: libc::c_short = 0x4000;
+
+    let _netns = common::Netns::enter();
+    for packet_info in [false, true] {
+        let device = tun::OpenOptions::new()
+            .packet_info(packet_info)
+            .open_device(10)
+            .expect("failed to open device");
+        let flags = device.tun_flags();
+        assert_eq!(flags & (IFF_TUN | IFF_TAP), IFF_TUN);
+        assert_eq!(flags & IFF_NO_PI == 0, packet_info);
+        assert_eq!(flags & IFF_VNET_HDR, 0);
+
+        let (file, name) = device.into_parts();
+        let adopted = unsafe { Device::from_fd(file.into_raw_fd(), name) };
+        assert_eq!(adopted.tun_flags(), flags);
+    }
+    let device = tap::OpenOptions::new()
+        .vnet_hdr(true)
+        .open_device(10)
+        .expect("failed to open device");
+    let flags = device.tun_flags();
+    assert_eq!(
+        flags & (IFF_TUN | IFF_TAP | IFF_VNET_HDR),
+        IFF_TAP | IFF_VNET_HDR
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_renames_interface() {
+    let _netns = common::Netns::isolate();
+    let device = tun::OpenOptions::new()
+        .open_device(None)
+        .expect("failed to open device");
+    utuntap::interface::rename(device.name(), "renamed-tun").expect("failed to rename");
+    utuntap::interface::get_mtu("renamed-tun").expect("renamed interface is missing");
+    let error = utuntap::interface::get_mtu(device.name()).unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::ENODEV));
+    let error = utuntap::interface::rename("renamed-tun", "a-name-far-too-long").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reports_operstate() {
+    use utuntap::interface::{get_operstate, OperState};
+
+    // The state is read from sysfs, which only shows the host namespace.
+    let device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    // The kernel updates the state shortly after the link changes.
+    let wait_for = |states: &[OperState]| {
+        for _ in 0..200 {
+            let state = get_operstate("tun10").expect("failed to read state");
+            if states.contains(&state) {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("tun10 never became {:?}", states);
+    };
+    device.set_down().expect("failed to bring device down");
+    wait_for(&[OperState::Down]);
+    device.set_up().expect("failed to bring device up");
+    wait_for(&[OperState::Unknown, OperState::Up]);
+    let error = get_operstate("../tun10").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_sets_alias() {
+    use utuntap::interface::{get_alias, set_alias};
+
+    // Aliases live in sysfs, which only shows the host namespace.
+    let _device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    set_alias("tun10", "test uplink").expect("failed to set alias");
+    assert_eq!(
+        get_alias("tun10").expect("failed to read alias").as_deref(),
+        Some("test uplink")
+    );
+    set_alias("tun10", "").expect("failed to clear alias");
+    assert_eq!(get_alias("tun10").expect("failed to read alias"), None);
+    let error = set_alias("tun10", "two\nlines").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reports_stats() {
+    use utuntap::interface::get_stats;
+
+    // Statistics come from sysfs, which only shows the host namespace,
+    // where `tests/setup.sh` gives `tun10` its address.
+    common::require_prepared("tun10");
+    let (mut file, _) = tun::OpenOptions::new()
+        .packet_info(false)
+        .open(10)
+        .expect("failed to open device");
+    // Packets sent before the kernel notices the carrier are dropped.
+    common::wait_until_running("tun10");
+    let before = get_stats("tun10").expect("failed to read stats");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(&[1; 10], "10.10.10.2:4242")
+        .expect("failed to send data");
+    // The kernel counts packets as transmitted once they are read.
+    let mut buffer = [0; 50];
+    file.read(&mut buffer).expect("failed to receive data");
+    let after = get_stats("tun10").expect("failed to read stats");
+    assert!(after.tx_packets > before.tx_packets);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_opens_multiple_queues() {
+    let _netns = common::Netns::isolate();
+    let (queues, name) = tun::OpenOptions::new()
+        .open_multiqueue(10, 3)
+        .expect("failed to open queues");
+    assert_eq!(name, "tun10");
+    let indices: Vec<usize> = queues.iter().map(|&(_, index)| index).collect();
+    assert_eq!(indices, [0, 1, 2]);
+
+    // A single-queue file cannot join a multi-queue device.
+    let error = tun::OpenOptions::new().open(10).unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::EINVAL));
+    let error = tun::OpenOptions::new().open_multiqueue(11, 0).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tap_has_no_ring_params() {
+    use utuntap::interface::{get_ring_params, set_ring_params};
+
+    let _netns = common::Netns::enter();
+    let device = tap::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    let error = get_ring_params(device.name()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+    let error = set_ring_params(device.name(), 256, 256).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+    let error = get_ring_params("an-overly-long-name").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_replaces_qdisc() {
+    use utuntap::interface::{get_qdisc, set_pfifo_limit};
+
+    let netns = common::Netns::isolate();
+    let device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    assert_eq!(
+        get_qdisc(device.name()).expect("failed to read qdisc"),
+        "noop"
+    );
+    netns.configure(device.name());
+    assert_ne!(
+        get_qdisc(device.name()).expect("failed to read qdisc"),
+        "noop"
+    );
+    set_pfifo_limit(device.name(), 4096).expect("failed to set qdisc");
+    assert_eq!(
+        get_qdisc(device.name()).expect("failed to read qdisc"),
+        "pfifo"
+    );
+    let error = get_qdisc("an-overly-long-name").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn interface_existence_is_checked() {
+    use utuntap::interface::interface_exists;
+
+    assert!(interface_exists("lo"));
+    assert!(!interface_exists("missing0"));
+    for name in ["", ".", "..", "../lo"] {
+        assert!(!interface_exists(name), "{}", name);
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_waits_for_interface() {
+    use std::time::{Duration, Instant};
+    use utuntap::interface::wait_for_interface;
+
+    // Interfaces are looked up in sysfs, which only shows the host namespace.
+    let (_file, _) = tun::OpenOptions::new()
+        .open(10)
+        .expect("failed to open device");
+    wait_for_interface("tun10", Duration::from_secs(1)).expect("tun10 did not show up");
+
+    let start = Instant::now();
+    let error = wait_for_interface("tun99", Duration::from_millis(50)).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    let error = wait_for_interface("..", Duration::from_millis(50)).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reports_queue_drops() {
+    use utuntap::interface::get_queue_drops;
+
+    // Statistics come from sysfs, which only shows the host namespace,
+    // where `tests/setup.sh` gives `tun10` its address.
+    common::require_prepared("tun10");
+    let (_file, _) = tun::OpenOptions::new()
+        .packet_info(false)
+        .open(10)
+        .expect("failed to open device");
+    common::wait_until_running("tun10");
+    let before = get_queue_drops("tun10").expect("failed to read drops");
+    // Nothing reads the file, so the queue overflows.
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    for _ in 0..1000 {
+        let _ = socket.send_to(&[1; 10], "10.10.10.2:4242");
+    }
+    let after = get_queue_drops("tun10").expect("failed to read drops");
+    assert!(after > before);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reads_packets_into_uninitialized_buffers() {
+    let netns = common::Netns::enter();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(false)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(&[1; 10], "10.10.10.2:4242")
+        .expect("failed to send data");
+    let error = device.read_packet_uninit(0).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    let packet = device
+        .read_packet_uninit(1500)
+        .expect("failed to receive data");
+    assert_eq!(packet.len(), 38);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reports_busy_device() {
+    let _netns = common::Netns::enter();
+    let _file = tun::OpenOptions::new()
+        .open(10)
+        .expect("failed to open device");
+    let error = tun::OpenOptions::new()
+        .open(10)
+        .expect_err("device in use should not open");
+    assert!(utuntap::is_busy(&error));
+    assert_ne!(error.kind(), ErrorKind::PermissionDenied);
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+#[serial]
+fn tun_opens_any_unit() {
+    let (_file, filename) = tun::OpenOptions::new()
+        .any_unit()
+        .open(None)
+        .expect("failed to open device");
+    assert!(filename.starts_with("utun"));
+    let error = tun::OpenOptions::new()
+        .any_unit()
+        .open(10)
+        .expect_err("a number should not be accepted with any unit");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+#[serial]
+fn tun_reports_taken_unit() {
+    let _file = tun::OpenOptions::new()
+        .open(10)
+        .expect("failed to open device");
+    let error = tun::OpenOptions::new()
+        .open(10)
+        .expect_err("taken unit should not open");
+    assert_eq!(error.kind(), ErrorKind::AddrInUse);
+    assert!(utuntap::is_busy(&error));
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+#[serial]
+fn tun_sets_socket_buffers() {
+    let device = tun::OpenOptions::new()
+        .open_device(None)
+        .expect("failed to open device");
+    tun::set_send_buffer(device.file(), 128 * 1024).expect("failed to set send buffer");
+    tun::set_recv_buffer(device.file(), 128 * 1024).expect("failed to set receive buffer");
+    let error = tun::set_recv_buffer(device.file(), libc::c_int::MAX).unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::ENOBUFS));
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+#[serial]
+fn device_adopts_utun_fd() {
+    use std::net::UdpSocket;
+    use std::os::unix::io::{AsRawFd, IntoRawFd};
+
+    let (file, filename) = tun::OpenOptions::new()
+        .open(10)
+        .expect("failed to open device");
+    let device = unsafe { utuntap::Device::from_utun_fd(file.into_raw_fd()) }
+        .expect("failed to adopt device");
+    assert_eq!(device.name(), filename);
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.connect("127.0.0.1:9").unwrap();
+    let error = unsafe { utuntap::Device::from_utun_fd(socket.as_raw_fd()) }.unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn guard_removes_persistent_interface() {
+    let _netns = common::Netns::enter();
+    let device = tun::OpenOptions::new()
+        .ephemeral()
+        .open_device(None)
+        .expect("failed to open device");
+    let name = device.name().to_string();
+    tun::set_persist(device.file(), true).expect("failed to persist device");
+    drop(utuntap::DeviceGuard::new(device));
+    let error = utuntap::interface::get_mtu(&name).expect_err("interface should be removed");
+    assert_eq!(error.raw_os_error(), Some(libc::ENODEV));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reopens_persistent_device_by_name() {
+    let _netns = common::Netns::enter();
+    let device = tun::OpenOptions::new()
+        .name("persist-test")
+        .open_device(None)
+        .expect("failed to open device");
+    assert_eq!(device.name(), "persist-test");
+    tun::set_persist(device.file(), true).expect("failed to persist device");
+    let name = std::ffi::CString::new("persist-test").unwrap();
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    assert_ne!(index, 0);
+    drop(device);
+
+    let device = tun::OpenOptions::new()
+        .name("persist-test")
+        .open_device(None)
+        .expect("failed to reopen device");
+    assert_eq!(device.name(), "persist-test");
+    let reopened = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    assert_eq!(reopened, index);
+    drop(utuntap::DeviceGuard::new(device));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reports_node_permissions() {
+    let device = tun::OpenOptions::new()
+        .open_device(11)
+        .expect("failed to open device");
+    let node = std::fs::metadata("/dev/net/tun").expect("failed to read node");
+    assert_eq!(device.permissions().unwrap(), node.permissions());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tap_sets_mac_address() {
+    use utuntap::interface::{link_local_v6, set_mac};
+
+    let _netns = common::Netns::isolate();
+    let device = tap::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    device.set_up().expect("failed to bring interface up");
+    set_mac(device.name(), [0x02, 0, 0, 0, 0, 0x01]).expect("failed to set address");
+    let address = link_local_v6(device.name()).expect("failed to derive address");
+    assert_eq!(
+        address,
+        "fe80::ff:fe00:1".parse::<std::net::Ipv6Addr>().unwrap()
+    );
+
+    let error = set_mac(device.name(), [0x01, 0, 0x5e, 0, 0, 0x01]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tap_derives_link_local_address() {
+    use utuntap::interface::{get_addresses, link_local_v6};
+
+    let _netns = common::Netns::isolate();
+    let device = tap::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    let address = link_local_v6(device.name()).expect("failed to derive address");
+    assert_eq!(address.segments()[..4], [0xfe80, 0, 0, 0]);
+    // The kernel generates the same address once the interface is up.
+    device.set_up().expect("failed to bring interface up");
+    let addresses = get_addresses(device.name()).expect("failed to list addresses");
+    assert!(
+        addresses.contains(&(IpAddr::V6(address), 64)),
+        "{:?}",
+        addresses
+    );
+
+    let tun = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    let error = link_local_v6(tun.name()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_opens_or_attaches() {
+    let _netns = common::Netns::isolate();
+    let mut options = tun::OpenOptions::new();
+    let (device, created) = options
+        .open_or_attach("provision0")
+        .expect("failed to create device");
+    assert!(created);
+    assert_eq!(device.name(), "provision0");
+    tun::set_persist(device.file(), true).expect("failed to make device persistent");
+    let error = options.open_or_attach("provision0").unwrap_err();
+    assert!(utuntap::is_busy(&error));
+    drop(device);
+
+    let (device, created) = options
+        .open_or_attach("provision0")
+        .expect("failed to attach to device");
+    assert!(!created);
+    tun::set_persist(device.file(), false).expect("failed to delete device");
+    let error = tap::OpenOptions::new()
+        .open_or_attach("provision0")
+        .unwrap_err();
+    assert!(utuntap::ModeMismatch::from_error(&error).is_some());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_falls_back_from_preferred_name() {
+    let _netns = common::Netns::isolate();
+    let (_first, name) = tun::OpenOptions::new()
+        .preferred_name("preferred0")
+        .open(None)
+        .expect("failed to open device");
+    assert_eq!(name, "preferred0");
+
+    let error = tun::OpenOptions::new()
+        .name("preferred0")
+        .open(None)
+        .unwrap_err();
+    assert!(utuntap::is_busy(&error));
+    let (_second, name) = tun::OpenOptions::new()
+        .preferred_name("preferred0")
+        .open(None)
+        .expect("failed to fall back");
+    assert!(name.starts_with("tun"), "{}", name);
+    let (_third, name) = tap::OpenOptions::new()
+        .preferred_name("lo")
+        .open(None)
+        .expect("failed to fall back");
+    assert!(name.starts_with("tap"), "{}", name);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reports_mode_mismatch() {
+    use utuntap::{Mode, ModeMismatch};
+
+    let _netns = common::Netns::enter();
+    let device = tap::OpenOptions::new()
+        .name("mismatch-test")
+        .open_device(None)
+        .expect("failed to open device");
+    tun::set_persist(device.file(), true).expect("failed to persist device");
+    let guard = utuntap::DeviceGuard::new(device);
+
+    let error = tun::OpenOptions::new()
+        .name("mismatch-test")
+        .open(None)
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+    let mismatch = ModeMismatch::from_error(&error).expect("no mode mismatch");
+    assert_eq!(mismatch.name, "mismatch-test");
+    assert_eq!(mismatch.requested, Mode::Tun);
+    assert_eq!(mismatch.existing, Mode::Tap);
+    drop(guard);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_exclusive_fails_on_existing_device() {
+    let _netns = common::Netns::enter();
+    let device = tun::OpenOptions::new()
+        .ephemeral()
+        .open_device(None)
+        .expect("failed to open device");
+    let name = device.name().to_string();
+    tun::set_persist(device.file(), true).expect("failed to persist device");
+    drop(device);
+
+    let error = tun::OpenOptions::new()
+        .name(&name)
+        .exclusive(true)
+        .open(None)
+        .expect_err("existing device should not open");
+    assert!(utuntap::is_busy(&error));
+
+    let device = tun::OpenOptions::new()
+        .name(&name)
+        .open_device(None)
+        .expect("failed to reopen device");
+    drop(utuntap::DeviceGuard::new(device));
+}
+
+#[cfg(all(target_os = "linux", feature = "async-io"))]
+#[test]
+#[serial]
+fn tun_reads_packets_asynchronously() {
+    use futures_lite::AsyncReadExt;
+
+    let netns = common::Netns::enter();
+    async_io::block_on(async {
+        let mut device = tun::OpenOptions::new()
+            .packet_info(false)
+            .open_async(10)
+            .expect("failed to open device");
+        netns.configure("tun10");
+        let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+        socket
+            .send_to(&[1; 10], "10.10.10.2:4242")
+            .expect("failed to send data");
+        let mut buffer = [0; 50];
+        let number = device
+            .read(&mut buffer)
+            .await
+            .expect("failed to receive data");
+        assert_eq!(number, 38);
+    });
+}
+
+#[cfg(all(target_os = "linux", feature = "async-io"))]
+#[test]
+#[serial]
+fn tun_opens_split_halves_asynchronously() {
+    use futures_lite::{AsyncReadExt, AsyncWriteExt};
+
+    let netns = common::Netns::enter();
+    async_io::block_on(async {
+        let (mut reader, mut writer, name) = tun::OpenOptions::new()
+            .packet_info(false)
+            .open_async_split(10)
+            .expect("failed to open device");
+        assert_eq!(name, "tun10");
+        netns.configure("tun10");
+        let packet = [
+            0x45, 0, 0, 20, 0, 0, 0, 0, 64, 17, 0, 0, 10, 10, 10, 2, 10, 10, 10, 1,
+        ];
+        let number = writer.write(&packet).await.expect("failed to write");
+        assert_eq!(number, 20);
+        // The reading half keeps its own descriptor.
+        drop(writer);
+        let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+        socket
+            .send_to(&[1; 10], "10.10.10.2:4242")
+            .expect("failed to send data");
+        let mut buffer = [0; 50];
+        let number = reader
+            .read(&mut buffer)
+            .await
+            .expect("failed to receive data");
+        assert_eq!(number, 38);
+    });
+}
+
+#[cfg(all(target_os = "linux", feature = "async-io"))]
+#[test]
+#[serial]
+fn tun_writes_packets_asynchronously() {
+    let netns = common::Netns::enter();
+    async_io::block_on(async {
+        let mut device = tun::OpenOptions::new()
+            .packet_info(false)
+            .open_async(10)
+            .expect("failed to open device");
+        netns.configure("tun10");
+        let packet = [
+            0x45, 0, 0, 20, 0, 0, 0, 0, 64, 17, 0, 0, 10, 10, 10, 2, 10, 10, 10, 1,
+        ];
+        let packets = vec![packet.to_vec(); 3];
+        let mut written = 0;
+        device
+            .write_all_packets(&packets, &mut written)
+            .await
+            .expect("failed to write packets");
+        assert_eq!(written, 3);
+        device
+            .write_all_packets(&packets, &mut written)
+            .await
+            .expect("failed to write packets");
+        assert_eq!(written, 3);
+    });
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reads_packets_with_address_family() {
+    let netns = common::Netns::enter();
+    for packet_info in [false, true] {
+        let mut device = tun::OpenOptions::new()
+            .packet_info(packet_info)
+            .open_device(10)
+            .expect("failed to open device");
+        netns.configure("tun10");
+        let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+        socket
+            .send_to(&[1; 10], "10.10.10.2:4242")
+            .expect("failed to send data");
+        let (family, packet) = device.read_packet(1500).expect("failed to receive data");
+        assert_eq!(family, utuntap::AddressFamily::Inet);
+        assert_eq!(packet.len(), 38);
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_opens_owned_device() {
+    let _netns = common::Netns::enter();
+    let mut options = tun::OpenOptions::new();
+    options.packet_info(false);
+    let device = options.open_owned(10).expect("failed to open device");
+    assert_eq!(device.name(), "tun10");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn device_reports_its_mode() {
+    let _netns = common::Netns::enter();
+    let device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    assert_eq!(
+        device.mode().expect("failed to query mode"),
+        utuntap::Mode::Tun
+    );
+    let device = tap::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    assert_eq!(
+        device.mode().expect("failed to query mode"),
+        utuntap::Mode::Tap
+    );
+}
+
+#[cfg(all(target_os = "linux", feature = "etherparse"))]
+#[test]
+#[serial]
+fn tap_writes_built_frames() {
+    let _netns = common::Netns::enter();
+    for packet_info in [false, true] {
+        let mut device = tap::OpenOptions::new()
+            .packet_info(packet_info)
+            .open_device(10)
+            .expect("failed to open device");
+        // The kernel rejects writes to an interface which is down.
+        device.set_up().expect("failed to bring interface up");
+        let builder = PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12])
+            .ipv4([10, 10, 10, 1], [10, 10, 10, 2], 64)
+            .udp(2424, 4242);
+        let number = device
+            .write_frame(|buffer| builder.write(buffer, &[1; 10]))
+            .expect("failed to write frame");
+        assert_eq!(number, 52);
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "etherparse"))]
+#[test]
+#[serial]
+fn tap_reads_parsed_frames() {
+    let netns = common::Netns::isolate();
+    let mut buffer = Vec::new();
+    for packet_info in [false, true] {
+        let mut device = tap::OpenOptions::new()
+            .packet_info(packet_info)
+            .open_device(10)
+            .expect("failed to open device");
+        netns.configure("tap10");
+        // A broadcast needs no ARP request before it.
+        let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+        socket
+            .set_broadcast(true)
+            .expect("failed to allow broadcast");
+        socket
+            .send_to(&[1; 10], "10.10.10.255:4242")
+            .expect("failed to send data");
+        let headers = device
+            .read_frame(&mut buffer)
+            .expect("failed to read frame");
+        let link = headers.link.expect("missing Ethernet header");
+        assert_eq!(link.destination, [0xff; 6]);
+        assert_eq!(link.ether_type, 0x0800);
+        match headers.transport {
+            Some(TransportHeader::Udp(udp_header)) => {
+                assert_eq!(udp_header.source_port, 2424);
+                assert_eq!(udp_header.destination_port, 4242);
+            }
+            transport => panic!("unexpected transport {:?}", transport),
+        }
+        assert_eq!(headers.payload, [1; 10]);
+        assert_eq!(buffer.len(), 52);
+    }
+}
+
+#[cfg(all(target_os = "macos", not(feature = "macos-feth")))]
+#[test]
+fn tap_is_unsupported() {
+    assert!(!utuntap::TAP_SUPPORTED);
+    let error = tap::OpenOptions::new()
+        .open(10)
+        .expect_err("tap should not open");
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+}
+
+#[cfg(all(target_os = "macos", feature = "macos-feth"))]
+#[test]
+#[serial]
+fn tap_moves_frames_between_feth_peers() {
+    use std::process::Command;
+    use std::time::Duration;
+    use utuntap::interface::interface_exists;
+
+    let mut sender = tap::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    let mut receiver = tap::OpenOptions::new()
+        .open_device(11)
+        .expect("failed to open device");
+    let status = Command::new("ifconfig")
+        .args(["feth11", "peer", "feth10"])
+        .status()
+        .expect("failed to run ifconfig");
+    assert!(status.success());
+    sender.set_up().expect("failed to bring device up");
+    receiver.set_up().expect("failed to bring device up");
+    receiver
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("failed to set read timeout");
+
+    let builder = PacketBuilder::ethernet2([2, 0, 0, 0, 0, 10], [2, 0, 0, 0, 0, 11])
+        .ipv4([10, 10, 10, 1], [10, 10, 10, 2], 20)
+        .udp(2424, 4242);
+    let mut frame = Vec::new();
+    builder
+        .write(&mut frame, &[1; 10])
+        .expect("failed to build frame");
+    assert_eq!(
+        sender.write(&frame).expect("failed to send frame"),
+        frame.len()
+    );
+
+    // The host may send frames of its own, e.g. IPv6 neighbor discovery.
+    let mut buffer = vec![0; receiver.recommended_buffer_size().unwrap()];
+    loop {
+        let number = receiver.read(&mut buffer).expect("failed to receive frame");
+        if buffer[..number] == frame[..] {
+            break;
+        }
+    }
+
+    drop(sender);
+    drop(receiver);
+    assert!(!interface_exists("feth10"));
+    assert!(!interface_exists("feth11"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn tap_is_supported() {
+    assert!(utuntap::TAP_SUPPORTED);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_opens_custom_device_path() {
+    use std::path::Path;
+    use utuntap::DeviceSource;
+
+    let _netns = common::Netns::enter();
+    let path = std::env::temp_dir().join(format!("utuntap-tun-{}", std::process::id()));
+    std::os::unix::fs::symlink("/dev/net/tun", &path).expect("failed to link node");
+    let result = tun::OpenOptions::new().device_path(&path).open_device(10);
+    std::fs::remove_file(&path).expect("failed to remove link");
+    let device = result.expect("failed to open device");
+    assert_eq!(device.source(), &DeviceSource::Node(path));
+    drop(device);
+
+    let error = tun::OpenOptions::new()
+        .device_path(Path::new("/dev/net/missing"))
+        .open_device(10)
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn device_reports_source() {
+    use utuntap::DeviceSource;
+
+    let _netns = common::Netns::enter();
+    let device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    assert_eq!(device.source(), &DeviceSource::Node("/dev/net/tun".into()));
+    let (file, filename) = device.into_parts();
+    let device = utuntap::Device::from_owned_fd(file.into(), filename);
+    assert_eq!(device.source(), &DeviceSource::Adopted);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn device_closes_with_errors_reported() {
+    use std::os::unix::io::AsRawFd;
+
+    let device = tun::OpenOptions::new()
+        .open_device(11)
+        .expect("failed to open device");
+    let fd = device.as_raw_fd();
+    device.close().expect("failed to close device");
+    assert_eq!(unsafe { libc::fcntl(fd, libc::F_GETFD) }, -1);
+    assert_eq!(
+        std::io::Error::last_os_error().raw_os_error(),
+        Some(libc::EBADF)
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn device_converts_into_parts() {
+    use std::os::unix::io::AsRawFd;
+
+    let _netns = common::Netns::enter();
+    let device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    let fd = device.as_raw_fd();
+    let (file, filename) = device.into_parts();
+    assert_eq!(file.as_raw_fd(), fd);
+    assert_eq!(filename, "tun10");
+    let device = utuntap::Device::from_owned_fd(file.into(), filename);
+    let (file, filename): (std::fs::File, String) = device.into();
+    assert_eq!(file.as_raw_fd(), fd);
+    assert_eq!(filename, "tun10");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_adopts_file_descriptors() {
+    let netns = common::Netns::enter();
+    let (file, filename) = tun::OpenOptions::new()
+        .packet_info(false)
+        .open(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let mut device = utuntap::Device::from_owned_fd(file.into(), filename);
+    assert_eq!(device.name(), "tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(&[1; 10], "10.10.10.2:4242")
+        .expect("failed to send data");
+    let mut buffer = [0; 50];
+    let number = device.read(&mut buffer).expect("failed to receive data");
+    assert_eq!(number, 38);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_borrows_file_descriptors() {
+    use std::os::unix::io::AsFd;
+    use utuntap::{BorrowedDevice, Mode};
+
+    let netns = common::Netns::enter();
+    let (file, filename) = tun::OpenOptions::new()
+        .packet_info(false)
+        .open(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    {
+        let mut device = BorrowedDevice::new(file.as_fd(), filename);
+        assert_eq!(device.mode().expect("failed to read mode"), Mode::Tun);
+        assert_eq!(device.mtu().expect("failed to read mtu"), 1500);
+        let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+        socket
+            .send_to(&[1; 10], "10.10.10.2:4242")
+            .expect("failed to send data");
+        let mut buffer = [0; 50];
+        let number = device.read(&mut buffer).expect("failed to receive data");
+        assert_eq!(number, 38);
+    }
+    // The descriptor is still open once the borrow ends.
+    let device = utuntap::Device::from_owned_fd(file.into(), "tun10".to_string());
+    assert_eq!(device.mode().expect("failed to read mode"), Mode::Tun);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_sends_devices_over_unix_sockets() {
+    use std::os::unix::net::UnixStream;
+
+    let netns = common::Netns::enter();
+    let (sender, receiver) = UnixStream::pair().expect("failed to create sockets");
+    {
+        let device = tun::OpenOptions::new()
+            .packet_info(false)
+            .open_device(10)
+            .expect("failed to open device");
+        device.send_over(&sender).expect("failed to send device");
+    }
+    let mut device = utuntap::Device::recv_over(&receiver, "tun10".to_string())
+        .expect("failed to receive device");
+    netns.configure("tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(&[1; 10], "10.10.10.2:4242")
+        .expect("failed to send data");
+    let mut buffer = [0; 50];
+    let number = device.read(&mut buffer).expect("failed to receive data");
+    assert_eq!(number, 38);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reads_and_writes_vnet_headers() {
+    use utuntap::vnet::{VnetHdr, VIRTIO_NET_HDR_GSO_NONE};
+
+    let netns = common::Netns::enter();
+    for packet_info in [false, true] {
+        let mut device = tun::OpenOptions::new()
+            .packet_info(packet_info)
+            .vnet_hdr(true)
+            .open_device(10)
+            .expect("failed to open device");
+        netns.configure("tun10");
+        let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+        socket
+            .send_to(&[1; 10], "10.10.10.2:4242")
+            .expect("failed to send data");
+        let (hdr, packet) = device.read_vnet().expect("failed to receive data");
+        assert_eq!(hdr.gso_type, VIRTIO_NET_HDR_GSO_NONE);
+        assert_eq!(packet.len(), 38);
+        assert_eq!(packet[0] >> 4, 4);
+        let written = device
+            .write_vnet(&VnetHdr::default(), &packet)
+            .expect("failed to send data");
+        assert_eq!(written, packet.len());
+    }
+
+    let mut device = tun::OpenOptions::new()
+        .open_device(10)
+        .expect("failed to open device");
+    let error = device
+        .read_vnet()
+        .expect_err("a device without header should not read");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_negotiates_offload() {
+    use utuntap::tun::Offload;
+
+    let _netns = common::Netns::enter();
+    let device = tun::OpenOptions::new()
+        .offload(Offload::CSUM | Offload::TSO4)
+        .open_device(10)
+        .expect("failed to open device");
+    assert!(device.offload().contains(Offload::CSUM));
+    let offload = tun::get_offload(device.file()).expect("failed to read offload");
+    assert_eq!(offload, device.offload());
+    let size = tun::get_vnet_hdr_size(device.file()).expect("failed to read header size");
+    assert_eq!(size, 10);
+
+    let error = tun::OpenOptions::new()
+        .vnet_hdr(false)
+        .offload(Offload::CSUM)
+        .open(10)
+        .expect_err("offload without vnet_hdr should fail");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn tun_reads_into_borrowed_buffer() {
+    let error = tun::OpenOptions::new()
+        .buffer_capacity(0)
+        .open_device(10)
+        .expect_err("opened device with an empty buffer");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+    let netns = common::Netns::isolate();
+    let mut device = tun::OpenOptions::new()
+        .packet_info(false)
+        .buffer_capacity(24)
+        .open_device(10)
+        .expect("failed to open device");
+    netns.configure("tun10");
+    let socket = UdpSocket::bind("10.10.10.1:2424").expect("failed to bind to address");
+    socket
+        .send_to(b"ping", "10.10.10.2:4242")
+        .expect("failed to send data");
+    socket
+        .send_to(b"pong", "10.10.10.2:4242")
+        .expect("failed to send data");
+
+    // The 32 bytes of each packet are truncated to the 24 of the buffer.
+    let packet = device.read_frame_borrowed().expect("failed to read packet");
+    assert_eq!(packet.len(), 24);
+    assert_eq!(packet[0] >> 4, 4);
+    assert_eq!(&packet[20..22], &2424u16.to_be_bytes());
+    let packet = device.read_frame_borrowed().expect("failed to read packet");
+    assert_eq!(packet.len(), 24);
+    assert_eq!(&packet[22..24], &4242u16.to_be_bytes());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn capabilities_match_the_driver() {
+    let capabilities = utuntap::capabilities();
+    if !std::path::Path::new("/dev/net/tun").exists() {
+        assert!(!capabilities.tun);
+        return;
+    }
+    assert!(capabilities.tun);
+    assert!(capabilities.tap);
+    assert!(capabilities.packet_info);
+    assert!(capabilities.persist);
+    assert!(capabilities.set_mtu);
+    assert_eq!(capabilities.offload, capabilities.vnet_hdr);
+
+    let _netns = common::Netns::enter();
+    let queues = tun::OpenOptions::new().open_multiqueue(10, 2);
+    assert_eq!(capabilities.multi_queue, queues.is_ok());
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "mock")]
+
+use std::io::{ErrorKind, Read, Write};
+use utuntap::MockDevice;
+
+#[test]
+fn mock_reads_queued_packets_one_per_call() {
+    let mut device = MockDevice::new("tun0");
+    device.push_read(vec![1; 10]);
+    device.push_read(vec![2; 30]);
+    let mut buffer = [0; 20];
+    assert_eq!(device.read(&mut buffer).unwrap(), 10);
+    // A short buffer gets the start of the packet, the rest is dropped.
+    assert_eq!(device.read(&mut buffer).unwrap(), 20);
+    assert_eq!(buffer, [2; 20]);
+    let error = device.read(&mut buffer).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+}
+
+#[test]
+fn mock_keeps_written_packets() {
+    let mut device = MockDevice::new("tap0");
+    assert_eq!(device.write(&[1; 14]).unwrap(), 14);
+    assert_eq!(device.write(&[2; 60]).unwrap(), 60);
+    assert_eq!(device.pop_written(), Some(vec![1; 14]));
+    assert_eq!(device.pop_written(), Some(vec![2; 60]));
+    assert_eq!(device.pop_written(), None);
+    let error = device.read(&mut [0; 100]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+}
+
+#[test]
+fn loopback_mock_reads_written_packets() {
+    let mut device = MockDevice::loopback("tun0");
+    assert_eq!(device.write(&[1; 20]).unwrap(), 20);
+    let mut buffer = [0; 1500];
+    assert_eq!(device.read(&mut buffer).unwrap(), 20);
+}
+
+#[test]
+fn mock_interface_state_is_canned() {
+    let mut device = MockDevice::new("tun0");
+    assert_eq!(device.name(), "tun0");
+    assert_eq!(device.mtu().unwrap(), 1500);
+    assert!(!device.is_up());
+    device.set_mtu(1280).unwrap();
+    device.set_up().unwrap();
+    assert_eq!(device.mtu().unwrap(), 1280);
+    assert!(device.is_up());
+}
+
+#[test]
+fn mock_stands_in_for_a_device() {
+    use utuntap::TunDevice;
+
+    fn echo(device: &mut dyn TunDevice) -> std::io::Result<usize> {
+        device.set_up()?;
+        let mut buffer = vec![0; device.mtu()? as usize];
+        let number = device.recv_frame(&mut buffer)?;
+        device.send_frame(&buffer[..number])
+    }
+
+    let mut mock = MockDevice::new("tun0");
+    mock.push_read(vec![0x45; 20]);
+    let mut devices: Vec<Box<dyn TunDevice>> = vec![Box::new(mock)];
+    assert_eq!(devices[0].name(), "tun0");
+    assert_eq!(echo(devices[0].as_mut()).unwrap(), 20);
+}
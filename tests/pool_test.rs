@@ -0,0 +1,42 @@
+use std::os::unix::io::OwnedFd;
+use std::os::unix::net::UnixDatagram;
+use utuntap::{Device, PooledReader};
+
+/// Returns a device reading the datagrams sent on the returned socket, which
+/// keep their boundaries as packets do.
+fn device() -> (PooledReader, UnixDatagram) {
+    let (inside, outside) = UnixDatagram::pair().unwrap();
+    let device = Device::from_owned_fd(OwnedFd::from(inside), "tun0".into());
+    (PooledReader::with_buffer_size(device, 100, 2), outside)
+}
+
+#[test]
+fn pooled_reader_reads_one_packet_per_frame() {
+    let (mut reader, socket) = device();
+    socket.send(&[1; 10]).unwrap();
+    socket.send(&[2; 150]).unwrap();
+    let frame = reader.read().unwrap();
+    assert_eq!(&*frame, &[1; 10]);
+    // A packet longer than the buffers is truncated.
+    let frame = reader.read().unwrap();
+    assert_eq!(&*frame, &[2; 100][..]);
+}
+
+#[test]
+fn pooled_reader_recycles_dropped_frames() {
+    let (mut reader, socket) = device();
+    assert_eq!(reader.free_buffers(), 0);
+    for _ in 0..3 {
+        socket.send(&[1; 10]).unwrap();
+    }
+    let frames: Vec<_> = (0..3).map(|_| reader.read().unwrap()).collect();
+    drop(frames);
+    // The pool keeps as many buffers as its capacity.
+    assert_eq!(reader.free_buffers(), 2);
+
+    socket.send(&[3; 20]).unwrap();
+    let frame = reader.read().unwrap();
+    assert_eq!(reader.free_buffers(), 1);
+    assert_eq!(frame.into_vec(), vec![3; 20]);
+    assert_eq!(reader.free_buffers(), 1);
+}
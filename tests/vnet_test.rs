@@ -0,0 +1,121 @@
+use utuntap::vnet::{
+    segment, Endianness, VnetHdr, VIRTIO_NET_HDR_F_NEEDS_CSUM, VIRTIO_NET_HDR_GSO_TCPV4,
+    VIRTIO_NET_HDR_GSO_UDP_L4,
+};
+
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|chunk| u32::from(chunk[0]) << 8 | u32::from(*chunk.get(1).unwrap_or(&0)))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn transport_checksum(packet: &[u8], protocol: u8) -> u16 {
+    let mut pseudo = Vec::new();
+    pseudo.extend_from_slice(&packet[12..20]);
+    pseudo.extend_from_slice(&[0, protocol]);
+    pseudo.extend_from_slice(&((packet.len() - 20) as u16).to_be_bytes());
+    pseudo.extend_from_slice(&packet[20..]);
+    checksum(&pseudo)
+}
+
+fn ipv4_header(protocol: u8, payload_length: usize) -> Vec<u8> {
+    let mut header = vec![
+        0x45, 0, 0, 0, 0x12, 0x34, 0x40, 0, 64, protocol, 0, 0, 10, 10, 10, 1, 10, 10, 10, 2,
+    ];
+    header[2..4].copy_from_slice(&((20 + payload_length) as u16).to_be_bytes());
+    header
+}
+
+#[test]
+fn segments_tcp_over_ipv4() {
+    let data: Vec<u8> = (0..2500).map(|byte| byte as u8).collect();
+    let mut packet = ipv4_header(6, 20 + data.len());
+    packet.extend_from_slice(&[
+        0x09, 0x78, 0x10, 0x92, 0, 0, 0x03, 0xe8, 0, 0, 0, 0, 0x50, 0x19, 0xff, 0xff, 0, 0, 0, 0,
+    ]);
+    packet.extend_from_slice(&data);
+    let hdr = VnetHdr {
+        flags: VIRTIO_NET_HDR_F_NEEDS_CSUM,
+        gso_type: VIRTIO_NET_HDR_GSO_TCPV4,
+        hdr_len: 40,
+        gso_size: 1000,
+        csum_start: 20,
+        csum_offset: 16,
+    };
+
+    let segments = segment(&hdr, &packet).expect("failed to segment");
+
+    assert_eq!(segments.len(), 3);
+    for (index, segment) in segments.iter().enumerate() {
+        let length = [1000, 1000, 500][index];
+        assert_eq!(segment.len(), 40 + length);
+        assert_eq!(&segment[2..4], &((40 + length) as u16).to_be_bytes());
+        assert_eq!(&segment[4..6], &(0x1234 + index as u16).to_be_bytes());
+        assert_eq!(checksum(&segment[..20]), 0);
+        assert_eq!(
+            &segment[24..28],
+            &(1000 + 1000 * index as u32).to_be_bytes()
+        );
+        assert_eq!(transport_checksum(segment, 6), 0);
+        assert_eq!(&segment[40..], &data[1000 * index..1000 * index + length]);
+    }
+    // FIN and PSH are only kept on the last segment.
+    assert_eq!(segments[0][33], 0x10);
+    assert_eq!(segments[1][33], 0x10);
+    assert_eq!(segments[2][33], 0x19);
+}
+
+#[test]
+fn segments_udp_over_ipv4() {
+    let data = [7u8; 1200];
+    let mut packet = ipv4_header(17, 8 + data.len());
+    packet.extend_from_slice(&[0x09, 0x78, 0x10, 0x92, 0, 0, 0, 0]);
+    packet[24..26].copy_from_slice(&((8 + data.len()) as u16).to_be_bytes());
+    packet.extend_from_slice(&data);
+    let hdr = VnetHdr {
+        flags: VIRTIO_NET_HDR_F_NEEDS_CSUM,
+        gso_type: VIRTIO_NET_HDR_GSO_UDP_L4,
+        hdr_len: 28,
+        gso_size: 500,
+        csum_start: 20,
+        csum_offset: 6,
+    };
+
+    let segments = segment(&hdr, &packet).expect("failed to segment");
+
+    assert_eq!(segments.len(), 3);
+    for (index, segment) in segments.iter().enumerate() {
+        let length = [500, 500, 200][index];
+        assert_eq!(segment.len(), 28 + length);
+        assert_eq!(&segment[2..4], &((28 + length) as u16).to_be_bytes());
+        assert_eq!(checksum(&segment[..20]), 0);
+        assert_eq!(&segment[24..26], &((8 + length) as u16).to_be_bytes());
+        assert_eq!(transport_checksum(segment, 17), 0);
+    }
+}
+
+#[test]
+fn parses_and_serializes_headers() {
+    let hdr = VnetHdr {
+        flags: VIRTIO_NET_HDR_F_NEEDS_CSUM,
+        gso_type: VIRTIO_NET_HDR_GSO_TCPV4,
+        hdr_len: 54,
+        gso_size: 1448,
+        csum_start: 34,
+        csum_offset: 16,
+    };
+    for endianness in [Endianness::Native, Endianness::Little, Endianness::Big] {
+        assert_eq!(
+            VnetHdr::parse(&hdr.to_bytes(endianness), endianness),
+            Some(hdr)
+        );
+    }
+    assert_eq!(&hdr.to_bytes(Endianness::Little)[4..6], &[0xa8, 0x05]);
+    assert_eq!(&hdr.to_bytes(Endianness::Big)[4..6], &[0x05, 0xa8]);
+    assert_eq!(VnetHdr::parse(&[0; 4], Endianness::Native), None);
+}